@@ -0,0 +1,398 @@
+use crate::math;
+use crate::quote::QuoteEngine;
+use crate::router::RouteGraph;
+use crate::types::{MarketContext, PoolInfo, Result, RouteHop};
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+/// A profitable cycle starting and ending at WETH
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArbitrageOpportunity {
+    /// Tokens visited by the cycle (first and last are WETH)
+    pub tokens: Vec<Address>,
+
+    /// Hops with the exact pools and amounts
+    pub hops: Vec<RouteHop>,
+
+    /// Optimal WETH input
+    pub amount_in: U256,
+
+    /// WETH returned at the end of the cycle
+    pub amount_out: U256,
+
+    /// amount_out - amount_in, before gas
+    pub gross_profit: U256,
+
+    /// Total gas estimate for the cycle
+    pub gas_estimate: U256,
+
+    /// Gas cost in ETH at the context gas price
+    pub gas_cost_eth: f64,
+
+    /// Profit in ETH net of gas
+    pub profit_eth: f64,
+
+    /// Cycle description
+    pub description: String,
+}
+
+/// Finds arbitrage cycles using log-price negative-cycle detection
+pub struct ArbitrageFinder {
+    weth: Address,
+    max_hops: usize,
+    min_profit_eth: f64,
+}
+
+impl ArbitrageFinder {
+    /// Create a new finder anchored on WETH
+    pub fn new(weth: Address, max_hops: usize) -> Self {
+        Self {
+            weth,
+            max_hops: max_hops.min(4), // Same cap as the router
+            min_profit_eth: 0.0,
+        }
+    }
+
+    /// Only report cycles whose net profit exceeds this many ETH
+    pub fn with_min_profit(mut self, min_profit_eth: f64) -> Self {
+        self.min_profit_eth = min_profit_eth;
+        self
+    }
+
+    /// Find profitable cycles, ranked by net profit in ETH (best first)
+    pub fn find_opportunities(
+        &self,
+        pools: &[PoolInfo],
+        context: &MarketContext,
+    ) -> Result<Vec<ArbitrageOpportunity>> {
+        let graph = RouteGraph::new(pools);
+
+        let cycles = self.find_negative_cycles(&graph);
+        info!("Found {} candidate cycles through WETH", cycles.len());
+
+        let mut opportunities = Vec::new();
+
+        for cycle in cycles {
+            match self.evaluate_cycle(&cycle, &graph, context) {
+                Ok(Some(opportunity)) => opportunities.push(opportunity),
+                Ok(None) => {}
+                Err(e) => debug!("Failed to evaluate cycle: {}", e),
+            }
+        }
+
+        // Sort by net profit (best first)
+        opportunities.sort_by(|a, b| b.profit_eth.total_cmp(&a.profit_eth));
+
+        Ok(opportunities)
+    }
+
+    /// Bounded depth-first search from WETH over -ln(rate) edge weights
+    /// Walks every simple path of up to `max_hops` hops and reports each closing edge back to
+    /// WETH whose total weight is negative. The search is exhaustive, so its cost grows with
+    /// the pool count raised to `max_hops`
+    fn find_negative_cycles(&self, graph: &RouteGraph) -> Vec<Cycle> {
+        let mut cycles = Vec::new();
+        let mut path = Cycle {
+            tokens: vec![self.weth],
+            pools: vec![],
+            weight: 0.0,
+        };
+
+        self.extend_path(graph, &mut path, &mut cycles);
+        cycles
+    }
+
+    /// Extend `path` by one hop in every possible direction, recording closed cycles
+    fn extend_path(&self, graph: &RouteGraph, path: &mut Cycle, cycles: &mut Vec<Cycle>) {
+        let token = *path.tokens.last().expect("path starts at WETH");
+        let Some(connections) = graph.adjacency.get(&token) else {
+            return;
+        };
+
+        for (pool_addr, next_token) in connections {
+            if path.pools.contains(pool_addr) {
+                continue;
+            }
+
+            let Some(weight) = graph
+                .pools
+                .get(pool_addr)
+                .and_then(|pool| Self::edge_weight(pool, &token))
+            else {
+                continue;
+            };
+            let total = path.weight + weight;
+
+            if *next_token == self.weth {
+                // Closing edge: a negative total weight means the product of rates exceeds 1
+                if !path.pools.is_empty() && total < 0.0 {
+                    let mut cycle = path.clone();
+                    cycle.tokens.push(*next_token);
+                    cycle.pools.push(*pool_addr);
+                    cycle.weight = total;
+                    cycles.push(cycle);
+                }
+                continue;
+            }
+
+            // Keep cycles simple and leave room for the closing hop
+            if path.tokens.contains(next_token) || path.pools.len() + 1 >= self.max_hops {
+                continue;
+            }
+
+            let previous = path.weight;
+            path.tokens.push(*next_token);
+            path.pools.push(*pool_addr);
+            path.weight = total;
+
+            self.extend_path(graph, path, cycles);
+
+            path.tokens.pop();
+            path.pools.pop();
+            path.weight = previous;
+        }
+    }
+
+    /// Edge weight -ln(fee_factor * reserve_out / reserve_in) for a swap out of `token_in`
+    fn edge_weight(pool: &PoolInfo, token_in: &Address) -> Option<f64> {
+        let (reserve_in, reserve_out) = pool.get_reserves(token_in)?;
+        if reserve_in.is_zero() || reserve_out.is_zero() {
+            return None;
+        }
+
+        let fee_factor = Self::fee_factor(pool);
+        let rate = fee_factor * math::to_f64(reserve_out) / math::to_f64(reserve_in);
+        Some(-rate.ln())
+    }
+
+    fn fee_factor(pool: &PoolInfo) -> f64 {
+        (10000.0 - pool.fee_bps as f64) / 10000.0
+    }
+
+    /// Size and price a cycle with exact V2 math, returning it if profitable net of gas
+    fn evaluate_cycle(
+        &self,
+        cycle: &Cycle,
+        graph: &RouteGraph,
+        context: &MarketContext,
+    ) -> Result<Option<ArbitrageOpportunity>> {
+        let route_pools: Vec<PoolInfo> = cycle
+            .pools
+            .iter()
+            .filter_map(|addr| graph.pools.get(addr).cloned())
+            .collect();
+
+        let amount_in = match Self::optimal_input(&route_pools, &cycle.tokens) {
+            Some(amount) => amount,
+            None => return Ok(None),
+        };
+
+        let hops = QuoteEngine::calculate_route_output(&route_pools, &cycle.tokens, amount_in)?;
+        let amount_out = hops.last().map(|h| h.amount_out).unwrap_or_default();
+
+        if amount_out <= amount_in {
+            return Ok(None);
+        }
+
+        let gross_profit = amount_out - amount_in;
+        let gas_estimate = hops
+            .iter()
+            .map(|h| h.gas_estimate)
+            .fold(U256::zero(), |acc, g| acc + g);
+        let gas_cost_eth = math::to_f64(gas_estimate) * math::to_f64(context.gas_price_wei) / 1e18;
+        let profit_eth = math::to_f64(gross_profit) / 1e18 - gas_cost_eth;
+
+        if profit_eth <= self.min_profit_eth {
+            return Ok(None);
+        }
+
+        let description = cycle
+            .tokens
+            .iter()
+            .map(|t| format!("{:?}", t))
+            .collect::<Vec<_>>()
+            .join(" → ");
+
+        Ok(Some(ArbitrageOpportunity {
+            tokens: cycle.tokens.clone(),
+            hops,
+            amount_in,
+            amount_out,
+            gross_profit,
+            gas_estimate,
+            gas_cost_eth,
+            profit_eth,
+            description,
+        }))
+    }
+
+    /// Optimal input for a chain of V2 pools
+    /// The chain is collapsed into one virtual pool (Ea, Eb), for which
+    /// the profit-maximizing input is (sqrt(Ea * Eb * γ) - Ea) / γ
+    fn optimal_input(pools: &[PoolInfo], tokens: &[Address]) -> Option<U256> {
+        let first = pools.first()?;
+        let gamma = Self::fee_factor(first);
+        let (reserve_in, reserve_out) = first.get_reserves(&tokens[0])?;
+        let mut ea = math::to_f64(reserve_in);
+        let mut eb = math::to_f64(reserve_out);
+
+        for (i, pool) in pools.iter().enumerate().skip(1) {
+            let (next_in, next_out) = pool.get_reserves(&tokens[i])?;
+            let next_in = math::to_f64(next_in);
+            let next_out = math::to_f64(next_out);
+            let next_gamma = Self::fee_factor(pool);
+
+            let denominator = next_in + next_gamma * eb;
+            ea = ea * next_in / denominator;
+            eb = next_gamma * eb * next_out / denominator;
+        }
+
+        let optimal = ((ea * eb * gamma).sqrt() - ea) / gamma;
+        if !optimal.is_finite() || optimal < 1.0 {
+            return None;
+        }
+
+        Some(U256::from(optimal.min(u128::MAX as f64) as u128))
+    }
+}
+
+/// A candidate cycle through the token graph
+#[derive(Debug, Clone)]
+struct Cycle {
+    tokens: Vec<Address>,
+    pools: Vec<Address>,
+    weight: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const E18: u128 = 1_000_000_000_000_000_000;
+
+    fn pool(address: u64, token0: u64, token1: u64, reserve0: u128, reserve1: u128, dex: &str) -> PoolInfo {
+        PoolInfo {
+            address: Address::from_low_u64_be(address),
+            token0: Address::from_low_u64_be(token0),
+            token1: Address::from_low_u64_be(token1),
+            reserve0: U256::from(reserve0),
+            reserve1: U256::from(reserve1),
+            fee_bps: 30,
+            dex_name: dex.to_string(),
+            last_updated: 0,
+            block_hash: None,
+            liquidity_usd: None,
+        }
+    }
+
+    fn weth() -> Address {
+        Address::from_low_u64_be(1)
+    }
+
+    #[test]
+    fn test_finds_cross_dex_cycle() {
+        // Token 2 is priced at 2000 on one DEX and 2200 on the other
+        let pools = vec![
+            pool(100, 1, 2, 1_000 * E18, 2_000_000 * E18, "Uniswap"),
+            pool(101, 1, 2, 1_000 * E18, 2_200_000 * E18, "SushiSwap"),
+        ];
+
+        let finder = ArbitrageFinder::new(weth(), 3);
+        let opportunities = finder.find_opportunities(&pools, &MarketContext::default()).unwrap();
+
+        assert!(!opportunities.is_empty());
+        let best = &opportunities[0];
+        assert_eq!(best.tokens.first(), Some(&weth()));
+        assert_eq!(best.tokens.last(), Some(&weth()));
+        assert_eq!(best.hops[0].pool, Address::from_low_u64_be(101));
+        assert!(best.amount_out > best.amount_in);
+        assert!(best.profit_eth > 0.0);
+    }
+
+    #[test]
+    fn test_finds_triangular_cycle() {
+        let pools = vec![
+            pool(100, 1, 2, 1_000 * E18, 2_000_000 * E18, "Uniswap"),
+            pool(101, 2, 3, 2_000_000 * E18, 2_000_000 * E18, "Uniswap"),
+            pool(102, 1, 3, 1_000 * E18, 2_300_000 * E18, "SushiSwap"),
+        ];
+
+        let finder = ArbitrageFinder::new(weth(), 3);
+        let opportunities = finder.find_opportunities(&pools, &MarketContext::default()).unwrap();
+
+        assert!(opportunities.iter().any(|o| o.hops.len() == 3));
+    }
+
+    #[test]
+    fn test_no_cycle_when_prices_agree() {
+        let pools = vec![
+            pool(100, 1, 2, 1_000 * E18, 2_000_000 * E18, "Uniswap"),
+            pool(101, 1, 2, 500 * E18, 1_000_000 * E18, "SushiSwap"),
+        ];
+
+        let finder = ArbitrageFinder::new(weth(), 3);
+        let opportunities = finder.find_opportunities(&pools, &MarketContext::default()).unwrap();
+
+        assert!(opportunities.is_empty());
+    }
+
+    #[test]
+    fn test_min_profit_filters_small_cycles() {
+        let pools = vec![
+            pool(100, 1, 2, 1_000 * E18, 2_000_000 * E18, "Uniswap"),
+            pool(101, 1, 2, 1_000 * E18, 2_200_000 * E18, "SushiSwap"),
+        ];
+
+        let finder = ArbitrageFinder::new(weth(), 3).with_min_profit(1_000.0);
+        let opportunities = finder.find_opportunities(&pools, &MarketContext::default()).unwrap();
+
+        assert!(opportunities.is_empty());
+    }
+
+    #[test]
+    fn test_finds_cycle_behind_lighter_prefix() {
+        // WETH → 2 → 3 → 4 → WETH gains 5% a hop, but the lightest path to token 3 runs
+        // through token 4 (pools 200 and 201), which the cycle still has to visit
+        let pools = vec![
+            pool(100, 1, 2, 1_000 * E18, 1_050 * E18, "Uniswap"),
+            pool(101, 2, 3, 1_000 * E18, 1_050 * E18, "Uniswap"),
+            pool(102, 3, 4, 1_000 * E18, 1_050 * E18, "Uniswap"),
+            pool(103, 4, 1, 1_000 * E18, 1_050 * E18, "Uniswap"),
+            pool(200, 1, 4, 1_000 * E18, 2_000 * E18, "SushiSwap"),
+            pool(201, 4, 3, 1_000 * E18, 2_000 * E18, "SushiSwap"),
+        ];
+
+        let finder = ArbitrageFinder::new(weth(), 4);
+        let opportunities = finder.find_opportunities(&pools, &MarketContext::default()).unwrap();
+
+        let cycle: Vec<Address> = [100, 101, 102, 103].map(Address::from_low_u64_be).to_vec();
+        assert!(opportunities
+            .iter()
+            .any(|o| o.hops.iter().map(|hop| hop.pool).collect::<Vec<_>>() == cycle));
+    }
+
+    #[test]
+    fn test_finds_cycle_behind_many_lighter_prefixes() {
+        // Only WETH → 2 → 3 → 4 → WETH is profitable (pool 102 is 3% cheap). Eight WETH/4
+        // pools 1% cheap give eight lighter paths to token 3 through token 4, none of which
+        // can close, so the cycle's prefix is only the ninth lightest at that depth
+        const R: u128 = 1_000_000 * E18;
+        let mut pools = vec![
+            pool(100, 1, 2, R, R, "Uniswap"),
+            pool(101, 2, 3, R, R, "Uniswap"),
+            pool(102, 3, 4, R, R / 100 * 103, "Uniswap"),
+            pool(103, 4, 1, R, R / 1000 * 993, "Uniswap"),
+            pool(201, 4, 3, R, R, "SushiSwap"),
+        ];
+        pools.extend((300..308).map(|address| pool(address, 1, 4, R, R / 100 * 101, "SushiSwap")));
+
+        let finder = ArbitrageFinder::new(weth(), 4);
+        let opportunities = finder.find_opportunities(&pools, &MarketContext::default()).unwrap();
+
+        let cycle: Vec<Address> = [100, 101, 102, 103].map(Address::from_low_u64_be).to_vec();
+        assert!(opportunities
+            .iter()
+            .any(|o| o.hops.iter().map(|hop| hop.pool).collect::<Vec<_>>() == cycle));
+    }
+}
@@ -0,0 +1,262 @@
+use crate::config::{DexConfig, PoolType};
+use ethers::types::{Address, H256};
+use std::str::FromStr;
+
+/// Init code hash shared by Uniswap V2 and its byte-for-byte forks
+const UNISWAP_V2_INIT_CODE_HASH: &str = "0x96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f";
+
+/// Built-in settings for a supported chain
+#[derive(Debug)]
+pub struct Chain {
+    /// Name accepted by `--chain` (e.g. "arbitrum")
+    pub name: &'static str,
+
+    pub chain_id: u64,
+
+    /// Symbols of the native gas token, resolved to the wrapped native token
+    pub native_symbols: &'static [&'static str],
+
+    /// Symbol of the wrapped native token in the chain's token list
+    pub wrapped_native: &'static str,
+
+    /// Symbols of the tokens multi-hop routes may pass through
+    pub connectors: &'static [&'static str],
+
+    /// Bundled token list
+    pub token_list: &'static str,
+
+    dexes: &'static [DexPreset],
+}
+
+/// A DEX deployment on a chain
+#[derive(Debug)]
+struct DexPreset {
+    name: &'static str,
+    factory: &'static str,
+    router: &'static str,
+    fee_bps: u32,
+    init_code_hash: Option<&'static str>,
+}
+
+impl Chain {
+    /// DEXes deployed on the chain
+    pub fn dexes(&self) -> Vec<DexConfig> {
+        self.dexes
+            .iter()
+            .map(|dex| DexConfig {
+                name: dex.name.to_string(),
+                factory: Address::from_str(dex.factory).expect("preset factory is valid"),
+                router: Address::from_str(dex.router).expect("preset router is valid"),
+                fee_bps: dex.fee_bps,
+                pool_type: PoolType::UniswapV2,
+                init_code_hash: dex
+                    .init_code_hash
+                    .map(|hash| H256::from_str(hash).expect("preset init code hash is valid")),
+            })
+            .collect()
+    }
+
+    /// Environment variable holding the chain's RPC URL: RPC_URL for Ethereum, <NAME>_RPC_URL otherwise
+    pub fn rpc_url_env(&self) -> String {
+        match self.chain_id {
+            1 => "RPC_URL".to_string(),
+            _ => format!("{}_RPC_URL", self.name.to_uppercase()),
+        }
+    }
+
+    /// Default pool cache location, one file per chain
+    pub fn default_cache_path(&self) -> String {
+        match self.chain_id {
+            1 => "./cache/pools.json".to_string(),
+            _ => format!("./cache/pools-{}.json", self.name),
+        }
+    }
+}
+
+/// Uniswap V2 deployed from the canonical factory on a non-mainnet chain
+const fn uniswap_v2(factory: &'static str, router: &'static str) -> DexPreset {
+    DexPreset {
+        name: "Uniswap",
+        factory,
+        router,
+        fee_bps: 30,
+        init_code_hash: Some(UNISWAP_V2_INIT_CODE_HASH),
+    }
+}
+
+/// SushiSwap's V2 factory and router share one address on most chains besides Ethereum
+const SUSHISWAP_MULTICHAIN: DexPreset = DexPreset {
+    name: "SushiSwap",
+    factory: "0xc35DADB65012eC5796536bD9864eD8773aBc74C4",
+    router: "0x1b02dA8Cb0d097eB8D57A175b88c7D8b47997506",
+    fee_bps: 30,
+    init_code_hash: None,
+};
+
+pub const ETHEREUM: Chain = Chain {
+    name: "ethereum",
+    chain_id: 1,
+    native_symbols: &["ETH"],
+    wrapped_native: "WETH",
+    connectors: &["WETH", "USDC", "USDT", "DAI", "WBTC"],
+    token_list: include_str!("../tokens/ethereum.tokenlist.json"),
+    dexes: &[
+        DexPreset {
+            name: "Uniswap",
+            factory: "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f",
+            router: "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D",
+            fee_bps: 30,
+            init_code_hash: Some(UNISWAP_V2_INIT_CODE_HASH),
+        },
+        DexPreset {
+            name: "SushiSwap",
+            factory: "0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac",
+            router: "0xd9e1cE17f2641f24aE83637ab66a2cca9C378B9F",
+            fee_bps: 30,
+            init_code_hash: None,
+        },
+    ],
+};
+
+pub const POLYGON: Chain = Chain {
+    name: "polygon",
+    chain_id: 137,
+    native_symbols: &["POL", "MATIC"],
+    wrapped_native: "WMATIC",
+    connectors: &["WMATIC", "WETH", "USDC", "USDC.e", "USDT", "DAI", "WBTC"],
+    token_list: include_str!("../tokens/polygon.tokenlist.json"),
+    dexes: &[
+        DexPreset {
+            name: "QuickSwap",
+            factory: "0x5757371414417b8C6CAad45bAeF941aBc7d3Ab32",
+            router: "0xa5E0829CaCEd8fFDD4De3c43696c57F7D7A678ff",
+            fee_bps: 30,
+            init_code_hash: Some(UNISWAP_V2_INIT_CODE_HASH),
+        },
+        uniswap_v2(
+            "0x9e5A52f57b3038F1B8EeE45F28b3C1967e22799C",
+            "0xedf6066a2b290C185783862C7F4776A2C8077AD1",
+        ),
+        SUSHISWAP_MULTICHAIN,
+    ],
+};
+
+pub const ARBITRUM: Chain = Chain {
+    name: "arbitrum",
+    chain_id: 42161,
+    native_symbols: &["ETH"],
+    wrapped_native: "WETH",
+    connectors: &["WETH", "USDC", "USDC.e", "USDT", "DAI", "WBTC", "ARB"],
+    token_list: include_str!("../tokens/arbitrum.tokenlist.json"),
+    dexes: &[
+        uniswap_v2(
+            "0xf1D7CC64Fb4452F05c498126312eBE29f30Fbcf9",
+            "0x4752ba5DBc23f44D87826276BF6Fd6b1C372aD24",
+        ),
+        SUSHISWAP_MULTICHAIN,
+    ],
+};
+
+pub const OPTIMISM: Chain = Chain {
+    name: "optimism",
+    chain_id: 10,
+    native_symbols: &["ETH"],
+    wrapped_native: "WETH",
+    connectors: &["WETH", "USDC", "USDC.e", "USDT", "DAI", "OP"],
+    token_list: include_str!("../tokens/optimism.tokenlist.json"),
+    dexes: &[uniswap_v2(
+        "0x0c3c1c532F1e39EdF36BE9Fe0bE1410313E074Bf",
+        "0x4A7b5Da61326A6379179b40d00F57E5bbDC962c2",
+    )],
+};
+
+pub const BASE: Chain = Chain {
+    name: "base",
+    chain_id: 8453,
+    native_symbols: &["ETH"],
+    wrapped_native: "WETH",
+    connectors: &["WETH", "USDC", "USDbC", "DAI"],
+    token_list: include_str!("../tokens/base.tokenlist.json"),
+    dexes: &[uniswap_v2(
+        "0x8909Dc15e40173Ff4699343b6eB8132c65e18eC6",
+        "0x4752ba5DBc23f44D87826276BF6Fd6b1C372aD24",
+    )],
+};
+
+pub const BSC: Chain = Chain {
+    name: "bsc",
+    chain_id: 56,
+    native_symbols: &["BNB"],
+    wrapped_native: "WBNB",
+    connectors: &["WBNB", "USDT", "USDC", "BUSD", "ETH", "BTCB"],
+    token_list: include_str!("../tokens/bsc.tokenlist.json"),
+    dexes: &[
+        DexPreset {
+            name: "PancakeSwap",
+            factory: "0xcA143Ce32Fe78f1f7019d7d551a6402fC5350c73",
+            router: "0x10ED43C718714eb63d5aA57B78B54704E256024E",
+            fee_bps: 25,
+            init_code_hash: Some("0x00fb7f630766e6a796048ea87d01acd3068e8ff67d078148a3fa3f4a84f69bd5"),
+        },
+        uniswap_v2(
+            "0x8909Dc15e40173Ff4699343b6eB8132c65e18eC6",
+            "0x4752ba5DBc23f44D87826276BF6Fd6b1C372aD24",
+        ),
+        SUSHISWAP_MULTICHAIN,
+    ],
+};
+
+/// Every supported chain
+pub const CHAINS: &[&Chain] = &[&ETHEREUM, &POLYGON, &ARBITRUM, &OPTIMISM, &BASE, &BSC];
+
+/// Look up a chain by name (case-insensitive); "mainnet" and "bnb" are accepted as aliases
+pub fn by_name(name: &str) -> Option<&'static Chain> {
+    let name = match name.to_lowercase().as_str() {
+        "mainnet" | "eth" => "ethereum".to_string(),
+        "bnb" | "binance" => "bsc".to_string(),
+        "matic" => "polygon".to_string(),
+        other => other.to_string(),
+    };
+    CHAINS.iter().copied().find(|chain| chain.name == name)
+}
+
+/// Look up a chain by chain id
+pub fn by_id(chain_id: u64) -> Option<&'static Chain> {
+    CHAINS.iter().copied().find(|chain| chain.chain_id == chain_id)
+}
+
+/// Names of the supported chains, for error messages
+pub fn names() -> Vec<&'static str> {
+    CHAINS.iter().map(|chain| chain.name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokens::TokenRegistry;
+
+    #[test]
+    fn test_presets_are_consistent() {
+        for chain in CHAINS {
+            let tokens = TokenRegistry::with_defaults(chain.chain_id);
+            assert!(!tokens.is_empty(), "{} has no tokens", chain.name);
+
+            // Every preset symbol resolves in the chain's own list, and every DEX parses
+            let wrapped = tokens.resolve(chain.wrapped_native).unwrap();
+            for symbol in chain.native_symbols {
+                assert_eq!(tokens.resolve(symbol).unwrap(), wrapped, "{} {}", chain.name, symbol);
+            }
+            for symbol in chain.connectors {
+                assert!(tokens.resolve(symbol).is_ok(), "{} {}", chain.name, symbol);
+            }
+            assert!(!chain.dexes().is_empty());
+            assert!(!tokens.stablecoins().is_empty(), "{} has no USD anchor", chain.name);
+
+            assert_eq!(by_id(chain.chain_id).unwrap().name, chain.name);
+            assert_eq!(by_name(&chain.name.to_uppercase()).unwrap().chain_id, chain.chain_id);
+        }
+
+        assert_eq!(by_name("mainnet").unwrap().chain_id, 1);
+        assert!(by_name("solana").is_none());
+    }
+}
@@ -1,99 +1,422 @@
+use crate::chains::{self, Chain};
 use crate::types::{AggregatorError, Result};
-use ethers::types::Address;
+use ethers::types::{Address, H256};
+use serde::{Deserialize, Serialize};
 use std::env;
 use std::str::FromStr;
+use tracing::warn;
 
+/// Environment variable naming the config file when `--config` isn't given
+pub const CONFIG_FILE_ENV: &str = "DEX_CONFIG";
+
+/// Environment variable naming the chain preset when `--chain` isn't given
+pub const CHAIN_ENV: &str = "CHAIN";
+
+/// How a DEX's pools price swaps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolType {
+    /// Constant-product pairs with the UniswapV2 pair and router interface
+    #[default]
+    UniswapV2,
+}
+
+/// A DEX the aggregator fetches pools from and routes through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DexConfig {
+    /// Name used for pools and route hops (e.g. "Uniswap")
+    pub name: String,
+
+    /// Pair factory
+    pub factory: Address,
+
+    /// Router swaps are sent to
+    pub router: Address,
+
+    /// Swap fee in basis points
+    #[serde(default = "default_fee_bps")]
+    pub fee_bps: u32,
+
+    /// Pool implementation
+    #[serde(default)]
+    pub pool_type: PoolType,
+
+    /// keccak256 of the pair creation code, for computing pair addresses without RPC calls
+    #[serde(default)]
+    pub init_code_hash: Option<H256>,
+}
+
+fn default_fee_bps() -> u32 {
+    30
+}
+
+/// Config file contents; every field is optional and falls back to the defaults
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    rpc_url: Option<String>,
+    chain: Option<String>,
+    chain_id: Option<u64>,
+    cache_enabled: Option<bool>,
+    cache_ttl: Option<u64>,
+    cache_path: Option<String>,
+    default_slippage_bps: Option<u32>,
+    tx_deadline_secs: Option<u64>,
+    max_hops: Option<usize>,
+    gas_price_gwei: Option<u64>,
+    eth_price_usd: Option<f64>,
+    token_lists: Option<Vec<String>>,
+    min_liquidity_usd: Option<f64>,
+    max_staleness_blocks: Option<u64>,
+    reject_stale_quotes: Option<bool>,
+    connectors: Option<Vec<String>>,
+    rpc_requests_per_second: Option<f64>,
+    rpc_max_retries: Option<u32>,
+    fetch_concurrency: Option<usize>,
+    checkpoint_interval: Option<usize>,
+    /// `[[dex]]` tables; replace the default DEX list when present
+    #[serde(default, rename = "dex")]
+    dexes: Vec<DexConfig>,
+}
 
 #[derive(Debug, Clone)]
 pub struct Config {
 
+    /// RPC endpoint, or several separated by commas to fail over between
     pub rpc_url: String,
-  
+
+    /// Requests per second allowed to each RPC endpoint; unlimited when unset
+    pub rpc_requests_per_second: Option<f64>,
+
+    /// Retry rounds after every RPC endpoint failed a request
+    pub rpc_max_retries: u32,
+
+    /// Pools fetched at the same time
+    pub fetch_concurrency: usize,
+
+    /// Pools fetched between writes of the partial cache during a fetch; 0 disables them
+    pub checkpoint_interval: usize,
+
+    /// Block pools are fetched at; the latest block when unset (see [`Config::at_block`])
+    pub fetch_block: Option<u64>,
+
     pub chain_id: u64,
 
-    pub uniswap_v2_factory: Address,
-    
-    pub sushiswap_factory: Address,
-    
+    /// DEXes to fetch pools from and route through
+    pub dexes: Vec<DexConfig>,
+
+    /// Symbols or addresses of the tokens multi-hop routes may pass through; empty allows any
+    pub connectors: Vec<String>,
+
     pub cache_enabled: bool,
-    
+
     pub cache_ttl: u64,
-    
+
     pub cache_path: String,
-    
+
     pub default_slippage_bps: u32,
-    
+
+    /// Seconds from now until a built swap transaction expires
+    pub tx_deadline_secs: u64,
+
     pub max_hops: usize,
-    
-    pub gas_price_gwei: u64,
+
+    /// Gas price override; read from the chain when unset
+    pub gas_price_gwei: Option<u64>,
+
+    /// ETH/USD override; derived from cached pools when unset
+    pub eth_price_usd: Option<f64>,
+
+    /// Token-list JSON files loaded after the bundled list, later files taking precedence
+    pub token_lists: Vec<String>,
+
+    /// Pools valued below this many USD are left out of routing; unvalued pools are kept
+    pub min_liquidity_usd: Option<f64>,
+
+    /// Pools whose reserves are more than this many blocks behind the chain head are stale
+    /// Quoting fails while the head is unknown, unless quoting a past `fetch_block`
+    pub max_staleness_blocks: Option<u64>,
+
+    /// Fail quotes built on stale pools instead of routing around them
+    pub reject_stale_quotes: bool,
 }
 
 impl Config {
-   
+
+    /// Load configuration from the file named by DEX_CONFIG (if set) and the environment
     pub fn from_env() -> Result<Self> {
-    
+        Self::load(None, None)
+    }
+
+    /// Load configuration for a chain from a TOML file (`path`, else DEX_CONFIG if set), then
+    /// apply environment overrides
+    /// The chain is `chain`, else CHAIN, else the file's `chain`, else CHAIN_ID (Ethereum by default)
+    pub fn load(path: Option<&str>, chain: Option<&str>) -> Result<Self> {
         let _ = dotenvy::dotenv();
 
-        let rpc_url = env::var("RPC_URL")
-            .map_err(|_| AggregatorError::ConfigError(
-                "RPC_URL not set. Please set it in .env file".to_string()
-            ))?;
-
-        let chain_id = env::var("CHAIN_ID")
-            .unwrap_or_else(|_| "1".to_string())
-            .parse()
-            .map_err(|_| AggregatorError::ConfigError("Invalid CHAIN_ID".to_string()))?;
-
-        let uniswap_v2_factory = Self::parse_address(
-            &env::var("UNISWAP_V2_FACTORY")
-                .unwrap_or_else(|_| "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f".to_string()),
-        )?;
-
-        let sushiswap_factory = Self::parse_address(
-            &env::var("SUSHISWAP_FACTORY")
-                .unwrap_or_else(|_| "0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac".to_string()),
-        )?;
-
-        let cache_enabled = env::var("CACHE_ENABLED")
-            .unwrap_or_else(|_| "true".to_string())
-            .parse()
-            .unwrap_or(true);
-
-        let cache_ttl = env::var("CACHE_TTL_SECONDS")
-            .unwrap_or_else(|_| "300".to_string())
-            .parse()
-            .unwrap_or(300);
-
-        let cache_path = env::var("CACHE_PATH")
-            .unwrap_or_else(|_| "./cache/pools.json".to_string());
-
-        let default_slippage_bps = env::var("DEFAULT_SLIPPAGE_BPS")
-            .unwrap_or_else(|_| "50".to_string())
-            .parse()
-            .unwrap_or(50);
-
-        let max_hops = env::var("MAX_HOPS")
-            .unwrap_or_else(|_| "3".to_string())
-            .parse()
-            .unwrap_or(3);
-
-        let gas_price_gwei = env::var("GAS_PRICE_GWEI")
-            .unwrap_or_else(|_| "30".to_string())
-            .parse()
-            .unwrap_or(30);
-
-        Ok(Self {
-            rpc_url,
-            chain_id,
-            uniswap_v2_factory,
-            sushiswap_factory,
-            cache_enabled,
-            cache_ttl,
-            cache_path,
-            default_slippage_bps,
-            max_hops,
-            gas_price_gwei,
-        })
+        let path = path.map(str::to_string).or_else(|| env::var(CONFIG_FILE_ENV).ok());
+        let file = match path {
+            Some(path) => Self::read_file(&path)?,
+            None => ConfigFile::default(),
+        };
+        let has_rpc_url = file.rpc_url.is_some();
+
+        let chain = chain.map(str::to_string).or_else(|| env::var(CHAIN_ENV).ok());
+        let mut config = Self::select_chain(chain.as_deref(), &file)?.with_file(file)?;
+        config.apply_env()?;
+
+        let rpc_url_env = config.rpc_url_env();
+        if !has_rpc_url && env::var(&rpc_url_env).is_err() {
+            return Err(AggregatorError::ConfigError(format!(
+                "{} not set. Please set it in .env file or rpc_url in the config file",
+                rpc_url_env
+            )));
+        }
+        if config.dexes.is_empty() {
+            return Err(AggregatorError::ConfigError(format!(
+                "No DEXes known for chain {}; add [[dex]] tables to the config file",
+                config.chain_id
+            )));
+        }
+
+        Ok(config)
+    }
+
+    /// Defaults for the built-in settings of a chain
+    pub fn for_chain(chain: &Chain) -> Self {
+        Self {
+            chain_id: chain.chain_id,
+            dexes: chain.dexes(),
+            connectors: chain.connectors.iter().map(|symbol| symbol.to_string()).collect(),
+            cache_path: chain.default_cache_path(),
+            ..Self::base()
+        }
+    }
+
+    /// Defaults for the chain picked by name, or else by chain id
+    /// A chain id without a preset starts with no DEXes, which the config file must supply
+    fn select_chain(name: Option<&str>, file: &ConfigFile) -> Result<Self> {
+        let unknown = |name: &str| {
+            AggregatorError::ConfigError(format!(
+                "Unknown chain {}; supported chains: {}",
+                name,
+                chains::names().join(", ")
+            ))
+        };
+
+        let Some(name) = name.or(file.chain.as_deref()) else {
+            let chain_id = match env::var("CHAIN_ID") {
+                Ok(chain_id) => chain_id
+                    .parse()
+                    .map_err(|_| AggregatorError::ConfigError("Invalid CHAIN_ID".to_string()))?,
+                Err(_) => file.chain_id.unwrap_or(1),
+            };
+            return Ok(match chains::by_id(chain_id) {
+                Some(chain) => Self::for_chain(chain),
+                None => Self {
+                    chain_id,
+                    ..Self::base()
+                },
+            });
+        };
+
+        let chain = chains::by_name(name).ok_or_else(|| unknown(name))?;
+        let file_chain = match &file.chain {
+            Some(file_chain) => Some(chains::by_name(file_chain).ok_or_else(|| unknown(file_chain))?.chain_id),
+            None => file.chain_id,
+        };
+        if file_chain.is_some_and(|id| id != chain.chain_id) {
+            return Err(AggregatorError::ConfigError(format!(
+                "The config file is for chain {}, not {}",
+                file_chain.unwrap_or_default(),
+                chain.name
+            )));
+        }
+
+        Ok(Self::for_chain(chain))
+    }
+
+    /// RPC endpoints in failover order
+    pub fn rpc_urls(&self) -> Vec<String> {
+        self.rpc_url
+            .split(',')
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect()
+    }
+
+    /// Built-in preset for the configured chain, if any
+    pub fn chain(&self) -> Option<&'static Chain> {
+        chains::by_id(self.chain_id)
+    }
+
+    /// Fetch pools as of a past block, keeping them in a cache file of their own next to the
+    /// latest one, e.g. `./cache/pools.block-18000000.json`
+    /// Reads at old blocks need an archive node
+    pub fn at_block(mut self, block: u64) -> Self {
+        let path = std::path::Path::new(&self.cache_path);
+        let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("pools");
+        let file = match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) => format!("{}.block-{}.{}", stem, block, extension),
+            None => format!("{}.block-{}", stem, block),
+        };
+        self.cache_path = path.with_file_name(file).to_string_lossy().into_owned();
+        self.fetch_block = Some(block);
+        self
+    }
+
+    /// Environment variable holding the RPC URL for the configured chain
+    pub fn rpc_url_env(&self) -> String {
+        self.chain()
+            .map(|chain| chain.rpc_url_env())
+            .unwrap_or_else(|| "RPC_URL".to_string())
+    }
+
+    fn read_file(path: &str) -> Result<ConfigFile> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| AggregatorError::ConfigError(format!("Failed to read config {}: {}", path, e)))?;
+        Self::parse_file(&contents)
+            .map_err(|e| AggregatorError::ConfigError(format!("Invalid config {}: {}", path, e)))
+    }
+
+    fn parse_file(contents: &str) -> std::result::Result<ConfigFile, String> {
+        toml::from_str(contents).map_err(|e| e.to_string())
+    }
+
+    /// Overlay the values set in a config file
+    fn with_file(mut self, file: ConfigFile) -> Result<Self> {
+        if let Some(rpc_url) = file.rpc_url {
+            self.rpc_url = rpc_url;
+        }
+        self.cache_enabled = file.cache_enabled.unwrap_or(self.cache_enabled);
+        self.cache_ttl = file.cache_ttl.unwrap_or(self.cache_ttl);
+        self.cache_path = file.cache_path.unwrap_or(self.cache_path);
+        self.default_slippage_bps = file.default_slippage_bps.unwrap_or(self.default_slippage_bps);
+        self.tx_deadline_secs = file.tx_deadline_secs.unwrap_or(self.tx_deadline_secs);
+        self.max_hops = file.max_hops.unwrap_or(self.max_hops);
+        self.gas_price_gwei = file.gas_price_gwei.or(self.gas_price_gwei);
+        self.eth_price_usd = file.eth_price_usd.or(self.eth_price_usd);
+        self.token_lists = file.token_lists.unwrap_or(self.token_lists);
+        self.min_liquidity_usd = file.min_liquidity_usd.or(self.min_liquidity_usd);
+        self.max_staleness_blocks = file.max_staleness_blocks.or(self.max_staleness_blocks);
+        self.reject_stale_quotes = file.reject_stale_quotes.unwrap_or(self.reject_stale_quotes);
+        self.connectors = file.connectors.unwrap_or(self.connectors);
+        self.rpc_requests_per_second = file.rpc_requests_per_second.or(self.rpc_requests_per_second);
+        self.rpc_max_retries = file.rpc_max_retries.unwrap_or(self.rpc_max_retries);
+        self.fetch_concurrency = file.fetch_concurrency.unwrap_or(self.fetch_concurrency);
+        self.checkpoint_interval = file.checkpoint_interval.unwrap_or(self.checkpoint_interval);
+
+        if !file.dexes.is_empty() {
+            self.dexes = file.dexes;
+        }
+        self.validate_dexes()?;
+        self.validate_eth_price()?;
+
+        Ok(self)
+    }
+
+    /// Apply environment variables, which take precedence over the config file
+    fn apply_env(&mut self) -> Result<()> {
+        if let Ok(rpc_url) = env::var(self.rpc_url_env()) {
+            self.rpc_url = rpc_url;
+        }
+
+        // Address overrides for the mainnet DEXes of the same name
+        let mainnet_overrides = if self.chain_id == 1 {
+            &[
+                ("UNISWAP_V2_FACTORY", "Uniswap", true),
+                ("SUSHISWAP_FACTORY", "SushiSwap", true),
+                ("UNISWAP_V2_ROUTER", "Uniswap", false),
+                ("SUSHISWAP_ROUTER", "SushiSwap", false),
+            ][..]
+        } else {
+            &[]
+        };
+        for &(var, dex, is_factory) in mainnet_overrides {
+            let Ok(value) = env::var(var) else { continue };
+            let address = Self::parse_address(&value)?;
+            let Some(dex) = self.dexes.iter_mut().find(|d| d.name.eq_ignore_ascii_case(dex)) else {
+                warn!("Ignoring {}: no {} DEX is configured", var, dex);
+                continue;
+            };
+            if is_factory {
+                dex.factory = address;
+            } else {
+                dex.router = address;
+            }
+        }
+
+        self.rpc_requests_per_second = env_parse("RPC_REQUESTS_PER_SECOND").or(self.rpc_requests_per_second);
+        if let Some(rpc_max_retries) = env_parse("RPC_MAX_RETRIES") {
+            self.rpc_max_retries = rpc_max_retries;
+        }
+        if let Some(fetch_concurrency) = env_parse("FETCH_CONCURRENCY") {
+            self.fetch_concurrency = fetch_concurrency;
+        }
+        if let Some(checkpoint_interval) = env_parse("CHECKPOINT_INTERVAL") {
+            self.checkpoint_interval = checkpoint_interval;
+        }
+
+        if let Some(cache_enabled) = env_parse("CACHE_ENABLED") {
+            self.cache_enabled = cache_enabled;
+        }
+        if let Some(cache_ttl) = env_parse("CACHE_TTL_SECONDS") {
+            self.cache_ttl = cache_ttl;
+        }
+        if let Ok(cache_path) = env::var("CACHE_PATH") {
+            self.cache_path = cache_path;
+        }
+        if let Some(default_slippage_bps) = env_parse("DEFAULT_SLIPPAGE_BPS") {
+            self.default_slippage_bps = default_slippage_bps;
+        }
+        if let Some(tx_deadline_secs) = env_parse("TX_DEADLINE_SECONDS") {
+            self.tx_deadline_secs = tx_deadline_secs;
+        }
+        if let Some(max_hops) = env_parse("MAX_HOPS") {
+            self.max_hops = max_hops;
+        }
+        self.gas_price_gwei = env_parse("GAS_PRICE_GWEI").or(self.gas_price_gwei);
+        self.eth_price_usd = env_parse("ETH_PRICE_USD").or(self.eth_price_usd);
+        self.min_liquidity_usd = env_parse("MIN_LIQUIDITY_USD").or(self.min_liquidity_usd);
+        self.max_staleness_blocks = env_parse("MAX_STALENESS_BLOCKS").or(self.max_staleness_blocks);
+        if let Some(reject_stale_quotes) = env_parse("REJECT_STALE_QUOTES") {
+            self.reject_stale_quotes = reject_stale_quotes;
+        }
+
+        if let Ok(token_lists) = env::var("TOKEN_LISTS") {
+            self.token_lists = token_lists
+                .split(',')
+                .map(|path| path.trim().to_string())
+                .filter(|path| !path.is_empty())
+                .collect();
+        }
+
+        self.validate_eth_price()
+    }
+
+    /// Reject ETH/USD overrides that would poison gas costs in route scores
+    fn validate_eth_price(&self) -> Result<()> {
+        match self.eth_price_usd {
+            Some(price) if !price.is_finite() || price <= 0.0 => Err(AggregatorError::ConfigError(format!(
+                "eth_price_usd must be a positive number, got {}",
+                price
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reject duplicate DEX names and impossible fees
+    fn validate_dexes(&self) -> Result<()> {
+        for (i, dex) in self.dexes.iter().enumerate() {
+            if dex.fee_bps >= 10000 {
+                return Err(AggregatorError::ConfigError(format!("{}: fee_bps must be below 10000", dex.name)));
+            }
+            if self.dexes[..i].iter().any(|other| other.name.eq_ignore_ascii_case(&dex.name)) {
+                return Err(AggregatorError::ConfigError(format!("DEX {} is configured twice", dex.name)));
+            }
+        }
+        Ok(())
     }
 
     /// Parse an Ethereum address from string
@@ -104,36 +427,94 @@ impl Config {
 
     /// Get factory addresses for all supported DEXs
     pub fn get_all_factories(&self) -> Vec<(String, Address)> {
-        vec![
-            ("Uniswap".to_string(), self.uniswap_v2_factory),
-            ("SushiSwap".to_string(), self.sushiswap_factory),
-        ]
+        self.dexes
+            .iter()
+            .map(|dex| (dex.name.clone(), dex.factory))
+            .collect()
+    }
+
+    /// Get router addresses for all supported DEXs
+    pub fn get_all_routers(&self) -> Vec<(String, Address)> {
+        self.dexes
+            .iter()
+            .map(|dex| (dex.name.clone(), dex.router))
+            .collect()
+    }
+
+    /// Get a DEX by name
+    pub fn get_dex(&self, dex_name: &str) -> Option<&DexConfig> {
+        self.dexes
+            .iter()
+            .find(|dex| dex.name.eq_ignore_ascii_case(dex_name))
+    }
+
+    /// Get the router address for a DEX by name
+    pub fn get_router(&self, dex_name: &str) -> Option<Address> {
+        self.get_dex(dex_name).map(|dex| dex.router)
     }
 }
 
-impl Default for Config {
-    fn default() -> Self {
+/// Parse an environment variable, ignoring it if unset or invalid
+fn env_parse<T: FromStr>(key: &str) -> Option<T> {
+    env::var(key).ok().and_then(|v| v.parse().ok())
+}
+
+impl Config {
+    /// Chain-independent defaults, with no chain, DEXes or connectors
+    fn base() -> Self {
         Self {
             rpc_url: "https://eth.llamarpc.com".to_string(),
+            rpc_requests_per_second: None,
+            rpc_max_retries: 3,
+            fetch_concurrency: 8,
+            checkpoint_interval: 1000,
+            fetch_block: None,
             chain_id: 1,
-            uniswap_v2_factory: Address::from_str("0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f")
-                .unwrap(),
-            sushiswap_factory: Address::from_str("0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac")
-                .unwrap(),
+            dexes: Vec::new(),
+            connectors: Vec::new(),
             cache_enabled: true,
             cache_ttl: 300,
             cache_path: "./cache/pools.json".to_string(),
             default_slippage_bps: 50,
+            tx_deadline_secs: 1200,
             max_hops: 3,
-            gas_price_gwei: 30,
+            gas_price_gwei: None,
+            eth_price_usd: None,
+            token_lists: Vec::new(),
+            min_liquidity_usd: None,
+            max_staleness_blocks: None,
+            reject_stale_quotes: false,
         }
     }
 }
 
+impl Default for Config {
+    fn default() -> Self {
+        Self::for_chain(&chains::ETHEREUM)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const CONFIG_FILE: &str = r#"
+        rpc_url = "http://localhost:8545, http://localhost:8546"
+        max_hops = 2
+
+        [[dex]]
+        name = "ShibaSwap"
+        factory = "0x115934131916C8b277DD010Ee02de363c09d037c"
+        router = "0x03f7724180AA6b939894B5Ca4314783B0b36b329"
+        init_code_hash = "0x65d1a3b1e46c6e4f1be1ad5f99ef14dc488ae0549dc97db9b30afe2241ce1c7a"
+
+        [[dex]]
+        name = "CheapSwap"
+        factory = "0x0000000000000000000000000000000000000001"
+        router = "0x0000000000000000000000000000000000000002"
+        fee_bps = 10
+    "#;
+
     #[test]
     fn test_default_config() {
         let config = Config::default();
@@ -150,4 +531,69 @@ mod tests {
         let invalid = Config::parse_address("invalid");
         assert!(invalid.is_err());
     }
+
+    #[test]
+    fn test_get_router() {
+        let config = Config::default();
+        assert_eq!(config.get_router("sushiswap"), Some(config.dexes[1].router));
+        assert!(config.get_router("Unknown").is_none());
+    }
+
+    #[test]
+    fn test_config_file_replaces_dexes() {
+        let file = Config::parse_file(CONFIG_FILE).unwrap();
+        let config = Config::default().with_file(file).unwrap();
+
+        assert_eq!(config.rpc_urls(), ["http://localhost:8545", "http://localhost:8546"]);
+        assert_eq!(config.max_hops, 2);
+        assert_eq!(config.cache_path, "./cache/pools.json");
+
+        let names: Vec<String> = config.get_all_factories().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["ShibaSwap", "CheapSwap"]);
+        assert_eq!(config.get_dex("shibaswap").unwrap().fee_bps, 30);
+        assert!(config.get_dex("ShibaSwap").unwrap().init_code_hash.is_some());
+        assert_eq!(config.get_dex("CheapSwap").unwrap().fee_bps, 10);
+    }
+
+    #[test]
+    fn test_chain_presets_are_selected_by_name() {
+        let config = Config::select_chain(Some("Arbitrum"), &ConfigFile::default()).unwrap();
+        assert_eq!(config.chain_id, 42161);
+        assert_eq!(config.rpc_url_env(), "ARBITRUM_RPC_URL");
+        assert_eq!(config.cache_path, "./cache/pools-arbitrum.json");
+        assert!(config.get_dex("Uniswap").is_some());
+        assert!(config.connectors.iter().any(|symbol| symbol == "ARB"));
+
+        let file = Config::parse_file("chain = \"bsc\"").unwrap();
+        let config = Config::select_chain(None, &file).unwrap();
+        assert_eq!(config.get_dex("PancakeSwap").unwrap().fee_bps, 25);
+
+        assert!(Config::select_chain(Some("base"), &file).is_err());
+        assert!(Config::select_chain(Some("solana"), &ConfigFile::default()).is_err());
+    }
+
+    #[test]
+    fn test_historical_snapshots_get_their_own_cache() {
+        let config = Config::default().at_block(18_000_000);
+        assert_eq!(config.fetch_block, Some(18_000_000));
+        assert_eq!(config.cache_path, "./cache/pools.block-18000000.json");
+
+        let config = Config { cache_path: "pools".to_string(), ..Config::default() }.at_block(1);
+        assert_eq!(config.cache_path, "pools.block-1");
+    }
+
+    #[test]
+    fn test_invalid_config_files_are_rejected() {
+        assert!(Config::parse_file("max_hopz = 2").is_err());
+        assert!(Config::parse_file("[[dex]]\nname = \"NoFactory\"").is_err());
+
+        let mut file = Config::parse_file(CONFIG_FILE).unwrap();
+        file.dexes[1].name = "shibaswap".to_string();
+        assert!(Config::default().with_file(file).is_err());
+
+        for price in ["nan", "inf", "0.0", "-1800.0"] {
+            let file = Config::parse_file(&format!("eth_price_usd = {}", price)).unwrap();
+            assert!(Config::default().with_file(file).is_err(), "accepted eth_price_usd = {}", price);
+        }
+    }
 }
@@ -0,0 +1,451 @@
+use crate::receipt::decode_transfers;
+use crate::rpc::RpcProvider;
+use crate::swap::{SwapTransaction, WithdrawalFilter, ERC20};
+use crate::types::{AggregatorError, MarketContext, Result};
+use crate::utils;
+use crate::verify::deviation_bps;
+use ethers::abi::AbiDecode;
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// Provider that signs with a local key
+pub type SignerClient = SignerMiddleware<Arc<RpcProvider>, LocalWallet>;
+
+/// Environment variable holding the keystore password
+pub const KEYSTORE_PASSWORD_ENV: &str = "KEYSTORE_PASSWORD";
+
+/// Load a signing key from an encrypted JSON keystore, or from a hex private key in `key_env`
+pub fn load_wallet(keystore: Option<&str>, key_env: &str, chain_id: u64) -> Result<LocalWallet> {
+    let wallet = match keystore {
+        Some(path) => {
+            let password = std::env::var(KEYSTORE_PASSWORD_ENV).map_err(|_| {
+                AggregatorError::ConfigError(format!("{} must be set to decrypt {}", KEYSTORE_PASSWORD_ENV, path))
+            })?;
+            LocalWallet::decrypt_keystore(path, password)
+                .map_err(|e| AggregatorError::ConfigError(format!("Failed to decrypt keystore {}: {}", path, e)))?
+        }
+        None => std::env::var(key_env)
+            .map_err(|_| AggregatorError::ConfigError(format!("No keystore given and {} is not set", key_env)))?
+            .trim()
+            .parse::<LocalWallet>()
+            .map_err(|e| AggregatorError::ConfigError(format!("Invalid private key in {}: {}", key_env, e)))?,
+    };
+
+    Ok(wallet.with_chain_id(chain_id))
+}
+
+/// EIP-1559 (max_fee_per_gas, max_priority_fee_per_gas) from the market context
+/// Allows the base fee to double before the transaction stops being includable
+pub fn eip1559_fees(context: &MarketContext) -> (U256, U256) {
+    let default_priority = utils::gwei_to_wei(1);
+
+    match context.base_fee_per_gas {
+        Some(base_fee) => {
+            let priority = context.priority_fee_per_gas.unwrap_or(default_priority);
+            (base_fee.saturating_mul(U256::from(2)).saturating_add(priority), priority)
+        }
+        None => {
+            let max_fee = context.gas_price_wei;
+            let priority = context.priority_fee_per_gas.unwrap_or(default_priority).min(max_fee);
+            (max_fee, priority)
+        }
+    }
+}
+
+/// Outcome of executing (or dry-running) a swap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapExecution {
+    /// Nothing was sent
+    pub dry_run: bool,
+
+    /// Sender address
+    pub from: Address,
+
+    /// Router allowance was missing (and sent unless dry-running)
+    pub approval_required: bool,
+
+    /// Hash of the approve transaction, if one was sent
+    pub approval_tx_hash: Option<H256>,
+
+    /// Hash of the swap transaction
+    pub tx_hash: Option<H256>,
+
+    /// Block the swap was included in
+    pub block_number: Option<u64>,
+
+    /// Gas used by the swap
+    pub gas_used: Option<U256>,
+
+    /// Price paid per unit of gas
+    pub effective_gas_price: Option<U256>,
+
+    /// Output promised by the quote
+    pub quoted_amount_out: U256,
+
+    /// Minimum output enforced by the router
+    pub amount_out_min: U256,
+
+    /// Output actually received (or returned by eth_call on a dry run)
+    pub realized_amount_out: Option<U256>,
+
+    /// Realized vs quoted output in basis points
+    pub deviation_bps: Option<i64>,
+}
+
+/// Signs and submits router transactions built by `SwapTxBuilder`
+pub struct SwapExecutor {
+    client: Arc<SignerClient>,
+    context: MarketContext,
+    approve: bool,
+}
+
+impl SwapExecutor {
+    /// Create an executor; fees are derived from `context`
+    pub fn new(provider: Arc<RpcProvider>, wallet: LocalWallet, context: MarketContext) -> Self {
+        Self {
+            client: Arc::new(SignerMiddleware::new(provider, wallet)),
+            context,
+            approve: false,
+        }
+    }
+
+    /// Send an ERC20 approve for the router when the allowance is too low
+    pub fn approve(mut self, approve: bool) -> Self {
+        self.approve = approve;
+        self
+    }
+
+    /// Address that signs the transactions
+    pub fn address(&self) -> Address {
+        self.client.address()
+    }
+
+    /// Approve if needed, then submit the swap and wait for its receipt
+    /// With `dry_run` nothing is sent and the swap is evaluated with eth_call
+    pub async fn execute(&self, swap: &SwapTransaction, quoted_amount_out: U256, dry_run: bool) -> Result<SwapExecution> {
+        self.check_chain_id().await?;
+
+        let mut execution = SwapExecution {
+            dry_run,
+            from: self.address(),
+            approval_required: false,
+            approval_tx_hash: None,
+            tx_hash: None,
+            block_number: None,
+            gas_used: None,
+            effective_gas_price: None,
+            quoted_amount_out,
+            amount_out_min: swap.amount_out_min,
+            realized_amount_out: None,
+            deviation_bps: None,
+        };
+
+        // Native ETH input needs no allowance
+        if swap.value.is_zero() {
+            execution.approval_required = !self.has_allowance(swap).await?;
+            if execution.approval_required {
+                if !self.approve && !dry_run {
+                    return Err(AggregatorError::ContractError(format!(
+                        "Router {:?} is not approved to spend {:?}; rerun with --approve",
+                        swap.to, swap.path[0]
+                    )));
+                }
+                if !dry_run {
+                    execution.approval_tx_hash = Some(self.send_approval(swap).await?);
+                }
+            }
+        }
+
+        let mut tx = swap.to_typed_transaction(Some(self.address()), self.chain_id(), &self.context);
+        // The node sizes gas itself; the quote's model must not cap the call or the estimate
+        if let TypedTransaction::Eip1559(inner) = &mut tx {
+            inner.gas = None;
+        }
+
+        if dry_run {
+            // The router would revert on transferFrom without an allowance
+            if !execution.approval_required {
+                let output = self
+                    .client
+                    .call(&tx, None)
+                    .await
+                    .map_err(|e| AggregatorError::ContractError(format!("Swap would revert: {}", e)))?;
+                let amounts = Vec::<U256>::decode(output)
+                    .map_err(|e| AggregatorError::ContractError(format!("Unexpected router output: {}", e)))?;
+                execution.realized_amount_out = amounts.last().copied();
+            }
+        } else {
+            let gas = self
+                .client
+                .estimate_gas(&tx, None)
+                .await
+                .map_err(|e| AggregatorError::ContractError(format!("Swap would revert: {}", e)))?;
+            tx.set_gas(gas * 120 / 100);
+
+            let receipt = self.send(tx).await?;
+            info!("Swap included in block {:?}: {:?}", receipt.block_number, receipt.transaction_hash);
+
+            execution.tx_hash = Some(receipt.transaction_hash);
+            execution.block_number = receipt.block_number.map(|n| n.as_u64());
+            execution.gas_used = receipt.gas_used;
+            execution.effective_gas_price = receipt.effective_gas_price;
+            execution.realized_amount_out = Some(realized_output(swap, &receipt.logs));
+        }
+
+        execution.deviation_bps = execution
+            .realized_amount_out
+            .map(|realized| deviation_bps(quoted_amount_out, realized));
+
+        Ok(execution)
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.client.signer().chain_id()
+    }
+
+    /// Refuse to sign for a different chain than the node serves
+    async fn check_chain_id(&self) -> Result<()> {
+        let node_chain_id = self
+            .client
+            .get_chainid()
+            .await
+            .map_err(|e| AggregatorError::RpcError(format!("Failed to get chain id: {}", e)))?;
+
+        if node_chain_id.as_u64() != self.chain_id() {
+            return Err(AggregatorError::ConfigError(format!(
+                "Node is on chain {} but the wallet is configured for chain {}",
+                node_chain_id,
+                self.chain_id()
+            )));
+        }
+        Ok(())
+    }
+
+    async fn has_allowance(&self, swap: &SwapTransaction) -> Result<bool> {
+        let token = ERC20::new(swap.path[0], self.client.clone());
+        let allowance = token
+            .allowance(self.address(), swap.to)
+            .call()
+            .await
+            .map_err(|e| AggregatorError::ContractError(format!("allowance failed: {}", e)))?;
+
+        debug!("Allowance of {:?} for router {:?}: {}", swap.path[0], swap.to, allowance);
+        Ok(allowance >= swap.amount_in)
+    }
+
+    /// Approve exactly the swap input
+    async fn send_approval(&self, swap: &SwapTransaction) -> Result<H256> {
+        let token = ERC20::new(swap.path[0], self.client.clone());
+        let mut tx = token.approve(swap.to, swap.amount_in).tx;
+        self.apply_fees(&mut tx);
+
+        let receipt = self.send(tx).await?;
+        info!("Approved router {:?} in {:?}", swap.to, receipt.transaction_hash);
+        Ok(receipt.transaction_hash)
+    }
+
+    fn apply_fees(&self, tx: &mut TypedTransaction) {
+        let (max_fee, priority_fee) = eip1559_fees(&self.context);
+
+        match tx {
+            TypedTransaction::Eip1559(inner) => {
+                inner.max_fee_per_gas = Some(max_fee);
+                inner.max_priority_fee_per_gas = Some(priority_fee);
+            }
+            _ => {
+                tx.set_gas_price(max_fee);
+            }
+        }
+    }
+
+    /// Sign, submit and wait for a successful receipt
+    async fn send(&self, tx: TypedTransaction) -> Result<TransactionReceipt> {
+        let receipt = self
+            .client
+            .send_transaction(tx, None)
+            .await
+            .map_err(|e| AggregatorError::RpcError(format!("Failed to send transaction: {}", e)))?
+            .await
+            .map_err(|e| AggregatorError::RpcError(format!("Failed to get receipt: {}", e)))?
+            .ok_or_else(|| AggregatorError::RpcError("Transaction dropped from the mempool".to_string()))?;
+
+        if receipt.status == Some(U64::zero()) {
+            return Err(AggregatorError::ContractError(format!(
+                "Transaction {:?} reverted",
+                receipt.transaction_hash
+            )));
+        }
+
+        Ok(receipt)
+    }
+}
+
+/// Output delivered to the recipient by a mined swap
+/// Native ETH output is the WETH the router unwrapped, taken from its Withdrawal event
+pub(crate) fn realized_output(swap: &SwapTransaction, logs: &[Log]) -> U256 {
+    let token_out = *swap.path.last().unwrap();
+
+    if swap.method == "swapExactTokensForETH" {
+        withdrawn_by(logs, token_out, swap.to)
+    } else {
+        transferred_to(logs, token_out, swap.recipient)
+    }
+}
+
+/// Sum of `token` Transfer events to `recipient` in a set of logs
+pub(crate) fn transferred_to(logs: &[Log], token: Address, recipient: Address) -> U256 {
+    decode_transfers(logs)
+        .iter()
+        .filter(|transfer| transfer.token == token && transfer.to == recipient)
+        .fold(U256::zero(), |acc, transfer| acc.saturating_add(transfer.value))
+}
+
+/// Sum of WETH Withdrawal events by `src` in a set of logs
+fn withdrawn_by(logs: &[Log], weth: Address, src: Address) -> U256 {
+    logs.iter()
+        .filter(|log| log.address == weth)
+        .filter_map(|log| parse_log::<WithdrawalFilter>(log.clone()).ok())
+        .filter(|withdrawal| withdrawal.src == src)
+        .fold(U256::zero(), |acc, withdrawal| acc.saturating_add(withdrawal.wad))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swap::TransferFilter;
+    use ethers::contract::EthEvent;
+
+    fn transfer_log(token: Address, to: Address, value: u64) -> Log {
+        let mut data = [0u8; 32];
+        U256::from(value).to_big_endian(&mut data);
+        Log {
+            address: token,
+            topics: vec![
+                TransferFilter::signature(),
+                H256::from(Address::from_low_u64_be(0xff)),
+                H256::from(to),
+            ],
+            data: data.to_vec().into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_eip1559_fees_from_base_fee() {
+        let context = MarketContext {
+            base_fee_per_gas: Some(utils::gwei_to_wei(20)),
+            priority_fee_per_gas: Some(utils::gwei_to_wei(2)),
+            ..MarketContext::default()
+        };
+
+        assert_eq!(eip1559_fees(&context), (utils::gwei_to_wei(42), utils::gwei_to_wei(2)));
+    }
+
+    #[test]
+    fn test_eip1559_fees_without_base_fee() {
+        let context = MarketContext {
+            gas_price_wei: utils::gwei_to_wei(15),
+            ..MarketContext::default()
+        };
+
+        assert_eq!(eip1559_fees(&context), (utils::gwei_to_wei(15), utils::gwei_to_wei(1)));
+    }
+
+    #[test]
+    fn test_transferred_to_filters_token_and_recipient() {
+        let token = Address::from_low_u64_be(1);
+        let recipient = Address::from_low_u64_be(2);
+        let logs = vec![
+            transfer_log(token, recipient, 100),
+            transfer_log(token, Address::from_low_u64_be(3), 1_000),
+            transfer_log(Address::from_low_u64_be(4), recipient, 10_000),
+            transfer_log(token, recipient, 5),
+        ];
+
+        assert_eq!(transferred_to(&logs, token, recipient), U256::from(105));
+    }
+
+    #[test]
+    fn test_realized_eth_output_from_router_withdrawal() {
+        let weth = Address::from_low_u64_be(1);
+        let router = Address::from_low_u64_be(0xaa);
+        let withdrawal = |src: Address, wad: u64| {
+            let mut data = [0u8; 32];
+            U256::from(wad).to_big_endian(&mut data);
+            Log {
+                address: weth,
+                topics: vec![WithdrawalFilter::signature(), H256::from(src)],
+                data: data.to_vec().into(),
+                ..Default::default()
+            }
+        };
+        let swap = SwapTransaction {
+            to: router,
+            value: U256::zero(),
+            data: Default::default(),
+            method: "swapExactTokensForETH".to_string(),
+            dex_name: "Uniswap".to_string(),
+            path: vec![Address::from_low_u64_be(2), weth],
+            amount_in: U256::from(1_000),
+            amount_out_min: U256::zero(),
+            recipient: Address::from_low_u64_be(0xcc),
+            deadline: U256::zero(),
+            gas_estimate: U256::zero(),
+        };
+        // Another unwrap in the same transaction and the router's own WETH transfer don't count
+        let logs = vec![
+            transfer_log(weth, router, 700),
+            withdrawal(Address::from_low_u64_be(0xbb), 50),
+            withdrawal(router, 700),
+        ];
+
+        assert_eq!(realized_output(&swap, &logs), U256::from(700));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil on PATH and FORK_URL pointing at a mainnet RPC"]
+    async fn test_swap_against_forked_node() {
+        use crate::{Aggregator, Config, OptimizationStrategy};
+        use ethers::utils::Anvil;
+
+        let fork_url = std::env::var("FORK_URL").expect("FORK_URL not set");
+        let anvil = Anvil::new().fork(fork_url).spawn();
+        let config = Config {
+            rpc_url: anvil.endpoint(),
+            cache_path: "./does-not-exist.json".to_string(),
+            ..Config::default()
+        };
+        let aggregator = Aggregator::new(config).await.unwrap();
+        aggregator.refresh_market_context().await.unwrap();
+
+        let weth = aggregator.tokens().resolve("WETH").unwrap();
+        let usdc = aggregator.tokens().resolve("USDC").unwrap();
+        let pair: Address = "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc".parse().unwrap();
+        aggregator.fetch_pool(pair, "Uniswap".to_string()).await.unwrap();
+
+        let wallet = LocalWallet::from(anvil.keys()[0].clone()).with_chain_id(anvil.chain_id());
+        let executor = aggregator.swap_executor(wallet);
+
+        let quote = aggregator
+            .get_best_quote(weth, usdc, U256::exp10(18), OptimizationStrategy::Price)
+            .unwrap();
+        let swap = aggregator
+            .swap_tx_builder(executor.address())
+            .unwrap()
+            .eth_in(true)
+            .build(&quote)
+            .unwrap();
+
+        let dry_run = executor.execute(&swap, quote.amount_out, true).await.unwrap();
+        assert!(dry_run.tx_hash.is_none());
+        assert!(dry_run.realized_amount_out.is_some());
+
+        let execution = executor.execute(&swap, quote.amount_out, false).await.unwrap();
+        assert!(execution.tx_hash.is_some());
+        assert!(execution.realized_amount_out.unwrap() >= swap.amount_out_min);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::types::{PoolInfo, SnapshotBlock};
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Blocks of reserve changes kept for rolling back; about two epochs, after which mainnet
+/// blocks are final
+pub const JOURNAL_DEPTH: u64 = 64;
+
+/// Reserve changes one block made to cached pools
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub block: SnapshotBlock,
+
+    /// Pool states before the block's changes
+    pub previous: Vec<PoolInfo>,
+
+    /// Pools a fetch read at this block rather than a sync, which can't be restored
+    #[serde(default)]
+    pub fetched: Vec<Address>,
+}
+
+/// Reserve changes of recent blocks, oldest first, so cached pools can be rolled back to a
+/// common ancestor after a reorg
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReserveJournal {
+    /// Block the journal starts from: the state every entry can be rolled back to
+    base: Option<SnapshotBlock>,
+    entries: VecDeque<JournalEntry>,
+}
+
+impl ReserveJournal {
+    /// Start journaling from `block` if nothing has been recorded yet
+    pub fn anchor(&mut self, block: SnapshotBlock) {
+        if self.base.is_none() {
+            self.base = Some(block);
+        }
+    }
+
+    /// Record a block's changes; blocks more than `JOURNAL_DEPTH` behind it are forgotten
+    /// Recording the newest block again adds the pools it didn't have yet
+    pub fn record(&mut self, entry: JournalEntry) {
+        if let Some(last) = self.entries.back_mut().filter(|last| last.block == entry.block) {
+            for pool in entry.previous {
+                if !last.previous.iter().any(|previous| previous.address == pool.address) {
+                    last.previous.push(pool);
+                }
+            }
+            last.fetched.extend(entry.fetched);
+            return;
+        }
+
+        let number = entry.block.number;
+        self.entries.push_back(entry);
+        while let Some(oldest) = self.entries.front() {
+            if oldest.block.number + JOURNAL_DEPTH > number {
+                break;
+            }
+            self.base = self.entries.pop_front().map(|entry| entry.block);
+        }
+    }
+
+    /// Note a pool a fetch read at a journaled block; rolling back past that block can't
+    /// restore it, so it has to be read again
+    pub fn note_fetched(&mut self, pool: &PoolInfo) {
+        let Some(block) = pool.snapshot_block() else { return };
+        if let Some(entry) = self.entries.iter_mut().rev().find(|entry| entry.block == block) {
+            entry.fetched.push(pool.address);
+        }
+    }
+
+    /// Pools fetched at journaled blocks after `ancestor`
+    pub fn fetched_after(&self, ancestor: SnapshotBlock) -> HashSet<Address> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.block.number > ancestor.number)
+            .flat_map(|entry| entry.fetched.iter().copied())
+            .collect()
+    }
+
+    /// Blocks the cache can be rolled back to, newest first
+    pub fn rollback_points(&self) -> Vec<SnapshotBlock> {
+        self.entries
+            .iter()
+            .rev()
+            .map(|entry| entry.block)
+            .chain(self.base)
+            .collect()
+    }
+
+    /// Undo the blocks after `ancestor`, returning the states the changed pools had at it
+    pub fn roll_back(&mut self, ancestor: SnapshotBlock) -> Vec<PoolInfo> {
+        let mut restored: HashMap<Address, PoolInfo> = HashMap::new();
+        while self.entries.back().is_some_and(|entry| entry.block.number > ancestor.number) {
+            if let Some(entry) = self.entries.pop_back() {
+                restored.extend(entry.previous.into_iter().map(|pool| (pool.address, pool)));
+            }
+        }
+        if self.base.is_some_and(|base| base.number > ancestor.number) {
+            self.base = Some(ancestor);
+        }
+        restored.into_values().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.base.is_none() && self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{H256, U256};
+
+    fn block(number: u64) -> SnapshotBlock {
+        SnapshotBlock {
+            number,
+            hash: H256::from_low_u64_be(number),
+        }
+    }
+
+    fn pool(reserve0: u64, number: u64) -> PoolInfo {
+        PoolInfo {
+            address: Address::from_low_u64_be(100),
+            token0: Address::from_low_u64_be(1),
+            token1: Address::from_low_u64_be(2),
+            reserve0: U256::from(reserve0),
+            reserve1: U256::from(1000),
+            fee_bps: 30,
+            dex_name: "TestDEX".to_string(),
+            last_updated: number,
+            block_hash: Some(block(number).hash),
+            liquidity_usd: None,
+        }
+    }
+
+    #[test]
+    fn test_roll_back_restores_state_at_ancestor() {
+        let mut journal = ReserveJournal::default();
+        journal.anchor(block(10));
+        journal.record(JournalEntry { block: block(11), previous: vec![pool(100, 10)], fetched: Vec::new() });
+        journal.record(JournalEntry { block: block(12), previous: vec![pool(110, 11)], fetched: Vec::new() });
+        journal.record(JournalEntry { block: block(13), previous: vec![pool(120, 12)], fetched: Vec::new() });
+
+        assert_eq!(
+            journal.rollback_points().iter().map(|block| block.number).collect::<Vec<_>>(),
+            [13, 12, 11, 10]
+        );
+        let reserves = |pools: Vec<PoolInfo>| pools.iter().map(|pool| pool.reserve0.as_u64()).collect::<Vec<_>>();
+        assert_eq!(reserves(journal.roll_back(block(11))), [110]);
+        assert_eq!(reserves(journal.roll_back(block(10))), [100]);
+        assert_eq!(journal.rollback_points(), [block(10)]);
+    }
+
+    #[test]
+    fn test_recording_a_block_again_keeps_earliest_state() {
+        let mut journal = ReserveJournal::default();
+        journal.anchor(block(10));
+        journal.record(JournalEntry { block: block(11), previous: vec![pool(100, 10)], fetched: Vec::new() });
+        journal.record(JournalEntry { block: block(11), previous: vec![pool(110, 11)], fetched: Vec::new() });
+
+        assert_eq!(journal.rollback_points(), [block(11), block(10)]);
+        let restored = journal.roll_back(block(10));
+        assert_eq!(restored.iter().map(|pool| pool.reserve0.as_u64()).collect::<Vec<_>>(), [100]);
+    }
+
+    #[test]
+    fn test_pools_fetched_at_journaled_blocks_are_noted() {
+        let mut journal = ReserveJournal::default();
+        journal.anchor(block(10));
+        journal.record(JournalEntry { block: block(11), previous: Vec::new(), fetched: Vec::new() });
+        journal.note_fetched(&pool(100, 11));
+        // Not journaled, so not noted
+        journal.note_fetched(&pool(100, 12));
+
+        assert_eq!(journal.fetched_after(block(10)), HashSet::from([Address::from_low_u64_be(100)]));
+        assert!(journal.fetched_after(block(11)).is_empty());
+    }
+
+    #[test]
+    fn test_old_blocks_are_forgotten() {
+        let mut journal = ReserveJournal::default();
+        journal.anchor(block(1));
+        journal.record(JournalEntry { block: block(2), previous: vec![pool(100, 1)], fetched: Vec::new() });
+        journal.record(JournalEntry { block: block(2 + JOURNAL_DEPTH), previous: vec![pool(110, 2)], fetched: Vec::new() });
+
+        assert_eq!(journal.rollback_points(), [block(2 + JOURNAL_DEPTH), block(2)]);
+    }
+}
@@ -1,36 +1,85 @@
+pub mod arbitrage;
+pub mod chains;
 pub mod config;
+pub mod execute;
+pub mod journal;
+pub mod market;
+pub mod math;
 pub mod pools;
+pub mod price;
 pub mod quote;
+pub mod receipt;
 pub mod router;
+pub mod rpc;
+#[cfg(feature = "simulation")]
+pub mod simulate;
+pub mod swap;
+pub mod tokens;
 pub mod types;
 pub mod utils;
+pub mod verify;
 
-pub use config::Config;
-pub use pools::{PoolManager, CacheStats};
+pub use arbitrage::{ArbitrageFinder, ArbitrageOpportunity};
+pub use chains::Chain;
+pub use config::{Config, DexConfig, PoolType};
+pub use execute::{SwapExecution, SwapExecutor};
+pub use journal::{JournalEntry, ReserveJournal};
+pub use pools::{CacheStats, FetchCheckpoint, FetchEvent, FetchFailure, FetchReport, PoolManager, Reorg, SyncReport};
 pub use quote::{QuoteEngine, QuoteResult};
-pub use router::Router;
+pub use receipt::{ExecutedSwap, QuoteDiff, TokenTransfer};
+pub use router::{RouteGraph, Router};
+pub use rpc::{EndpointStats, FailoverClient, RpcProvider};
+#[cfg(feature = "simulation")]
+pub use simulate::{RouteSimulator, SimulationResult};
+pub use swap::{SwapTransaction, SwapTxBuilder};
+pub use tokens::TokenRegistry;
 pub use types::{
-    AggregatorError, MarketContext, OptimizationStrategy, PoolInfo, RouteQuote, RouteHop,
-    Result, TokenInfo,
+    AggregatorError, MarketContext, OptimizationStrategy, PoolInfo, QuoteRequest, RouteQuote,
+    RouteHop, Result, SnapshotBlock, TokenInfo,
 };
+pub use verify::{QuoteVerification, QuoteVerifier, SegmentVerification};
 
-use ethers::providers::{Http, Provider};
-use ethers::types::{Address, U256};
-use std::sync::Arc;
+use ethers::providers::Middleware;
+use ethers::signers::LocalWallet;
+use ethers::types::{Address, BlockNumber, H256, U256};
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use swap::ERC20;
 
 /// Main aggregator interface
 pub struct Aggregator {
+    provider: Arc<RpcProvider>,
     pool_manager: Arc<PoolManager>,
     config: Config,
+    tokens: Arc<TokenRegistry>,
+    connectors: HashSet<Address>,
+    market: RwLock<MarketContext>,
 }
 
 impl Aggregator {
     /// Create a new aggregator instance
     pub async fn new(config: Config) -> Result<Self> {
-        let provider = Provider::<Http>::try_from(config.rpc_url.clone())
-            .map_err(|e| AggregatorError::RpcError(format!("Failed to create provider: {}", e)))?;
+        let provider = Arc::new(rpc::connect(&config)?);
 
-        let pool_manager = Arc::new(PoolManager::new(Arc::new(provider), config.clone()));
+        let tokens = TokenRegistry::with_defaults(config.chain_id);
+        for path in &config.token_lists {
+            tokens.load_list_file(path)?;
+        }
+
+        let connectors = config
+            .connectors
+            .iter()
+            .map(|symbol| {
+                tokens.resolve(symbol).map_err(|_| {
+                    AggregatorError::ConfigError(format!("Unknown connector token {} on chain {}", symbol, config.chain_id))
+                })
+            })
+            .collect::<Result<HashSet<_>>>()?;
+
+        let tokens = Arc::new(tokens);
+        let pool_manager = Arc::new(PoolManager::new(provider.clone(), config.clone()).with_tokens(tokens.clone()));
 
         // Auto-load cache if it exists
         let cache_path = &config.cache_path;
@@ -39,25 +88,35 @@ impl Aggregator {
             // Silently ignore errors - cache is optional
         }
 
-        Ok(Self {
+        let aggregator = Self {
+            provider,
             pool_manager,
             config,
-        })
+            tokens,
+            connectors,
+            market: RwLock::new(MarketContext::default()),
+        };
+        *aggregator.market.write().unwrap() = aggregator.offline_market_context();
+        aggregator.revalue_pools();
+
+        Ok(aggregator)
     }
 
-    /// Fetch pools from all configured DEX factories
-    pub async fn fetch_all_pools(&self, limit_per_dex: Option<usize>) -> Result<usize> {
-        let mut total_fetched = 0;
+    /// Fetch pools from all configured DEX factories, all at one block
+    pub async fn fetch_all_pools(&self, limit_per_dex: Option<usize>) -> Result<Vec<FetchReport>> {
+        let mut reports = Vec::new();
+        let block = self.pinned_block().await?;
 
         for (dex_name, factory_addr) in self.config.get_all_factories() {
-            let pools = self
+            let report = self
                 .pool_manager
-                .fetch_pools(factory_addr, dex_name, limit_per_dex)
+                .fetch_pools_with_progress(factory_addr, dex_name, limit_per_dex, false, block, &|_| {})
                 .await?;
-            total_fetched += pools.len();
+            reports.push(report);
         }
+        self.revalue_pools();
 
-        Ok(total_fetched)
+        Ok(reports)
     }
 
     /// Fetch pools from a specific factory
@@ -66,13 +125,133 @@ impl Aggregator {
         factory_address: Address,
         dex_name: String,
         limit: Option<usize>,
-    ) -> Result<Vec<PoolInfo>> {
+    ) -> Result<FetchReport> {
+        let block = self.pinned_block().await?;
+        self.fetch_pools_with_progress(factory_address, dex_name, limit, false, block, &|_| {}).await
+    }
+
+    /// Block fetches read at: the configured `fetch_block`, else the latest
+    /// Commands fetching from several DEXes resolve it once and pass it to each fetch
+    pub async fn pinned_block(&self) -> Result<SnapshotBlock> {
+        self.pool_manager.pinned_block().await
+    }
+
+    /// Fetch pools from a specific factory at `block`, reporting progress as pools arrive
+    /// With `resume`, the fetch continues from the factory's checkpoint in the cache
+    pub async fn fetch_pools_with_progress(
+        &self,
+        factory_address: Address,
+        dex_name: String,
+        limit: Option<usize>,
+        resume: bool,
+        block: SnapshotBlock,
+        progress: &(dyn Fn(&FetchEvent) + Sync),
+    ) -> Result<FetchReport> {
+        let report = self
+            .pool_manager
+            .fetch_pools_with_progress(factory_address, dex_name, limit, resume, block, progress)
+            .await?;
+        self.revalue_pools();
+        Ok(report)
+    }
+
+    /// Fetch the `limit` pools of a factory with the most USD liquidity, scanning every pair
+    /// Pairs are valued at the token prices of cached pools; stablecoins and the wrapped native
+    /// token are always priced, so a first fetch ranks the pools that pair with them
+    pub async fn fetch_top_pools(
+        &self,
+        factory_address: Address,
+        dex_name: String,
+        limit: usize,
+        block: SnapshotBlock,
+        progress: &(dyn Fn(&FetchEvent) + Sync),
+    ) -> Result<FetchReport> {
+        let prices = self.token_prices_usd()?;
+        let report = self
+            .pool_manager
+            .fetch_top_pools(factory_address, dex_name, limit, &prices, block, progress)
+            .await?;
+        self.revalue_pools();
+        Ok(report)
+    }
+
+    /// Fetch the first `limit` pairs of a DEX's ranked seed list (see [`pools::load_seed_list`])
+    pub async fn fetch_seeded_pools(
+        &self,
+        factory_address: Address,
+        dex_name: String,
+        pairs: &[Address],
+        limit: Option<usize>,
+        block: SnapshotBlock,
+        progress: &(dyn Fn(&FetchEvent) + Sync),
+    ) -> Result<FetchReport> {
+        let report = self
+            .pool_manager
+            .fetch_seeded_pools(factory_address, dex_name, pairs, limit, block, progress)
+            .await?;
+        self.revalue_pools();
+        Ok(report)
+    }
+
+    /// Fetch the pools routes between `tokens` can use without a full factory sync
+    /// Looks up every pair among the tokens and the connector tokens (or the wrapped native
+    /// token when any token may connect) on each DEX, and returns the pools that were not cached
+    pub async fn ensure_pools_for(&self, tokens: &[Address]) -> Result<Vec<PoolInfo>> {
+        let mut candidates: Vec<Address> = tokens.to_vec();
+        if self.connectors.is_empty() {
+            candidates.extend(self.weth().ok());
+        } else {
+            let mut connectors: Vec<Address> = self.connectors.iter().copied().collect();
+            connectors.sort();
+            candidates.extend(connectors);
+        }
+        let mut seen = HashSet::new();
+        candidates.retain(|token| seen.insert(*token));
+
+        let pairs: Vec<(Address, Address)> = candidates
+            .iter()
+            .enumerate()
+            .flat_map(|(i, a)| candidates[i + 1..].iter().map(move |b| (*a, *b)))
+            .collect();
+
+        let pools = self.pool_manager.discover_pools(&pairs).await?;
+        self.revalue(&pools.iter().map(|pool| pool.address).collect::<Vec<_>>());
+        Ok(pools)
+    }
+
+    /// Update cached reserves to the chain head from the pools' Sync events
+    /// Reorged blocks are rolled back to the common ancestor first (see [`PoolManager::sync_reserves`])
+    /// Every cached pool is revalued afterwards, since moved reserves move the prices the others
+    /// are valued at
+    pub async fn sync_reserves(&self) -> Result<SyncReport> {
+        let report = self.pool_manager.sync_reserves().await?;
+        self.revalue_pools();
+        Ok(report)
+    }
+
+    /// Latest block pools were fetched at, with its hash
+    pub fn snapshot_block(&self) -> Option<SnapshotBlock> {
+        self.pool_manager.snapshot()
+    }
+
+    /// Where the last fetch from a factory got to
+    pub fn fetch_checkpoint(&self, factory: &Address) -> Option<FetchCheckpoint> {
+        self.pool_manager.checkpoint(factory)
+    }
+
+    /// Fetch a single pool by address and add it to the cache
+    pub async fn fetch_pool(&self, pair_address: Address, dex_name: String) -> Result<PoolInfo> {
+        let pool = self.pool_manager.fetch_pool_info(pair_address, dex_name).await?;
+        self.pool_manager.insert_pool(pool);
+        self.revalue(&[pair_address]);
         self.pool_manager
-            .fetch_pools(factory_address, dex_name, limit)
-            .await
+            .get_pool(&pair_address)
+            .ok_or_else(|| AggregatorError::PoolNotFound(format!("{:?}", pair_address)))
     }
 
     /// Get the best quote for a swap
+    /// With `max_staleness_blocks` set, the chain head must be known: call
+    /// `refresh_market_context` first
     pub fn get_best_quote(
         &self,
         token_in: Address,
@@ -93,22 +272,217 @@ impl Aggregator {
         optimization: OptimizationStrategy,
         limit: usize,
     ) -> Result<Vec<RouteQuote>> {
-        let pools = self.pool_manager.get_all_pools();
+        let pools = self.routable_pools()?;
 
         if pools.is_empty() {
-            return Err(AggregatorError::PoolNotFound(
-                "No pools cached. Run fetch-pools first.".to_string(),
-            ));
+            return Err(self.no_routable_pools());
         }
 
-        let router = Router::new(optimization, self.config.max_hops);
-        let context = MarketContext {
-            gas_price_gwei: self.config.gas_price_gwei,
-            eth_price_usd: 1800.0,
-            block_number: 0,
-        };
+        let router = self.router(optimization);
+        let context = self.market_context();
+
+        self.check_staleness(router.find_top_routes(&pools, token_in, token_out, amount_in, &context, limit)?)
+    }
+
+    /// Router limited to the configured hop count and connector tokens
+    fn router(&self, optimization: OptimizationStrategy) -> Router {
+        Router::new(optimization, self.config.max_hops).with_connectors(self.connectors.clone())
+    }
+
+    /// Snapshot the cached pools into a graph that can be reused across quotes
+    pub fn route_graph(&self) -> Result<RouteGraph> {
+        let pools = self.routable_pools()?;
+
+        if pools.is_empty() {
+            return Err(self.no_routable_pools());
+        }
+
+        Ok(RouteGraph::new(&pools))
+    }
+
+    /// Get the best quote for a request against a graph snapshot
+    pub fn quote_with_graph(
+        &self,
+        graph: &RouteGraph,
+        request: &QuoteRequest,
+        optimization: OptimizationStrategy,
+    ) -> Result<RouteQuote> {
+        let router = self.router(optimization);
+        let context = self.market_context();
+
+        let quotes = router.find_top_routes_in(
+            graph,
+            request.token_in,
+            request.token_out,
+            request.amount_in,
+            &context,
+            1,
+        )?;
+        Ok(self.check_staleness(quotes)?.into_iter().next().unwrap())
+    }
+
+    /// Quote many swaps against one graph snapshot
+    /// Failures are reported per request instead of aborting the batch
+    pub fn quote_batch(
+        &self,
+        requests: &[QuoteRequest],
+        optimization: OptimizationStrategy,
+    ) -> Result<Vec<Result<RouteQuote>>> {
+        let graph = self.route_graph()?;
+
+        Ok(requests
+            .iter()
+            .map(|request| self.quote_with_graph(&graph, request, optimization))
+            .collect())
+    }
+
+    /// Create a transaction builder using the configured routers, slippage and deadline
+    pub fn swap_tx_builder(&self, recipient: Address) -> Result<SwapTxBuilder> {
+        let weth = self.weth()?;
+
+        Ok(SwapTxBuilder::new(self.config.get_all_routers(), weth, recipient)
+            .slippage_bps(self.config.default_slippage_bps)
+            .deadline_secs(self.config.tx_deadline_secs))
+    }
+
+    /// Executor that signs swaps with `wallet`, priced from the current market context
+    pub fn swap_executor(&self, wallet: LocalWallet) -> SwapExecutor {
+        SwapExecutor::new(self.provider.clone(), wallet, self.market_context())
+    }
+
+    /// Decode a mined swap transaction into its executed route
+    /// Pools missing from the cache are fetched and named after the factory that deployed them
+    pub async fn decode_receipt(&self, tx_hash: H256) -> Result<ExecutedSwap> {
+        let receipt = self
+            .provider
+            .get_transaction_receipt(tx_hash)
+            .await
+            .map_err(|e| AggregatorError::RpcError(format!("Failed to get receipt: {}", e)))?
+            .ok_or_else(|| AggregatorError::RpcError(format!("No receipt for {:?}", tx_hash)))?;
+
+        let mut pools = Vec::new();
+        for (pool_address, _) in receipt::decode_swaps(&receipt.logs) {
+            let pool = match self.pool_manager.get_pool(&pool_address) {
+                Some(pool) => pool,
+                None => self.identify_pool(pool_address).await?,
+            };
+            pools.push(pool);
+        }
+
+        ExecutedSwap::from_receipt(&receipt, &pools)
+    }
+
+    async fn identify_pool(&self, pair_address: Address) -> Result<PoolInfo> {
+        let factory = self.pool_manager.fetch_pair_factory(pair_address).await?;
+        let dex_name = self
+            .config
+            .get_all_factories()
+            .into_iter()
+            .find(|(_, address)| *address == factory)
+            .map(|(name, _)| name)
+            .unwrap_or_else(|| "Unknown".to_string());
+
+        self.pool_manager.fetch_pool_info(pair_address, dex_name).await
+    }
+
+    /// Check a quote against each DEX router's getAmountsOut
+    pub async fn verify_quote(&self, quote: &RouteQuote) -> Result<QuoteVerification> {
+        QuoteVerifier::new(self.provider.clone(), self.config.get_all_routers())
+            .verify(quote)
+            .await
+    }
+
+    /// Execute a quote in a local fork of the block its reserves were read at (the latest block
+    /// when its pools come from different blocks) and record the simulated output
+    #[cfg(feature = "simulation")]
+    pub async fn simulate_quote(&self, quote: &mut RouteQuote) -> Result<SimulationResult> {
+        let weth = self.weth()?;
+        let mut simulator = RouteSimulator::new(self.provider.clone(), self.config.get_all_routers(), weth);
+        if let Some(block) = quote.block {
+            simulator = simulator.at_block(block.number);
+        }
+        let result = simulator.simulate(quote).await?;
+
+        quote.simulated_amount_out = Some(result.amount_out);
+        quote.simulated_gas_used = Some(result.gas_used);
+
+        Ok(result)
+    }
+
+    /// Find profitable WETH cycles across cached pools, ranked by net profit in ETH
+    pub fn find_arbitrage(&self, min_profit_eth: f64, limit: usize) -> Result<Vec<ArbitrageOpportunity>> {
+        let pools = self.routable_pools()?;
 
-        router.find_top_routes(&pools, token_in, token_out, amount_in, &context, limit)
+        if pools.is_empty() {
+            return Err(self.no_routable_pools());
+        }
+
+        let weth = self.weth()?;
+        let finder = ArbitrageFinder::new(weth, self.config.max_hops).with_min_profit(min_profit_eth);
+        let context = self.market_context();
+
+        let mut opportunities = finder.find_opportunities(&pools, &context)?;
+        opportunities.truncate(limit);
+        Ok(opportunities)
+    }
+
+    /// Current market context used for scoring
+    pub fn market_context(&self) -> MarketContext {
+        self.market.read().unwrap().clone()
+    }
+
+    /// Refresh the market context from the provider
+    /// Gas price and block number come from the chain, at `fetch_block` when set, and ETH/USD
+    /// from the deepest cached WETH/stablecoin pools; config values take precedence when set
+    pub async fn refresh_market_context(&self) -> Result<MarketContext> {
+        let mut context = self.offline_market_context();
+
+        // Keep pool-derived values current even if the RPC call fails
+        *self.market.write().unwrap() = context.clone();
+        let at = self.config.fetch_block.map_or(BlockNumber::Latest, BlockNumber::from);
+        let state = market::fetch_chain_state(&self.provider, at).await?;
+
+        context.block_number = state.block_number;
+        context.block_known = true;
+        context.base_fee_per_gas = state.base_fee_per_gas;
+        context.priority_fee_per_gas = state.priority_fee_per_gas;
+        if self.config.gas_price_gwei.is_none() {
+            context.gas_price_wei = state.gas_price;
+        }
+
+        *self.market.write().unwrap() = context.clone();
+        self.revalue_pools();
+        Ok(context)
+    }
+
+    /// Market context built without RPC calls, from config overrides and cached pools
+    fn offline_market_context(&self) -> MarketContext {
+        let pools = self.pool_manager.get_all_pools();
+        let defaults = MarketContext::default();
+
+        let eth_price_usd = self
+            .config
+            .eth_price_usd
+            .or_else(|| {
+                let weth = self.weth().ok()?;
+                market::derive_eth_price_usd(&pools, weth, &self.tokens.stablecoins())
+            })
+            .unwrap_or(defaults.eth_price_usd);
+
+        MarketContext {
+            gas_price_wei: self
+                .config
+                .gas_price_gwei
+                .map_or(defaults.gas_price_wei, utils::gwei_to_wei),
+            eth_price_usd,
+            // Historical snapshots are aged against their own block, not the chain head
+            block_number: self
+                .config
+                .fetch_block
+                .unwrap_or_else(|| pools.iter().map(|p| p.last_updated).max().unwrap_or(0)),
+            block_known: self.config.fetch_block.is_some(),
+            ..defaults
+        }
     }
 
     /// Get all cached pools
@@ -121,11 +495,184 @@ impl Aggregator {
         self.pool_manager.get_pools_with_token(&token)
     }
 
+    /// Request counters and health of each RPC endpoint
+    pub fn rpc_stats(&self) -> Vec<EndpointStats> {
+        self.provider.as_ref().as_ref().stats()
+    }
+
     /// Get configuration
     pub fn get_config(&self) -> &Config {
         &self.config
     }
 
+    /// Token metadata used for symbol resolution, amount parsing and formatting
+    pub fn tokens(&self) -> &TokenRegistry {
+        &self.tokens
+    }
+
+    /// Wrapped native token of the configured chain
+    pub fn weth(&self) -> Result<Address> {
+        self.tokens.wrapped_native()
+    }
+
+    /// Built-in preset for the configured chain, if any
+    pub fn chain(&self) -> Option<&'static Chain> {
+        self.config.chain()
+    }
+
+    /// USD prices of every token linked to a stablecoin or WETH through cached pools
+    pub fn token_prices_usd(&self) -> Result<HashMap<Address, f64>> {
+        let pools = self.pool_manager.get_all_pools();
+        let eth_price_usd = self.market_context().eth_price_usd;
+
+        Ok(market::derive_token_prices_usd(&pools, self.weth()?, eth_price_usd, &self.tokens))
+    }
+
+    /// USD price of a token, priced through cached pools
+    pub fn token_price_usd(&self, token: Address) -> Result<f64> {
+        self.token_prices_usd()?
+            .remove(&token)
+            .filter(|price| *price > 0.0)
+            .ok_or_else(|| {
+                AggregatorError::PoolNotFound(format!(
+                    "No cached USD price for {}; fetch a pool pairing it with WETH or a stablecoin",
+                    self.tokens.symbol(token)
+                ))
+            })
+    }
+
+    /// Value every cached pool in USD at current token prices
+    fn revalue_pools(&self) {
+        let Ok(prices) = self.token_prices_usd() else { return };
+        self.pool_manager
+            .set_liquidity_usd(|pool| market::pool_liquidity_usd(pool, &prices, &self.tokens));
+    }
+
+    /// Value the given cached pools in USD at current token prices, leaving the rest as they are
+    fn revalue(&self, pools: &[Address]) {
+        if pools.is_empty() {
+            return;
+        }
+        let Ok(prices) = self.token_prices_usd() else { return };
+        self.pool_manager
+            .set_liquidity_usd_of(pools, |pool| market::pool_liquidity_usd(pool, &prices, &self.tokens));
+    }
+
+    /// Cached pools eligible for routing, leaving out pools below `min_liquidity_usd` and,
+    /// unless stale quotes are rejected instead, pools older than `max_staleness_blocks`
+    fn routable_pools(&self) -> Result<Vec<PoolInfo>> {
+        let mut pools = self.pool_manager.get_all_pools();
+        if let Some(min) = self.config.min_liquidity_usd {
+            pools.retain(|pool| pool.liquidity_usd.is_none_or(|tvl| tvl >= min));
+        }
+
+        if let Some(max) = self.config.max_staleness_blocks.filter(|_| !self.config.reject_stale_quotes) {
+            let head = self.staleness_reference()?;
+            // Pools from caches without block numbers can't be aged and are kept
+            pools.retain(|pool| pool.last_updated == 0 || head.saturating_sub(pool.last_updated) <= max);
+        }
+        Ok(pools)
+    }
+
+    /// Block that `max_staleness_blocks` ages pools against: the chain head, or the past block
+    /// being quoted
+    /// Fails when neither is known instead of trusting the newest cached pool, which would pass a
+    /// cache that is stale as a whole
+    fn staleness_reference(&self) -> Result<u64> {
+        let context = self.market_context();
+        if !context.block_known || context.block_number == 0 {
+            return Err(AggregatorError::RpcError(
+                "Chain head unknown, so pools can't be checked against MAX_STALENESS_BLOCKS; refresh the market context first"
+                    .to_string(),
+            ));
+        }
+        Ok(context.block_number)
+    }
+
+    /// Error for when `routable_pools` is empty
+    fn no_routable_pools(&self) -> AggregatorError {
+        let message = if self.pool_manager.get_cache_stats().total_pools == 0 {
+            "No pools cached. Run fetch-pools first."
+        } else {
+            "No cached pools are liquid and fresh enough to route through (see MIN_LIQUIDITY_USD and MAX_STALENESS_BLOCKS)"
+        };
+        AggregatorError::PoolNotFound(message.to_string())
+    }
+
+    /// With `reject_stale_quotes`, fail if the best quote is older than `max_staleness_blocks`
+    /// and drop stale alternatives
+    fn check_staleness(&self, mut quotes: Vec<RouteQuote>) -> Result<Vec<RouteQuote>> {
+        let Some(max) = self.config.max_staleness_blocks.filter(|_| self.config.reject_stale_quotes) else {
+            return Ok(quotes);
+        };
+        self.staleness_reference()?;
+        if let Some(age) = quotes.first().and_then(|quote| quote.staleness_blocks).filter(|age| *age > max) {
+            return Err(AggregatorError::StaleState { age, max });
+        }
+        quotes.retain(|quote| quote.staleness_blocks.is_none_or(|age| age <= max));
+        Ok(quotes)
+    }
+
+    /// Parse an amount of `token` into base units
+    /// Besides plain amounts ("1.5", "1_000", "1e6") accepts a USD value ("$5000"), converted at
+    /// the cached USD price, and "max", the full token balance of `owner`
+    pub async fn parse_amount(&self, token: Address, input: &str, owner: Option<Address>) -> Result<U256> {
+        let input = input.trim();
+
+        if input.eq_ignore_ascii_case("max") {
+            let owner = owner.ok_or_else(|| {
+                AggregatorError::InvalidAmount("\"max\" needs an account to read the balance of (--from)".to_string())
+            })?;
+            let balance = ERC20::new(token, self.provider.clone())
+                .balance_of(owner)
+                .call()
+                .await
+                .map_err(|e| AggregatorError::ContractError(format!("Failed to read balance: {}", e)))?;
+            if balance.is_zero() {
+                return Err(AggregatorError::InvalidAmount(format!(
+                    "{:?} holds no {}",
+                    owner,
+                    self.tokens.symbol(token)
+                )));
+            }
+            return Ok(balance);
+        }
+
+        let Some(usd) = input.strip_prefix('$') else {
+            return self.tokens.parse_amount(token, input);
+        };
+
+        let usd = math::to_decimal(utils::parse_token_amount(usd, 18)?, 18)?;
+        let price = Decimal::from_f64(self.token_price_usd(token)?).ok_or(AggregatorError::MathError)?;
+        let decimals = self.tokens.require_decimals(token)?;
+        let units = usd
+            .checked_div(price)
+            .ok_or(AggregatorError::MathError)?
+            .round_dp_with_strategy(decimals.min(28) as u32, RoundingStrategy::ToZero);
+
+        utils::parse_token_amount(&units.to_string(), decimals)
+    }
+
+    /// Build a quote request from token symbols or addresses and an amount in any form
+    /// `parse_amount` accepts
+    pub async fn quote_request(
+        &self,
+        token_in: &str,
+        token_out: &str,
+        amount: &str,
+        owner: Option<Address>,
+    ) -> Result<QuoteRequest> {
+        let token_in = self.tokens.resolve(token_in)?;
+        let token_out = self.tokens.resolve(token_out)?;
+        let amount_in = self.parse_amount(token_in, amount, owner).await?;
+
+        Ok(QuoteRequest {
+            token_in,
+            token_out,
+            amount_in,
+        })
+    }
+
     /// Export cache to file
     pub fn export_cache(&self, path: &str) -> Result<()> {
         self.pool_manager.export_to_file(path)
@@ -133,7 +680,9 @@ impl Aggregator {
 
     /// Import cache from file
     pub fn import_cache(&self, path: &str) -> Result<usize> {
-        self.pool_manager.import_from_file(path)
+        let count = self.pool_manager.import_from_file(path)?;
+        self.revalue_pools();
+        Ok(count)
     }
 
     /// Get cache statistics
@@ -147,6 +696,60 @@ impl Aggregator {
     }
 }
 
+/// Aggregators for several chains served from one process, keyed by chain id
+#[derive(Default)]
+pub struct MultiChainAggregator {
+    aggregators: HashMap<u64, Arc<Aggregator>>,
+}
+
+impl MultiChainAggregator {
+    /// Create an empty set of aggregators
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the configuration of each named chain (see `Config::load`) and create its aggregator
+    pub async fn from_chains(chains: &[&str], config_path: Option<&str>) -> Result<Self> {
+        let mut multi = Self::new();
+        for chain in chains {
+            multi.add(Config::load(config_path, Some(chain))?).await?;
+        }
+        Ok(multi)
+    }
+
+    /// Create an aggregator for the chain of `config`; each chain can be added once
+    pub async fn add(&mut self, config: Config) -> Result<Arc<Aggregator>> {
+        if self.aggregators.contains_key(&config.chain_id) {
+            return Err(AggregatorError::ConfigError(format!(
+                "An aggregator for chain {} already exists",
+                config.chain_id
+            )));
+        }
+
+        let chain_id = config.chain_id;
+        let aggregator = Arc::new(Aggregator::new(config).await?);
+        self.aggregators.insert(chain_id, aggregator.clone());
+        Ok(aggregator)
+    }
+
+    /// Aggregator for a chain id
+    pub fn get(&self, chain_id: u64) -> Option<Arc<Aggregator>> {
+        self.aggregators.get(&chain_id).cloned()
+    }
+
+    /// Aggregator for a chain preset name (e.g. "arbitrum")
+    pub fn get_by_name(&self, name: &str) -> Option<Arc<Aggregator>> {
+        self.get(chains::by_name(name)?.chain_id)
+    }
+
+    /// Chain ids with an aggregator, in ascending order
+    pub fn chain_ids(&self) -> Vec<u64> {
+        let mut chain_ids: Vec<u64> = self.aggregators.keys().copied().collect();
+        chain_ids.sort_unstable();
+        chain_ids
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -157,4 +760,298 @@ mod tests {
         let result = Aggregator::new(config).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_aggregators_for_several_chains() {
+        let mut multi = MultiChainAggregator::new();
+        for chain in [&chains::ETHEREUM, &chains::ARBITRUM] {
+            let config = Config {
+                cache_path: "./does-not-exist.json".to_string(),
+                ..Config::for_chain(chain)
+            };
+            multi.add(config).await.unwrap();
+        }
+        assert!(multi.add(Config::for_chain(&chains::ARBITRUM)).await.is_err());
+
+        let mainnet = multi.get(1).unwrap();
+        let arbitrum = multi.get_by_name("arbitrum").unwrap();
+        assert_eq!(multi.chain_ids(), [1, 42161]);
+        assert_ne!(mainnet.weth().unwrap(), arbitrum.weth().unwrap());
+        assert_eq!(arbitrum.tokens().resolve("ETH").unwrap(), arbitrum.weth().unwrap());
+        assert!(multi.get_by_name("bsc").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_market_context_overrides() {
+        let config = Config {
+            cache_path: "./does-not-exist.json".to_string(),
+            gas_price_gwei: Some(12),
+            eth_price_usd: Some(2500.0),
+            ..Config::default()
+        };
+        let aggregator = Aggregator::new(config).await.unwrap();
+
+        let context = aggregator.market_context();
+        assert_eq!(context.gas_price_wei, utils::gwei_to_wei(12));
+        assert_eq!(context.eth_price_usd, 2500.0);
+    }
+
+    #[tokio::test]
+    async fn test_historical_quotes_are_aged_against_their_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            // Nothing listens here, so the market context stays offline
+            rpc_url: "http://127.0.0.1:1".to_string(),
+            rpc_max_retries: 0,
+            cache_path: dir.path().join("pools.json").to_string_lossy().into_owned(),
+            max_staleness_blocks: Some(5),
+            ..Config::default()
+        }
+        .at_block(18_000_000);
+
+        let snapshot = Aggregator::new(config.clone()).await.unwrap();
+        let weth = snapshot.tokens().resolve("WETH").unwrap();
+        let usdc = snapshot.tokens().resolve("USDC").unwrap();
+        snapshot.pool_manager.insert_pool(PoolInfo {
+            address: Address::from_low_u64_be(100),
+            token0: usdc,
+            token1: weth,
+            reserve0: U256::from(2_000_000u64) * U256::exp10(6),
+            reserve1: U256::from(1_000u64) * U256::exp10(18),
+            fee_bps: 30,
+            dex_name: "Uniswap".to_string(),
+            last_updated: 18_000_000,
+            block_hash: Some(H256::from_low_u64_be(1)),
+            liquidity_usd: None,
+        });
+        snapshot.export_cache(&config.cache_path).unwrap();
+
+        let aggregator = Aggregator::new(config).await.unwrap();
+        assert!(aggregator.refresh_market_context().await.is_err());
+        assert_eq!(aggregator.market_context().block_number, 18_000_000);
+        let quote = aggregator
+            .get_best_quote(weth, usdc, U256::exp10(18), OptimizationStrategy::Price)
+            .unwrap();
+        assert_eq!(quote.staleness_blocks, Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_staleness_limit_needs_the_chain_head() {
+        let config = Config {
+            rpc_url: "http://127.0.0.1:1".to_string(),
+            rpc_max_retries: 0,
+            cache_path: "./does-not-exist.json".to_string(),
+            ..Config::default()
+        };
+        let aggregator = Aggregator::new(config.clone()).await.unwrap();
+        let weth = aggregator.tokens().resolve("WETH").unwrap();
+        let usdc = aggregator.tokens().resolve("USDC").unwrap();
+        aggregator.pool_manager.insert_pool(PoolInfo {
+            address: Address::from_low_u64_be(100),
+            token0: usdc,
+            token1: weth,
+            reserve0: U256::from(2_000_000u64) * U256::exp10(6),
+            reserve1: U256::from(1_000u64) * U256::exp10(18),
+            fee_bps: 30,
+            dex_name: "Uniswap".to_string(),
+            last_updated: 18_000_000,
+            block_hash: None,
+            liquidity_usd: None,
+        });
+        assert!(aggregator.refresh_market_context().await.is_err());
+
+        // Offline, the newest cached pool stands in for the head only when nothing is limited
+        let quote = aggregator
+            .get_best_quote(weth, usdc, U256::exp10(18), OptimizationStrategy::Price)
+            .unwrap();
+        assert_eq!(quote.staleness_blocks, Some(0));
+
+        for reject_stale_quotes in [false, true] {
+            let limited = Aggregator::new(Config {
+                max_staleness_blocks: Some(5),
+                reject_stale_quotes,
+                ..config.clone()
+            })
+            .await
+            .unwrap();
+            limited.pool_manager.insert_pool(aggregator.pool_manager.get_all_pools().remove(0));
+            assert!(limited.refresh_market_context().await.is_err());
+
+            let err = limited
+                .get_best_quote(weth, usdc, U256::exp10(18), OptimizationStrategy::Price)
+                .unwrap_err();
+            assert!(matches!(err, AggregatorError::RpcError(_)), "{}", err);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_requests_accept_usd_amounts() {
+        let config = Config {
+            rpc_url: "http://127.0.0.1:1".to_string(),
+            rpc_max_retries: 0,
+            cache_path: "./does-not-exist.json".to_string(),
+            eth_price_usd: Some(2000.0),
+            ..Config::default()
+        };
+        let aggregator = Aggregator::new(config).await.unwrap();
+        let weth = aggregator.tokens().resolve("WETH").unwrap();
+        let usdc = aggregator.tokens().resolve("USDC").unwrap();
+        aggregator.pool_manager.insert_pool(PoolInfo {
+            address: Address::from_low_u64_be(100),
+            token0: usdc,
+            token1: weth,
+            reserve0: U256::from(2_000_000u64) * U256::exp10(6),
+            reserve1: U256::from(1_000u64) * U256::exp10(18),
+            fee_bps: 30,
+            dex_name: "Uniswap".to_string(),
+            last_updated: 18_000_000,
+            block_hash: None,
+            liquidity_usd: None,
+        });
+
+        // $5000 of WETH at 2000 USD is 2.5 WETH
+        let requests = vec![
+            aggregator.quote_request("WETH", "USDC", "$5000", None).await.unwrap(),
+            aggregator.quote_request("USDC", "WETH", "1_000", None).await.unwrap(),
+        ];
+        assert_eq!(requests[0].amount_in, U256::from(25) * U256::exp10(17));
+        assert!(aggregator.quote_request("WETH", "USDC", "max", None).await.is_err());
+
+        let quotes = aggregator.quote_batch(&requests, OptimizationStrategy::Price).unwrap();
+        assert_eq!(quotes[0].as_ref().unwrap().amount_in, requests[0].amount_in);
+        assert!(quotes[1].is_ok());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil on PATH and FORK_URL pointing at a mainnet RPC"]
+    async fn test_ensure_pools_for_discovers_pairs() {
+        use ethers::utils::Anvil;
+
+        let fork_url = std::env::var("FORK_URL").expect("FORK_URL not set");
+        let anvil = Anvil::new().fork(fork_url).spawn();
+        let config = Config {
+            rpc_url: anvil.endpoint(),
+            cache_path: "./does-not-exist.json".to_string(),
+            ..Config::default()
+        };
+        let aggregator = Aggregator::new(config).await.unwrap();
+
+        let weth = aggregator.tokens().resolve("WETH").unwrap();
+        let usdc = aggregator.tokens().resolve("USDC").unwrap();
+        let pools = aggregator.ensure_pools_for(&[weth, usdc]).await.unwrap();
+
+        // Uniswap's pair is found with CREATE2, SushiSwap's through getPair
+        let pair: Address = "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc".parse().unwrap();
+        assert!(pools.iter().any(|pool| pool.address == pair));
+        assert!(pools.iter().any(|pool| pool.dex_name == "SushiSwap"));
+        assert!(aggregator.ensure_pools_for(&[weth, usdc]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil on PATH and FORK_URL pointing at a mainnet RPC"]
+    async fn test_fetch_at_block_reads_past_reserves() {
+        use ethers::signers::Signer;
+        use ethers::utils::Anvil;
+
+        let fork_url = std::env::var("FORK_URL").expect("FORK_URL not set");
+        let anvil = Anvil::new().fork(fork_url).spawn();
+        let config = Config {
+            rpc_url: anvil.endpoint(),
+            cache_path: "./does-not-exist.json".to_string(),
+            ..Config::default()
+        };
+        let aggregator = Aggregator::new(config.clone()).await.unwrap();
+        aggregator.refresh_market_context().await.unwrap();
+
+        let pair: Address = "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc".parse().unwrap();
+        let before = aggregator.fetch_pool(pair, "Uniswap".to_string()).await.unwrap();
+
+        // Swap through the pair so the next block has different reserves
+        let weth = aggregator.tokens().resolve("WETH").unwrap();
+        let usdc = aggregator.tokens().resolve("USDC").unwrap();
+        let wallet = LocalWallet::from(anvil.keys()[0].clone()).with_chain_id(anvil.chain_id());
+        let executor = aggregator.swap_executor(wallet);
+        let quote = aggregator
+            .get_best_quote(weth, usdc, U256::exp10(18), OptimizationStrategy::Price)
+            .unwrap();
+        let swap = aggregator
+            .swap_tx_builder(executor.address())
+            .unwrap()
+            .eth_in(true)
+            .build(&quote)
+            .unwrap();
+        executor.execute(&swap, quote.amount_out, false).await.unwrap();
+
+        let historical = Config {
+            max_staleness_blocks: Some(5),
+            ..config.at_block(before.last_updated)
+        };
+        let historical = Aggregator::new(historical).await.unwrap();
+        let pool = historical.fetch_pool(pair, "Uniswap".to_string()).await.unwrap();
+        assert_eq!(pool.snapshot_block(), before.snapshot_block());
+        assert_eq!((pool.reserve0, pool.reserve1), (before.reserve0, before.reserve1));
+        let context = historical.refresh_market_context().await.unwrap();
+        assert_eq!(context.block_number, before.last_updated);
+        // Aged against the snapshot's block rather than the head, so still routable
+        let quote = historical
+            .get_best_quote(weth, usdc, U256::exp10(18), OptimizationStrategy::Price)
+            .unwrap();
+        assert_eq!(quote.staleness_blocks, Some(0));
+
+        let latest = aggregator.fetch_pool(pair, "Uniswap".to_string()).await.unwrap();
+        assert!(latest.last_updated > before.last_updated);
+        assert_ne!((latest.reserve0, latest.reserve1), (before.reserve0, before.reserve1));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil on PATH and FORK_URL pointing at a mainnet RPC"]
+    async fn test_sync_rolls_back_reorged_reserves() {
+        use ethers::providers::{Http, Provider};
+        use ethers::signers::Signer;
+        use ethers::utils::Anvil;
+
+        let fork_url = std::env::var("FORK_URL").expect("FORK_URL not set");
+        let anvil = Anvil::new().fork(fork_url).spawn();
+        let node = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        let config = Config {
+            rpc_url: anvil.endpoint(),
+            cache_path: "./does-not-exist.json".to_string(),
+            ..Config::default()
+        };
+        let aggregator = Aggregator::new(config).await.unwrap();
+        aggregator.refresh_market_context().await.unwrap();
+
+        let pair: Address = "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc".parse().unwrap();
+        let before = aggregator.fetch_pool(pair, "Uniswap".to_string()).await.unwrap();
+        let snapshot: U256 = node.request("evm_snapshot", ()).await.unwrap();
+
+        let weth = aggregator.tokens().resolve("WETH").unwrap();
+        let usdc = aggregator.tokens().resolve("USDC").unwrap();
+        let wallet = LocalWallet::from(anvil.keys()[0].clone()).with_chain_id(anvil.chain_id());
+        let executor = aggregator.swap_executor(wallet);
+        let quote = aggregator
+            .get_best_quote(weth, usdc, U256::exp10(18), OptimizationStrategy::Price)
+            .unwrap();
+        let swap = aggregator
+            .swap_tx_builder(executor.address())
+            .unwrap()
+            .eth_in(true)
+            .build(&quote)
+            .unwrap();
+        executor.execute(&swap, quote.amount_out, false).await.unwrap();
+
+        let report = aggregator.sync_reserves().await.unwrap();
+        assert!(report.events > 0 && report.reorg.is_none());
+        assert_ne!(aggregator.pool_manager.get_pool(&pair).unwrap().reserve0, before.reserve0);
+
+        // Replace the swap's block with an empty one
+        let _: bool = node.request("evm_revert", [snapshot]).await.unwrap();
+        let _: String = node.request("evm_mine", ()).await.unwrap();
+
+        let report = aggregator.sync_reserves().await.unwrap();
+        let reorg = report.reorg.unwrap();
+        assert_eq!(Some(reorg.ancestor), before.snapshot_block());
+        assert_eq!(reorg.pools_rolled_back, 1);
+        assert_eq!(aggregator.pool_manager.get_pool(&pair).unwrap().reserve0, before.reserve0);
+    }
 }
@@ -1,10 +1,19 @@
 use clap::{Parser, Subcommand};
 use colored::*;
 use comfy_table::{presets::UTF8_FULL, Table};
+use ethers::types::Address;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use once_cell::sync::Lazy;
 use rust_aggregator::{
-    utils, Aggregator, Config, OptimizationStrategy, Result,
+    execute, pools, utils, Aggregator, AggregatorError, Config, FetchEvent, FetchFailure, FetchReport,
+    OptimizationStrategy, Result, SnapshotBlock, TokenRegistry,
 };
-use tracing::Level;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::Write;
+use std::sync::Mutex;
+use tracing::{warn, Level};
+use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::FmtSubscriber;
 
 #[derive(Parser)]
@@ -22,6 +31,18 @@ struct Cli {
     /// Output as JSON
     #[arg(short, long, global = true)]
     json: bool,
+
+    /// Extra token-list JSON file; may be repeated, later lists override earlier ones
+    #[arg(long, global = true)]
+    token_list: Vec<String>,
+
+    /// TOML config file (defaults to $DEX_CONFIG); environment variables override it
+    #[arg(long, global = true)]
+    config: Option<String>,
+
+    /// Chain preset: ethereum, polygon, arbitrum, optimism, base or bsc (defaults to $CHAIN)
+    #[arg(long, global = true)]
+    chain: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -39,6 +60,28 @@ enum Commands {
         /// Maximum number of pools to fetch
         #[arg(long)]
         limit: Option<usize>,
+
+        /// Pools fetched at the same time (defaults to FETCH_CONCURRENCY or 8)
+        #[arg(long)]
+        concurrency: Option<usize>,
+
+        /// Continue from the checkpoint of an interrupted fetch instead of starting over
+        #[arg(long)]
+        resume: bool,
+
+        /// Keep the pairs with the most USD liquidity instead of the first by index; reads every
+        /// pair's reserves with multicall
+        #[arg(long, conflicts_with_all = ["resume", "seed"])]
+        ranked: bool,
+
+        /// Fetch the top pairs of a ranked seed list instead: a JSON object mapping DEX names to
+        /// pair addresses, most liquid first
+        #[arg(long, conflicts_with = "resume")]
+        seed: Option<String>,
+
+        /// Fetch pools as of this past block into a snapshot of their own (needs an archive node)
+        #[arg(long)]
+        block: Option<u64>,
     },
 
     /// Fetch pools from all supported DEXes
@@ -46,8 +89,34 @@ enum Commands {
         /// Maximum number of pools to fetch per DEX
         #[arg(long, default_value = "100")]
         limit: usize,
+
+        /// Pools fetched at the same time (defaults to FETCH_CONCURRENCY or 8)
+        #[arg(long)]
+        concurrency: Option<usize>,
+
+        /// Continue from the checkpoint of an interrupted fetch instead of starting over
+        #[arg(long)]
+        resume: bool,
+
+        /// Keep the pairs with the most USD liquidity instead of the first by index; reads every
+        /// pair's reserves with multicall
+        #[arg(long, conflicts_with_all = ["resume", "seed"])]
+        ranked: bool,
+
+        /// Fetch the top pairs of a ranked seed list instead: a JSON object mapping DEX names to
+        /// pair addresses, most liquid first
+        #[arg(long, conflicts_with = "resume")]
+        seed: Option<String>,
+
+        /// Fetch pools as of this past block into a snapshot of their own (needs an archive node)
+        #[arg(long)]
+        block: Option<u64>,
     },
 
+    /// Update cached reserves to the latest block from the pools' Sync events, rolling back
+    /// blocks a reorg replaced
+    Sync,
+
     /// Get best swap quote
     Quote {
         /// Input token address or symbol
@@ -56,7 +125,7 @@ enum Commands {
         /// Output token address or symbol
         token_out: String,
 
-        /// Amount to swap
+        /// Amount to swap (e.g. 1.5, 1_000, 1e6, $5000 or max)
         amount: String,
 
         /// Optimization strategy
@@ -67,9 +136,171 @@ enum Commands {
         #[arg(long)]
         refresh: bool,
 
+        /// Look up and fetch the pools between the two tokens and the connector tokens first
+        #[arg(long)]
+        discover: bool,
+
         /// Show top N alternative routes for comparison
         #[arg(long)]
         show_alternatives: Option<usize>,
+
+        /// Check the quote against each router's on-chain getAmountsOut
+        #[arg(long)]
+        verify: bool,
+
+        /// Execute the route in a local EVM fork (requires the `simulation` feature)
+        #[arg(long)]
+        simulate: bool,
+
+        /// Write the best quote as JSON, for comparing with `receipt --quote` later
+        #[arg(long)]
+        save_quote: Option<String>,
+
+        /// Account whose balance an amount of `max` refers to
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Quote against the pool snapshot fetched with `fetch-pools --block`
+        #[arg(long, conflicts_with_all = ["verify", "simulate"])]
+        block: Option<u64>,
+
+        /// Leave pools more than this many blocks behind the head out of routing (defaults to
+        /// MAX_STALENESS_BLOCKS)
+        #[arg(long)]
+        max_staleness_blocks: Option<u64>,
+
+        /// Fail if the best route uses pools older than the staleness limit instead of routing
+        /// around them (defaults to REJECT_STALE_QUOTES)
+        #[arg(long)]
+        reject_stale: bool,
+    },
+
+    /// Build a ready-to-sign router transaction for the best single-DEX route
+    BuildTx {
+        /// Input token address or symbol
+        token_in: String,
+
+        /// Output token address or symbol
+        token_out: String,
+
+        /// Amount to swap (e.g. 1.5, 1_000, 1e6, $5000 or max)
+        amount: String,
+
+        /// Recipient of the output tokens
+        #[arg(long)]
+        recipient: String,
+
+        /// Sender address to include in the transaction
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Slippage tolerance in basis points (defaults to DEFAULT_SLIPPAGE_BPS)
+        #[arg(long, value_parser = parse_slippage_bps)]
+        slippage_bps: Option<u32>,
+
+        /// Seconds until the transaction expires (defaults to TX_DEADLINE_SECONDS)
+        #[arg(long)]
+        deadline_secs: Option<u64>,
+
+        /// Pay with native ETH (input must be WETH)
+        #[arg(long)]
+        eth_in: bool,
+
+        /// Receive native ETH (output must be WETH)
+        #[arg(long)]
+        eth_out: bool,
+
+        /// Optimization strategy
+        #[arg(long, default_value = "balanced")]
+        optimize: String,
+    },
+
+    /// Sign and submit a swap through the best single-DEX route
+    Swap {
+        /// Input token address or symbol
+        token_in: String,
+
+        /// Output token address or symbol
+        token_out: String,
+
+        /// Amount to swap (e.g. 1.5, 1_000, 1e6, $5000 or max)
+        amount: String,
+
+        /// Encrypted JSON keystore (password read from KEYSTORE_PASSWORD)
+        #[arg(long)]
+        keystore: Option<String>,
+
+        /// Environment variable holding a hex private key, used without --keystore
+        #[arg(long, default_value = "PRIVATE_KEY")]
+        private_key_env: String,
+
+        /// Recipient of the output tokens (defaults to the signer)
+        #[arg(long)]
+        recipient: Option<String>,
+
+        /// Slippage tolerance in basis points (defaults to DEFAULT_SLIPPAGE_BPS)
+        #[arg(long, value_parser = parse_slippage_bps)]
+        slippage_bps: Option<u32>,
+
+        /// Seconds until the transaction expires (defaults to TX_DEADLINE_SECONDS)
+        #[arg(long)]
+        deadline_secs: Option<u64>,
+
+        /// Pay with native ETH (input must be WETH)
+        #[arg(long)]
+        eth_in: bool,
+
+        /// Receive native ETH (output must be WETH)
+        #[arg(long)]
+        eth_out: bool,
+
+        /// Send an ERC20 approve for the router if the allowance is too low
+        #[arg(long)]
+        approve: bool,
+
+        /// Evaluate the swap with eth_call without sending anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Optimization strategy
+        #[arg(long, default_value = "balanced")]
+        optimize: String,
+    },
+
+    /// Decode a mined swap and compare it with a saved quote
+    Receipt {
+        /// Transaction hash
+        tx_hash: String,
+
+        /// Quote saved with `quote --save-quote`
+        #[arg(long)]
+        quote: Option<String>,
+    },
+
+    /// Quote many swaps from a CSV or JSONL file, streaming NDJSON results
+    QuoteBatch {
+        /// Input file (.csv with token_in,token_out,amount or .jsonl with the same keys)
+        #[arg(long)]
+        input: String,
+
+        /// Account whose balance an amount of `max` refers to
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Optimization strategy
+        #[arg(long, default_value = "balanced")]
+        optimize: String,
+    },
+
+    /// Find arbitrage cycles through WETH across cached pools
+    Arb {
+        /// Minimum net profit in ETH
+        #[arg(long, default_value = "0")]
+        min_profit: f64,
+
+        /// Maximum number of opportunities to show
+        #[arg(long, default_value = "10")]
+        limit: usize,
     },
 
     /// List cached pools
@@ -77,6 +308,14 @@ enum Commands {
         /// Filter by token address
         #[arg(long)]
         token: Option<String>,
+
+        /// Only show pools with at least this much liquidity in USD
+        #[arg(long)]
+        min_tvl: Option<f64>,
+
+        /// Sort by USD liquidity, deepest first
+        #[arg(long)]
+        sort_by_tvl: bool,
     },
 
     /// Cache management
@@ -90,16 +329,14 @@ enum Commands {
 enum CacheAction {
     /// Export cache to file
     Export {
-        /// Output file path
-        #[arg(default_value = "./cache/pools.json")]
-        path: String,
+        /// Output file path (defaults to the chain's cache path)
+        path: Option<String>,
     },
 
     /// Import cache from file
     Import {
-        /// Input file path
-        #[arg(default_value = "./cache/pools.json")]
-        path: String,
+        /// Input file path (defaults to the chain's cache path)
+        path: Option<String>,
     },
 
     /// Show cache statistics
@@ -119,20 +356,42 @@ async fn main() {
         .with_max_level(level)
         .with_target(false)
         .without_time()
+        .with_writer(LogWriter)
         .finish();
     tracing::subscriber::set_global_default(subscriber).expect("Failed to set subscriber");
 
     // Load configuration
-    let config = match Config::from_env() {
+    let mut config = match Config::load(cli.config.as_deref(), cli.chain.as_deref()) {
         Ok(cfg) => cfg,
         Err(e) => {
             eprintln!("{} {}", "Error:".red().bold(), e);
-            eprintln!("\nPlease create a .env file with your RPC_URL.");
-            eprintln!("See .env.example for reference.");
+            eprintln!("\nPlease create a .env file with your RPC_URL (or <CHAIN>_RPC_URL for --chain), or pass a config file with --config.");
+            eprintln!("See the Configuration section of the README for reference.");
             std::process::exit(1);
         }
     };
 
+    config.token_lists.extend(cli.token_list.iter().cloned());
+    if let Commands::FetchPools { concurrency: Some(concurrency), .. }
+    | Commands::FetchAllDexes { concurrency: Some(concurrency), .. } = &cli.command
+    {
+        config.fetch_concurrency = *concurrency;
+    }
+    if let Commands::Quote { max_staleness_blocks, reject_stale, .. } = &cli.command {
+        config.max_staleness_blocks = max_staleness_blocks.or(config.max_staleness_blocks);
+        config.reject_stale_quotes |= *reject_stale;
+    }
+    if let Commands::FetchPools { block: Some(block), .. }
+    | Commands::FetchAllDexes { block: Some(block), .. }
+    | Commands::Quote { block: Some(block), .. } = &cli.command
+    {
+        config = config.at_block(*block);
+        if matches!(cli.command, Commands::Quote { discover: false, .. }) && !std::path::Path::new(&config.cache_path).exists() {
+            eprintln!("{} No pool snapshot at block {}; fetch one with fetch-pools --block {}", "Error:".red().bold(), block, block);
+            std::process::exit(1);
+        }
+    }
+
     // Create aggregator
     let aggregator = match Aggregator::new(config).await {
         Ok(agg) => agg,
@@ -144,21 +403,93 @@ async fn main() {
 
     // Execute command
     let result = match cli.command {
-        Commands::FetchPools { factory, name, limit } => {
-            handle_fetch_pools(&aggregator, &factory, &name, limit, cli.json).await
+        Commands::FetchPools { factory, name, limit, resume, ranked, seed, .. } => {
+            async {
+                let selection = Selection::new(resume, ranked, seed.as_deref())?;
+                save_on_interrupt(&aggregator, handle_fetch_pools(&aggregator, &factory, &name, limit, &selection, cli.json)).await
+            }
+            .await
         }
-        Commands::FetchAllDexes { limit } => {
-            handle_fetch_all_dexes(&aggregator, limit, cli.json).await
+        Commands::FetchAllDexes { limit, resume, ranked, seed, .. } => {
+            async {
+                let selection = Selection::new(resume, ranked, seed.as_deref())?;
+                save_on_interrupt(&aggregator, handle_fetch_all_dexes(&aggregator, limit, &selection, cli.json)).await
+            }
+            .await
         }
+        Commands::Sync => handle_sync(&aggregator, cli.json).await,
         Commands::Quote {
             token_in,
             token_out,
             amount,
             optimize,
             refresh,
+            discover,
             show_alternatives,
-        } => handle_quote(&aggregator, &token_in, &token_out, &amount, &optimize, refresh, show_alternatives, cli.json).await,
-        Commands::ListPools { token } => handle_list_pools(&aggregator, token.as_deref(), cli.json),
+            verify,
+            simulate,
+            save_quote,
+            from,
+            ..
+        } => handle_quote(&aggregator, &token_in, &token_out, &amount, &optimize, refresh, discover, show_alternatives, verify, simulate, save_quote.as_deref(), from.as_deref(), cli.json).await,
+        Commands::BuildTx {
+            token_in,
+            token_out,
+            amount,
+            recipient,
+            from,
+            slippage_bps,
+            deadline_secs,
+            eth_in,
+            eth_out,
+            optimize,
+        } => {
+            let options = BuildTxOptions {
+                recipient,
+                from,
+                slippage_bps,
+                deadline_secs,
+                eth_in,
+                eth_out,
+            };
+            handle_build_tx(&aggregator, &token_in, &token_out, &amount, &optimize, options, cli.json).await
+        }
+        Commands::Swap {
+            token_in,
+            token_out,
+            amount,
+            keystore,
+            private_key_env,
+            recipient,
+            slippage_bps,
+            deadline_secs,
+            eth_in,
+            eth_out,
+            approve,
+            dry_run,
+            optimize,
+        } => {
+            let options = SwapOptions {
+                keystore,
+                private_key_env,
+                recipient,
+                slippage_bps,
+                deadline_secs,
+                eth_in,
+                eth_out,
+                approve,
+                dry_run,
+            };
+            handle_swap(&aggregator, &token_in, &token_out, &amount, &optimize, options, cli.json).await
+        }
+        Commands::Receipt { tx_hash, quote } => handle_receipt(&aggregator, &tx_hash, quote.as_deref(), cli.json).await,
+        Commands::QuoteBatch { input, from, optimize } => {
+            handle_quote_batch(&aggregator, &input, from.as_deref(), &optimize).await
+        }
+        Commands::Arb { min_profit, limit } => handle_arb(&aggregator, min_profit, limit, cli.json).await,
+        Commands::ListPools { token, min_tvl, sort_by_tvl } => {
+            handle_list_pools(&aggregator, token.as_deref(), min_tvl, sort_by_tvl, cli.json)
+        }
         Commands::Cache { action } => handle_cache(&aggregator, action, cli.json),
     };
 
@@ -168,41 +499,137 @@ async fn main() {
     }
 }
 
+/// How the fetch commands pick the pairs to fetch
+enum Selection {
+    /// The first pairs by factory index, optionally continuing from the saved checkpoint
+    Index { resume: bool },
+    /// The most liquid pairs, found by scanning the whole factory
+    Ranked,
+    /// The first pairs of each DEX's seed list
+    Seeded(HashMap<String, Vec<Address>>),
+}
+
+impl Selection {
+    fn new(resume: bool, ranked: bool, seed: Option<&str>) -> Result<Self> {
+        Ok(match seed {
+            Some(path) => Selection::Seeded(pools::load_seed_list(path)?),
+            None if ranked => Selection::Ranked,
+            None => Selection::Index { resume },
+        })
+    }
+
+    /// Checkpoint a fetch continues from
+    fn resume_point(&self, aggregator: &Aggregator, factory: &Address) -> Option<usize> {
+        match self {
+            Selection::Index { resume: true } => aggregator.fetch_checkpoint(factory).map(|checkpoint| checkpoint.next_index),
+            _ => None,
+        }
+    }
+}
+
+/// Fetch a DEX's pools at `block`, picking the pairs as `selection` says
+async fn fetch_dex(
+    aggregator: &Aggregator,
+    selection: &Selection,
+    factory: Address,
+    dex_name: &str,
+    limit: Option<usize>,
+    block: SnapshotBlock,
+    progress: &FetchProgress,
+) -> Result<FetchReport> {
+    let progress = &|event: &FetchEvent| progress.handle(event);
+    match selection {
+        Selection::Index { resume } => {
+            aggregator.fetch_pools_with_progress(factory, dex_name.to_string(), limit, *resume, block, progress).await
+        }
+        Selection::Ranked => {
+            aggregator.fetch_top_pools(factory, dex_name.to_string(), limit.unwrap_or(usize::MAX), block, progress).await
+        }
+        Selection::Seeded(seeds) => {
+            let pairs = seeds
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(dex_name))
+                .map(|(_, pairs)| pairs.as_slice())
+                .ok_or_else(|| AggregatorError::ConfigError(format!("Seed list has no pairs for {}", dex_name)))?;
+            aggregator.fetch_seeded_pools(factory, dex_name.to_string(), pairs, limit, block, progress).await
+        }
+    }
+}
+
+/// Run a fetch; on Ctrl-C, save the pools fetched so far with their checkpoint and exit
+async fn save_on_interrupt(aggregator: &Aggregator, fetch: impl Future<Output = Result<()>>) -> Result<()> {
+    tokio::select! {
+        result = fetch => result,
+        _ = tokio::signal::ctrl_c() => {
+            PROGRESS_BARS.clear().ok();
+            let cache_path = &aggregator.get_config().cache_path;
+            aggregator.export_cache(cache_path)?;
+            eprintln!(
+                "{} Interrupted; fetched pools saved to {}. Run the command again with --resume to continue.",
+                "!".bright_yellow(),
+                cache_path
+            );
+            std::process::exit(130);
+        }
+    }
+}
+
 async fn handle_fetch_pools(
     aggregator: &Aggregator,
     factory: &str,
     name: &str,
     limit: Option<usize>,
+    selection: &Selection,
     json_output: bool,
 ) -> Result<()> {
     let factory_addr = utils::parse_address(factory)?;
 
     if !json_output {
         println!("\n{}", "━".repeat(60).bright_cyan());
-        println!("{}  {}", "".to_string(), "Fetching Pool Data".bright_cyan().bold());
+        println!("  {}", "Fetching Pool Data".bright_cyan().bold());
         println!("{}", "━".repeat(60).bright_cyan());
         println!("  DEX:     {}", name.bright_white().bold());
         println!("  Factory: {}", factory.bright_black());
         println!("  Limit:   {}", limit.map(|l| l.to_string()).unwrap_or_else(|| "All".to_string()).bright_black());
+        match selection {
+            Selection::Ranked => println!("  Select:  {}", "most liquid".bright_black()),
+            Selection::Seeded(_) => println!("  Select:  {}", "seed list".bright_black()),
+            Selection::Index { .. } => {
+                if let Some(next_index) = selection.resume_point(aggregator, &factory_addr) {
+                    println!("  Resume:  {}", format!("from pair {}", next_index).bright_black());
+                }
+            }
+        }
         println!();
     }
 
-    let pools = aggregator.fetch_pools(factory_addr, name.to_string(), limit).await?;
+    let progress = FetchProgress::new(json_output);
+    let block = aggregator.pinned_block().await?;
+    let report = fetch_dex(aggregator, selection, factory_addr, name, limit, block, &progress).await?;
 
     // Export to cache
-    aggregator.export_cache("./cache/pools.json")?;
+    let cache_path = &aggregator.get_config().cache_path;
+    aggregator.export_cache(cache_path)?;
 
     if json_output {
         let output = serde_json::json!({
-            "success": true,
-            "pools_fetched": pools.len(),
+            "success": report.failures.is_empty(),
+            "pools_fetched": report.pools.len(),
             "dex": name,
+            "block": report.block,
+            "resumed_from": report.resumed_from,
+            "failed": report.failures,
+            "rpc_endpoints": aggregator.rpc_stats(),
         });
         println!("{}", serde_json::to_string_pretty(&output).unwrap());
     } else {
-        println!("{} {}", "".to_string(), "Success!".bright_green().bold());
-        println!("  Pools fetched: {}", pools.len().to_string().bright_yellow().bold());
-        println!("  Cache saved:   {}", "./cache/pools.json".bright_cyan());
+        if report.failures.is_empty() {
+            println!(" {}", "Success!".bright_green().bold());
+        }
+        println!("  Pools fetched: {}", report.pools.len().to_string().bright_yellow().bold());
+        println!("  Block:         {} {}", report.block.number, format!("({:?})", report.block.hash).bright_black());
+        println!("  Cache saved:   {}", cache_path.bright_cyan());
+        print_fetch_failures(aggregator, std::slice::from_ref(&report));
         println!("{}", "━".repeat(60).bright_cyan());
         println!();
     }
@@ -210,14 +637,45 @@ async fn handle_fetch_pools(
     Ok(())
 }
 
+async fn handle_sync(aggregator: &Aggregator, json_output: bool) -> Result<()> {
+    let report = aggregator.sync_reserves().await?;
+    let cache_path = &aggregator.get_config().cache_path;
+    aggregator.export_cache(cache_path)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        return Ok(());
+    }
+
+    println!();
+    if let Some(reorg) = report.reorg {
+        println!(
+            " {} block {} was replaced; rolled back {} pools to block {}",
+            "Reorg:".bright_yellow().bold(),
+            reorg.orphaned.number,
+            reorg.pools_rolled_back,
+            reorg.ancestor.number
+        );
+    }
+    println!(" {}", "Synced!".bright_green().bold());
+    println!("  Blocks:        {} → {}", report.from.number, report.to.number);
+    println!("  Sync events:   {}", report.events.to_string().bright_yellow().bold());
+    println!("  Pools updated: {}", report.pools_updated.len().to_string().bright_yellow().bold());
+    println!("  Cache saved:   {}", cache_path.bright_cyan());
+    println!();
+
+    Ok(())
+}
+
 async fn handle_fetch_all_dexes(
     aggregator: &Aggregator,
     limit: usize,
+    selection: &Selection,
     json_output: bool,
 ) -> Result<()> {
     if !json_output {
         println!("\n{}", "━".repeat(60).bright_cyan());
-        println!("{}  {}", "".to_string(), "Fetching Pools from All DEXes".bright_cyan().bold());
+        println!("  {}", "Fetching Pools from All DEXes".bright_cyan().bold());
         println!("{}", "━".repeat(60).bright_cyan());
         println!();
     }
@@ -225,24 +683,58 @@ async fn handle_fetch_all_dexes(
     let factories = aggregator.get_config().get_all_factories();
     let mut total_fetched = 0;
     let mut dex_results = Vec::new();
+    let mut reports = Vec::new();
+    let progress = FetchProgress::new(json_output);
+    // Every DEX is read at the same block, so their pools can be routed through together
+    let block = aggregator.pinned_block().await?;
 
     for (dex_name, factory_addr) in factories {
         if !json_output {
-            println!("  {} Fetching from {}...", "→".bright_yellow(), dex_name.bright_white().bold());
+            match selection.resume_point(aggregator, &factory_addr) {
+                Some(next_index) => println!(
+                    "  {} Fetching from {} (resuming from pair {})...",
+                    "→".bright_yellow(),
+                    dex_name.bright_white().bold(),
+                    next_index
+                ),
+                None => println!("  {} Fetching from {}...", "→".bright_yellow(), dex_name.bright_white().bold()),
+            }
         }
 
-        match aggregator.fetch_pools(factory_addr, dex_name.clone(), Some(limit)).await {
-            Ok(pools) => {
-                let count = pools.len();
+        let fetched = fetch_dex(aggregator, selection, factory_addr, &dex_name, Some(limit), block, &progress).await;
+        match fetched {
+            Ok(report) => {
+                let count = report.pools.len();
                 total_fetched += count;
-                dex_results.push((dex_name.clone(), count, true));
-                
+                dex_results.push(serde_json::json!({
+                    "dex": dex_name,
+                    "pools_fetched": count,
+                    "block": report.block,
+                    "resumed_from": report.resumed_from,
+                    "success": report.failures.is_empty(),
+                    "failed": report.failures,
+                }));
+
                 if !json_output {
-                    println!("    {} {} pools", "✓".bright_green(), count.to_string().bright_yellow());
+                    println!(
+                        "    {} {} pools at block {}",
+                        "✓".bright_green(),
+                        count.to_string().bright_yellow(),
+                        report.block.number
+                    );
+                    if !report.failures.is_empty() {
+                        println!("    {} {} could not be fetched", "!".bright_yellow(), report.failures.len().to_string().bright_red());
+                    }
                 }
+                reports.push(report);
             }
             Err(e) => {
-                dex_results.push((dex_name.clone(), 0, false));
+                dex_results.push(serde_json::json!({
+                    "dex": dex_name,
+                    "pools_fetched": 0,
+                    "success": false,
+                    "error": e.to_string(),
+                }));
                 if !json_output {
                     println!("    {} Failed: {}", "✗".bright_red(), e.to_string().bright_red());
                 }
@@ -251,28 +743,23 @@ async fn handle_fetch_all_dexes(
     }
 
     // Export to cache
-    aggregator.export_cache("./cache/pools.json")?;
+    let cache_path = &aggregator.get_config().cache_path;
+    aggregator.export_cache(cache_path)?;
 
     if json_output {
-        let results: Vec<_> = dex_results.iter().map(|(name, count, success)| {
-            serde_json::json!({
-                "dex": name,
-                "pools_fetched": count,
-                "success": success
-            })
-        }).collect();
-        
         let output = serde_json::json!({
-            "success": true,
+            "success": dex_results.iter().all(|result| result["success"] == true),
             "total_pools": total_fetched,
-            "dexes": results,
+            "dexes": dex_results,
+            "rpc_endpoints": aggregator.rpc_stats(),
         });
         println!("{}", serde_json::to_string_pretty(&output).unwrap());
     } else {
         println!();
-        println!("{} {}", "".to_string(), "Summary".bright_green().bold());
+        println!(" {}", "Summary".bright_green().bold());
         println!("  Total pools fetched: {}", total_fetched.to_string().bright_yellow().bold());
-        println!("  Cache saved:         {}", "./cache/pools.json".bright_cyan());
+        println!("  Cache saved:         {}", cache_path.bright_cyan());
+        print_fetch_failures(aggregator, &reports);
         println!("{}", "━".repeat(60).bright_cyan());
         println!();
     }
@@ -280,6 +767,149 @@ async fn handle_fetch_all_dexes(
     Ok(())
 }
 
+/// Progress bars on stderr; log lines are printed above them instead of through them
+static PROGRESS_BARS: Lazy<MultiProgress> = Lazy::new(MultiProgress::new);
+
+/// Stderr writer for tracing that hides the progress bars while a log line is written
+#[derive(Clone, Copy)]
+struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        PROGRESS_BARS.suspend(|| std::io::stderr().write_all(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stderr().flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogWriter {
+    type Writer = LogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        *self
+    }
+}
+
+/// Shows fetch events as a progress bar per DEX, or with --json as JSON lines on stderr
+struct FetchProgress {
+    json_output: bool,
+    bar: Mutex<Option<ProgressBar>>,
+    /// Last whole percent reported in JSON mode
+    percent: Mutex<usize>,
+}
+
+impl FetchProgress {
+    fn new(json_output: bool) -> Self {
+        Self {
+            json_output,
+            bar: Mutex::new(None),
+            percent: Mutex::new(0),
+        }
+    }
+
+    fn handle(&self, event: &FetchEvent) {
+        if self.json_output {
+            self.emit_json(event);
+            return;
+        }
+
+        let mut bar = self.bar.lock().unwrap();
+        match event {
+            FetchEvent::Started { dex, total } => {
+                let style = ProgressStyle::with_template(
+                    "    {prefix:<12} [{bar:30.cyan/blue}] {pos}/{len} pools ({eta} left) {msg}",
+                )
+                .unwrap()
+                .progress_chars("=> ");
+                let new_bar = ProgressBar::new(*total as u64).with_style(style).with_prefix(dex.clone());
+                *bar = Some(PROGRESS_BARS.add(new_bar));
+            }
+            FetchEvent::Progress { pass: 0, done, failed, .. } => {
+                if let Some(bar) = bar.as_ref() {
+                    bar.set_position(*done as u64);
+                    if *failed > 0 {
+                        bar.set_message(format!("{} failed", failed));
+                    }
+                }
+            }
+            FetchEvent::Progress { done, total, .. } => {
+                if let Some(bar) = bar.as_ref() {
+                    bar.set_message(format!("retried {}/{}", done, total));
+                }
+            }
+            FetchEvent::Retrying { pass, pending, .. } => {
+                if let Some(bar) = bar.as_ref() {
+                    bar.set_message(format!("retrying {} (pass {})", pending, pass));
+                }
+            }
+            // The caller prints the per-DEX result in place of the bar
+            FetchEvent::Finished { .. } => {
+                if let Some(bar) = bar.take() {
+                    bar.finish_and_clear();
+                }
+            }
+        }
+    }
+
+    /// Write an event as one JSON line, at most one progress event per percent
+    fn emit_json(&self, event: &FetchEvent) {
+        let mut percent = self.percent.lock().unwrap();
+        match event {
+            FetchEvent::Started { .. } | FetchEvent::Retrying { .. } => *percent = 0,
+            FetchEvent::Progress { done, total, .. } => {
+                let now = done * 100 / (*total).max(1);
+                if now <= *percent && done < total {
+                    return;
+                }
+                *percent = now;
+            }
+            FetchEvent::Finished { .. } => {}
+        }
+        eprintln!("{}", serde_json::to_string(event).unwrap());
+    }
+}
+
+/// Pools that could not be fetched after every retry, and the health of each RPC endpoint
+fn print_fetch_failures(aggregator: &Aggregator, reports: &[FetchReport]) {
+    const MAX_LISTED: usize = 10;
+
+    let failures: Vec<(&str, &FetchFailure)> = reports
+        .iter()
+        .flat_map(|report| report.failures.iter().map(move |failure| (report.dex_name.as_str(), failure)))
+        .collect();
+    let stats = aggregator.rpc_stats();
+
+    if !failures.is_empty() {
+        println!();
+        println!(" {}", "Not fetched".bright_red().bold());
+        for (dex_name, failure) in failures.iter().take(MAX_LISTED) {
+            println!("  {} #{}: {}", dex_name.bright_white(), failure.index, failure.error.bright_black());
+        }
+        if failures.len() > MAX_LISTED {
+            println!("  ... and {} more", failures.len() - MAX_LISTED);
+        }
+    }
+
+    if !failures.is_empty() || stats.len() > 1 || stats.iter().any(|endpoint| endpoint.failures > 0) {
+        println!();
+        println!(" {}", "RPC endpoints".bright_white().bold());
+        for endpoint in &stats {
+            let health = if endpoint.healthy { "healthy".bright_green() } else { "failing".bright_red() };
+            println!(
+                "  {} {} requests, {} failed ({})",
+                endpoint.endpoint.bright_cyan(),
+                endpoint.requests,
+                endpoint.failures,
+                health
+            );
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn handle_quote(
     aggregator: &Aggregator,
     token_in: &str,
@@ -287,9 +917,15 @@ async fn handle_quote(
     amount_str: &str,
     optimize: &str,
     refresh: bool,
+    discover: bool,
     show_alternatives: Option<usize>,
+    verify: bool,
+    simulate: bool,
+    save_quote: Option<&str>,
+    from: Option<&str>,
     json_output: bool,
 ) -> Result<()> {
+    let tokens = aggregator.tokens();
     // Refresh pools if requested
     if refresh {
         if !json_output {
@@ -297,10 +933,13 @@ async fn handle_quote(
         }
         
         let factories = aggregator.get_config().get_all_factories();
+        let block = aggregator.pinned_block().await?;
         for (dex_name, factory_addr) in factories {
-            let _ = aggregator.fetch_pools(factory_addr, dex_name, Some(100)).await;
+            let _ = aggregator
+                .fetch_pools_with_progress(factory_addr, dex_name, Some(100), false, block, &|_| {})
+                .await;
         }
-        aggregator.export_cache("./cache/pools.json")?;
+        aggregator.export_cache(&aggregator.get_config().cache_path)?;
         
         if !json_output {
             println!("{} Pool data refreshed!\n", "✓".bright_green());
@@ -308,21 +947,26 @@ async fn handle_quote(
     }
 
     // Parse token symbols or addresses
-    let token_in_addr = utils::parse_token(token_in)?;
-    let token_out_addr = utils::parse_token(token_out)?;
+    let token_in_addr = tokens.resolve(token_in)?;
+    let token_out_addr = tokens.resolve(token_out)?;
+
+    if discover {
+        let discovered = aggregator.ensure_pools_for(&[token_in_addr, token_out_addr]).await?;
+        if !discovered.is_empty() {
+            aggregator.export_cache(&aggregator.get_config().cache_path)?;
+        }
+        if !json_output {
+            println!("\n{} Discovered {} new pools", "✓".bright_green(), discovered.len());
+        }
+    }
 
-    // Get token decimals for proper parsing
-    let token_in_decimals = utils::get_token_decimals(token_in_addr);
-    let amount_in = utils::parse_token_amount(amount_str, token_in_decimals)?;
+    refresh_market(aggregator).await;
+
+    let from = from.map(utils::parse_address).transpose()?;
+    let amount_in = aggregator.parse_amount(token_in_addr, amount_str, from).await?;
 
     // Parse optimization strategy
-    let strategy = match optimize.to_lowercase().as_str() {
-        "price" => OptimizationStrategy::Price,
-        "gas" => OptimizationStrategy::Gas,
-        "slippage" => OptimizationStrategy::Slippage,
-        "balanced" => OptimizationStrategy::Balanced,
-        _ => OptimizationStrategy::Balanced,
-    };
+    let strategy = parse_strategy(optimize);
 
     if !json_output {
         println!();
@@ -332,37 +976,76 @@ async fn handle_quote(
         println!("  {:<20} {:?}", "STRATEGY".bright_white().bold(), strategy);
         println!("  {:<20} {} → {}", 
             "PAIR".bright_white().bold(),
-            utils::get_token_symbol(token_in_addr).bright_cyan(),
-            utils::get_token_symbol(token_out_addr).bright_cyan()
+            tokens.symbol(token_in_addr).bright_cyan(),
+            tokens.symbol(token_out_addr).bright_cyan()
         );
         println!();
     }
 
     // Get top N quotes if alternatives requested, otherwise just get best
     let limit = show_alternatives.map(|n| n + 1).unwrap_or(1); // +1 to include best route
-    let quotes = aggregator.get_top_quotes(token_in_addr, token_out_addr, amount_in, strategy, limit)?;
+    let mut quotes = aggregator.get_top_quotes(token_in_addr, token_out_addr, amount_in, strategy, limit)?;
+
+    if simulate {
+        simulate_quote(aggregator, &mut quotes[0]).await?;
+    }
+
     let quote = &quotes[0]; // Best quote
 
+    if let Some(path) = save_quote {
+        let contents = serde_json::to_string_pretty(quote)
+            .map_err(|e| AggregatorError::ParseError(format!("Failed to serialize quote: {}", e)))?;
+        std::fs::write(path, contents)
+            .map_err(|e| AggregatorError::ParseError(format!("Failed to write {}: {}", path, e)))?;
+    }
+
+    let verification = if verify {
+        Some(aggregator.verify_quote(quote).await?)
+    } else {
+        None
+    };
+
     if json_output {
-        let output = serde_json::json!({
+        let mut output = serde_json::json!({
             "token_in": format!("{:?}", quote.token_in),
             "token_out": format!("{:?}", quote.token_out),
             "amount_in": quote.amount_in.to_string(),
             "amount_out": quote.amount_out.to_string(),
-            "rate": quote.exchange_rate(),
+            "execution_price": quote.execution_price(tokens).ok(),
+            "inverse_price": quote.inverse_price(tokens).ok(),
             "hops": quote.hop_count(),
             "gas_estimate": quote.gas_estimate.to_string(),
             "price_impact_bps": quote.price_impact_bps,
             "route": quote.description,
+            "block": quote.block,
+            "oldest_block": quote.oldest_block,
+            "staleness_blocks": quote.staleness_blocks,
         });
+        if let Some(verification) = &verification {
+            output["verification"] = serde_json::to_value(verification).unwrap();
+        }
+        if let (Some(amount_out), Some(gas_used)) = (quote.simulated_amount_out, quote.simulated_gas_used) {
+            output["simulation"] = serde_json::json!({
+                "amount_out": amount_out.to_string(),
+                "gas_used": gas_used,
+            });
+        }
         println!("{}", serde_json::to_string_pretty(&output).unwrap());
     } else {
-        print_quote(quote);
+        print_quote(tokens, quote);
+
+        if let Some(verification) = &verification {
+            print_verification(tokens, verification, token_out_addr);
+        }
+
+        if quote.simulated_amount_out.is_some() {
+            print_simulation(tokens, quote);
+        }
 
         // Print alternative routes if requested
         if let Some(alt_count) = show_alternatives {
             if quotes.len() > 1 {
-                print_alternative_routes(&quotes[1..], alt_count, token_in_addr, token_out_addr);
+                print_alternative_routes(tokens, &quotes[1..], alt_count, token_in_addr, token_out_addr);
             }
         }
     }
@@ -370,20 +1053,557 @@ async fn handle_quote(
     Ok(())
 }
 
-fn handle_list_pools(aggregator: &Aggregator, token_filter: Option<&str>, json_output: bool) -> Result<()> {
-    let pools = if let Some(token_str) = token_filter {
-        let token_addr = utils::parse_address(token_str)?;
+#[cfg(feature = "simulation")]
+async fn simulate_quote(aggregator: &Aggregator, quote: &mut rust_aggregator::RouteQuote) -> Result<()> {
+    aggregator.simulate_quote(quote).await?;
+    Ok(())
+}
+
+#[cfg(not(feature = "simulation"))]
+async fn simulate_quote(_aggregator: &Aggregator, _quote: &mut rust_aggregator::RouteQuote) -> Result<()> {
+    Err(AggregatorError::ConfigError(
+        "--simulate requires building with `--features simulation`".to_string(),
+    ))
+}
+
+/// Transaction options for build-tx
+struct BuildTxOptions {
+    recipient: String,
+    from: Option<String>,
+    slippage_bps: Option<u32>,
+    deadline_secs: Option<u64>,
+    eth_in: bool,
+    eth_out: bool,
+}
+
+async fn handle_build_tx(
+    aggregator: &Aggregator,
+    token_in: &str,
+    token_out: &str,
+    amount_str: &str,
+    optimize: &str,
+    options: BuildTxOptions,
+    json_output: bool,
+) -> Result<()> {
+    let tokens = aggregator.tokens();
+    refresh_market(aggregator).await;
+
+    let token_in_addr = tokens.resolve(token_in)?;
+    let token_out_addr = tokens.resolve(token_out)?;
+    let recipient = utils::parse_address(&options.recipient)?;
+    let from = options.from.as_deref().map(utils::parse_address).transpose()?;
+    if options.eth_in && amount_str.trim().eq_ignore_ascii_case("max") {
+        return Err(AggregatorError::InvalidAmount("\"max\" can't be used with --eth-in; give an explicit amount".to_string()));
+    }
+    let amount_in = aggregator.parse_amount(token_in_addr, amount_str, from).await?;
+
+    let mut builder = aggregator
+        .swap_tx_builder(recipient)?
+        .eth_in(options.eth_in)
+        .eth_out(options.eth_out);
+    if let Some(slippage_bps) = options.slippage_bps {
+        builder = builder.slippage_bps(slippage_bps);
+    }
+    if let Some(deadline_secs) = options.deadline_secs {
+        builder = builder.deadline_secs(deadline_secs);
+    }
+
+    let quotes = aggregator.get_top_quotes(token_in_addr, token_out_addr, amount_in, parse_strategy(optimize), 20)?;
+    let (_, swap_tx) = select_swap(&builder, &quotes)?;
+
+    let chain_id = aggregator.get_config().chain_id;
+    let typed_tx = swap_tx.to_typed_transaction(from, chain_id, &aggregator.market_context());
+    let tx_json = serde_json::json!({
+        "chain_id": chain_id,
+        "swap": swap_tx,
+        "tx": typed_tx,
+    });
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&tx_json).unwrap());
+        return Ok(());
+    }
+
+    let token_out_decimals = tokens.display_decimals(token_out_addr);
+    let path_symbols: Vec<String> = swap_tx.path.iter().map(|addr| tokens.symbol(*addr)).collect();
+
+    println!();
+    println!("{}", "═".repeat(70).bright_green());
+    println!("{:^70}", "SWAP TRANSACTION".bright_green().bold());
+    println!("{}", "═".repeat(70).bright_green());
+    println!();
+    println!("  {:<20} {}", "METHOD".bright_white().bold(), swap_tx.method.bright_cyan());
+    println!("  {:<20} {} ({:?})", "ROUTER".bright_white().bold(), swap_tx.dex_name.bright_yellow(), swap_tx.to);
+    println!("  {:<20} {}", "PATH".bright_white().bold(), path_symbols.join(" → ").bright_cyan());
+    println!("  {:<20} {} {}",
+        "MIN OUTPUT".bright_white().bold(),
+        utils::format_token_amount(swap_tx.amount_out_min, token_out_decimals).bright_green(),
+        tokens.symbol(token_out_addr).bright_green()
+    );
+    println!("  {:<20} {:?}", "RECIPIENT".bright_white().bold(), swap_tx.recipient);
+    println!("  {:<20} {}", "DEADLINE".bright_white().bold(), swap_tx.deadline);
+    println!();
+    println!("{}", serde_json::to_string_pretty(&tx_json["tx"]).unwrap());
+    println!();
+
+    Ok(())
+}
+
+/// Take the best route one router can execute; report the best route's error otherwise
+fn select_swap<'a>(
+    builder: &rust_aggregator::SwapTxBuilder,
+    quotes: &'a [rust_aggregator::RouteQuote],
+) -> Result<(&'a rust_aggregator::RouteQuote, rust_aggregator::SwapTransaction)> {
+    match quotes.iter().find_map(|quote| builder.build(quote).ok().map(|tx| (quote, tx))) {
+        Some(selected) => Ok(selected),
+        None => Err(builder.build(&quotes[0]).unwrap_err()),
+    }
+}
+
+/// Signing and safety options for swap
+struct SwapOptions {
+    keystore: Option<String>,
+    private_key_env: String,
+    recipient: Option<String>,
+    slippage_bps: Option<u32>,
+    deadline_secs: Option<u64>,
+    eth_in: bool,
+    eth_out: bool,
+    approve: bool,
+    dry_run: bool,
+}
+
+async fn handle_swap(
+    aggregator: &Aggregator,
+    token_in: &str,
+    token_out: &str,
+    amount_str: &str,
+    optimize: &str,
+    options: SwapOptions,
+    json_output: bool,
+) -> Result<()> {
+    let tokens = aggregator.tokens();
+    // Fees for a signed transaction must come from the live chain, never the offline fallback
+    aggregator.refresh_market_context().await?;
+
+    let token_in_addr = tokens.resolve(token_in)?;
+    let token_out_addr = tokens.resolve(token_out)?;
+
+    let wallet = execute::load_wallet(
+        options.keystore.as_deref(),
+        &options.private_key_env,
+        aggregator.get_config().chain_id,
+    )?;
+    let executor = aggregator.swap_executor(wallet).approve(options.approve);
+
+    // With --eth-in the input is native ETH, whose full balance would leave nothing for gas
+    if options.eth_in && amount_str.trim().eq_ignore_ascii_case("max") {
+        return Err(AggregatorError::InvalidAmount("\"max\" can't be used with --eth-in; give an explicit amount".to_string()));
+    }
+    let amount_in = aggregator.parse_amount(token_in_addr, amount_str, Some(executor.address())).await?;
+    let recipient = match options.recipient.as_deref() {
+        Some(recipient) => utils::parse_address(recipient)?,
+        None => executor.address(),
+    };
+
+    let mut builder = aggregator
+        .swap_tx_builder(recipient)?
+        .eth_in(options.eth_in)
+        .eth_out(options.eth_out);
+    if let Some(slippage_bps) = options.slippage_bps {
+        builder = builder.slippage_bps(slippage_bps);
+    }
+    if let Some(deadline_secs) = options.deadline_secs {
+        builder = builder.deadline_secs(deadline_secs);
+    }
+
+    let quotes = aggregator.get_top_quotes(token_in_addr, token_out_addr, amount_in, parse_strategy(optimize), 20)?;
+    let (quote, swap_tx) = select_swap(&builder, &quotes)?;
+
+    if !json_output {
+        let path_symbols: Vec<String> = swap_tx.path.iter().map(|addr| tokens.symbol(*addr)).collect();
+        println!("\n{} {} {} via {}...",
+            if options.dry_run { "Dry-running" } else { "Executing" }.bright_cyan(),
+            path_symbols.join(" → ").bright_white(),
+            format!("({})", swap_tx.method).bright_black(),
+            swap_tx.dex_name.bright_yellow()
+        );
+    }
+
+    let execution = executor.execute(&swap_tx, quote.amount_out, options.dry_run).await?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&execution).unwrap());
+        return Ok(());
+    }
+
+    let token_out_decimals = tokens.display_decimals(token_out_addr);
+    let token_out_symbol = tokens.symbol(token_out_addr);
+    let format_out = |amount| format!("{} {}", utils::format_token_amount(amount, token_out_decimals), token_out_symbol);
+
+    println!();
+    println!("{}", "═".repeat(70).bright_green());
+    println!("{:^70}", if execution.dry_run { "SWAP DRY RUN" } else { "SWAP EXECUTED" }.bright_green().bold());
+    println!("{}", "═".repeat(70).bright_green());
+    println!();
+    println!("  {:<20} {:?}", "FROM".bright_white().bold(), execution.from);
+    println!("  {:<20} {:?}", "RECIPIENT".bright_white().bold(), swap_tx.recipient);
+    if execution.approval_required {
+        let approval = match execution.approval_tx_hash {
+            Some(hash) => format!("{:?}", hash),
+            None => "required (not sent)".to_string(),
+        };
+        println!("  {:<20} {}", "APPROVAL".bright_white().bold(), approval.bright_yellow());
+    }
+    if let Some(hash) = execution.tx_hash {
+        println!("  {:<20} {:?}", "TX HASH".bright_white().bold(), hash);
+    }
+    if let Some(block) = execution.block_number {
+        println!("  {:<20} {}", "BLOCK".bright_white().bold(), block);
+    }
+    if let Some(gas_used) = execution.gas_used {
+        println!("  {:<20} {}", "GAS USED".bright_white().bold(), gas_used);
+    }
+    println!("  {:<20} {}", "QUOTED OUTPUT".bright_white().bold(), format_out(execution.quoted_amount_out).bright_white());
+    println!("  {:<20} {}", "MIN OUTPUT".bright_white().bold(), format_out(execution.amount_out_min).bright_white());
+    if let (Some(realized), Some(deviation)) = (execution.realized_amount_out, execution.deviation_bps) {
+        println!("  {:<20} {} {}",
+            "REALIZED OUTPUT".bright_white().bold(),
+            format_out(realized).bright_green().bold(),
+            format!("({:+} bps)", deviation).bright_black()
+        );
+    }
+    println!();
+
+    Ok(())
+}
+
+async fn handle_receipt(aggregator: &Aggregator, tx_hash: &str, quote_path: Option<&str>, json_output: bool) -> Result<()> {
+    let tokens = aggregator.tokens();
+    let tx_hash: ethers::types::H256 = tx_hash
+        .parse()
+        .map_err(|e| AggregatorError::ParseError(format!("Invalid transaction hash {}: {}", tx_hash, e)))?;
+
+    let quote: Option<rust_aggregator::RouteQuote> = quote_path
+        .map(|path| {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| AggregatorError::ParseError(format!("Failed to read {}: {}", path, e)))?;
+            serde_json::from_str(&contents)
+                .map_err(|e| AggregatorError::ParseError(format!("Invalid quote in {}: {}", path, e)))
+        })
+        .transpose()?;
+
+    let executed = aggregator.decode_receipt(tx_hash).await?;
+    let diff = quote.as_ref().map(|quote| executed.diff(quote, tokens));
+
+    if json_output {
+        let mut output = serde_json::to_value(&executed).unwrap();
+        if let Some(diff) = &diff {
+            output["diff"] = serde_json::to_value(diff).unwrap();
+        }
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+        return Ok(());
+    }
+
+    let route = &executed.route;
+    let token_in_decimals = tokens.display_decimals(route.token_in);
+    let token_out_decimals = tokens.display_decimals(route.token_out);
+    let token_out_symbol = tokens.symbol(route.token_out);
+
+    println!();
+    println!("{}", "═".repeat(70).bright_green());
+    println!("{:^70}", "EXECUTED SWAP".bright_green().bold());
+    println!("{}", "═".repeat(70).bright_green());
+    println!();
+    println!("  {:<20} {:?}", "TX HASH".bright_white().bold(), executed.tx_hash);
+    println!("  {:<20} {}", "STATUS".bright_white().bold(),
+        if executed.success { "success".bright_green() } else { "reverted".bright_red() }
+    );
+    if let Some(block) = executed.block_number {
+        println!("  {:<20} {}", "BLOCK".bright_white().bold(), block);
+    }
+    println!("  {:<20} {}", "GAS USED".bright_white().bold(), executed.gas_used);
+    println!();
+
+    for hop in &route.hops {
+        println!("  {} {} → {}",
+            hop.dex_name.bright_yellow().bold(),
+            format!("{} {}",
+                tokens.format_amount(hop.token_in, hop.amount_in),
+                tokens.symbol(hop.token_in)
+            ).bright_white(),
+            format!("{} {}",
+                tokens.format_amount(hop.token_out, hop.amount_out),
+                tokens.symbol(hop.token_out)
+            ).bright_cyan()
+        );
+        println!("  {}", format!("pool {:?}", hop.pool).bright_black());
+    }
+    println!();
+    println!("  {:<20} {} {}",
+        "INPUT".bright_white().bold(),
+        utils::format_token_amount(route.amount_in, token_in_decimals).bright_white(),
+        tokens.symbol(route.token_in).bright_cyan()
+    );
+    println!("  {:<20} {} {}",
+        "OUTPUT".bright_white().bold(),
+        utils::format_token_amount(route.amount_out, token_out_decimals).bright_green().bold(),
+        token_out_symbol.bright_cyan()
+    );
+    println!();
+
+    if let Some(diff) = &diff {
+        println!("{}", "─".repeat(70).bright_black());
+        println!("{:^70}", "QUOTE VS EXECUTION".bright_blue().bold());
+        println!("{}", "─".repeat(70).bright_black());
+        println!();
+        println!("  {:<20} {}", "PATH".bright_white().bold(),
+            if diff.path_matches { "as quoted".bright_green() } else { "differs from quote".bright_red() }
+        );
+        println!("  {:<20} {} {}",
+            "QUOTED OUTPUT".bright_white().bold(),
+            utils::format_token_amount(diff.expected_amount_out, token_out_decimals).bright_white(),
+            token_out_symbol.bright_cyan()
+        );
+        println!("  {:<20} {} {} {}",
+            "SHORTFALL".bright_white().bold(),
+            utils::format_token_amount(diff.shortfall, token_out_decimals).bright_white(),
+            token_out_symbol.bright_cyan(),
+            format!("({:+} bps)", diff.deviation_bps).bright_black()
+        );
+        println!("  {:<20} {} vs {} estimated", "GAS".bright_white().bold(), diff.gas_used, diff.gas_estimate);
+        if let (Some(quoted), Some(effective)) = (diff.quoted_price, diff.effective_price) {
+            println!("  {:<20} {:.6} quoted, {:.6} executed", "PRICE".bright_white().bold(), quoted, effective);
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+async fn handle_quote_batch(aggregator: &Aggregator, input: &str, from: Option<&str>, optimize: &str) -> Result<()> {
+    let tokens = aggregator.tokens();
+    refresh_market(aggregator).await;
+
+    let owner = from.map(utils::parse_address).transpose()?;
+    let contents = std::fs::read_to_string(input)
+        .map_err(|e| AggregatorError::ParseError(format!("Failed to read {}: {}", input, e)))?;
+    let is_csv = input.to_lowercase().ends_with(".csv");
+    let strategy = parse_strategy(optimize);
+
+    // One graph snapshot for the whole batch
+    let graph = aggregator.route_graph()?;
+
+    for (idx, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if is_csv && line.to_lowercase().starts_with("token_in") {
+            continue; // Header row
+        }
+
+        let fields = if is_csv { parse_csv_fields(line) } else { parse_jsonl_fields(line) };
+        let parsed = match fields {
+            Ok((token_in, token_out, amount)) => aggregator.quote_request(&token_in, &token_out, &amount, owner).await,
+            Err(e) => Err(e),
+        };
+
+        let output = match parsed.and_then(|request| {
+            aggregator
+                .quote_with_graph(&graph, &request, strategy)
+                .map(|quote| (request, quote))
+        }) {
+            Ok((request, quote)) => serde_json::json!({
+                "line": idx + 1,
+                "token_in": format!("{:?}", request.token_in),
+                "token_out": format!("{:?}", request.token_out),
+                "amount_in": request.amount_in.to_string(),
+                "amount_out": quote.amount_out.to_string(),
+                "execution_price": quote.execution_price(tokens).ok(),
+                "inverse_price": quote.inverse_price(tokens).ok(),
+                "hops": quote.hop_count(),
+                "gas_estimate": quote.gas_estimate.to_string(),
+                "price_impact_bps": quote.price_impact_bps,
+                "route": quote.description,
+            }),
+            Err(e) => serde_json::json!({
+                "line": idx + 1,
+                "error": e.to_string(),
+            }),
+        };
+
+        println!("{}", output);
+    }
+
+    Ok(())
+}
+
+/// Split a `token_in,token_out,amount` CSV row
+fn parse_csv_fields(line: &str) -> Result<(String, String, String)> {
+    let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+    if fields.len() != 3 {
+        return Err(AggregatorError::ParseError(format!(
+            "Expected token_in,token_out,amount but got: {}",
+            line
+        )));
+    }
+
+    Ok((fields[0].to_string(), fields[1].to_string(), fields[2].to_string()))
+}
+
+/// Read the fields of a `{"token_in": ..., "token_out": ..., "amount": ...}` JSON line
+fn parse_jsonl_fields(line: &str) -> Result<(String, String, String)> {
+    let value: serde_json::Value = serde_json::from_str(line)
+        .map_err(|e| AggregatorError::ParseError(format!("Invalid JSON: {}", e)))?;
+
+    let field = |key: &str| -> Result<String> {
+        match value.get(key) {
+            Some(serde_json::Value::String(s)) => Ok(s.clone()),
+            Some(serde_json::Value::Number(n)) => Ok(n.to_string()),
+            _ => Err(AggregatorError::ParseError(format!("Missing field: {}", key))),
+        }
+    };
+
+    Ok((field("token_in")?, field("token_out")?, field("amount")?))
+}
+
+/// Refresh gas price and block number from the chain, falling back to offline values
+async fn refresh_market(aggregator: &Aggregator) {
+    if let Err(e) = aggregator.refresh_market_context().await {
+        warn!("Using offline market context: {}", e);
+    }
+}
+
+fn parse_strategy(optimize: &str) -> OptimizationStrategy {
+    match optimize.to_lowercase().as_str() {
+        "price" => OptimizationStrategy::Price,
+        "gas" => OptimizationStrategy::Gas,
+        "slippage" => OptimizationStrategy::Slippage,
+        "balanced" => OptimizationStrategy::Balanced,
+        _ => OptimizationStrategy::Balanced,
+    }
+}
+
+/// Slippage tolerance in basis points, at most 10000 (100%)
+fn parse_slippage_bps(value: &str) -> Result<u32> {
+    let slippage_bps: u32 = value
+        .parse()
+        .map_err(|_| AggregatorError::InvalidAmount(format!("Invalid slippage: {}", value)))?;
+    if slippage_bps > 10000 {
+        return Err(AggregatorError::InvalidAmount(format!(
+            "Slippage of {} bps exceeds 10000 (100%)",
+            slippage_bps
+        )));
+    }
+    Ok(slippage_bps)
+}
+
+async fn handle_arb(aggregator: &Aggregator, min_profit: f64, limit: usize, json_output: bool) -> Result<()> {
+    let tokens = aggregator.tokens();
+    refresh_market(aggregator).await;
+    let opportunities = aggregator.find_arbitrage(min_profit, limit)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&opportunities).unwrap());
+        return Ok(());
+    }
+
+    println!();
+    println!("{}", "═".repeat(70).bright_cyan());
+    println!("{:^70}", "ARBITRAGE OPPORTUNITIES".bright_cyan().bold());
+    println!("{}", "═".repeat(70).bright_cyan());
+    println!();
+
+    if opportunities.is_empty() {
+        println!("  {}", "No profitable cycles found".bright_yellow());
+        println!();
+        return Ok(());
+    }
+
+    for (idx, opportunity) in opportunities.iter().enumerate() {
+        let route_symbols: Vec<String> = opportunity
+            .tokens
+            .iter()
+            .map(|addr| tokens.symbol(*addr))
+            .collect();
+
+        println!("  {} {}",
+            format!("#{}", idx + 1).bright_white().bold(),
+            route_symbols.join(" → ").bright_cyan()
+        );
+        println!("  {:<18} {} {}",
+            "INPUT".bright_white().bold(),
+            utils::format_token_amount(opportunity.amount_in, 18).bright_cyan(),
+            tokens.wrapped_native_symbol()
+        );
+        println!("  {:<18} {} {}",
+            "OUTPUT".bright_white().bold(),
+            utils::format_token_amount(opportunity.amount_out, 18).bright_green(),
+            tokens.wrapped_native_symbol()
+        );
+        println!("  {:<18} {} {} ({} gas)",
+            "GAS COST".bright_white().bold(),
+            format!("{:.6}", opportunity.gas_cost_eth).bright_yellow(),
+            tokens.native_symbol(),
+            opportunity.gas_estimate
+        );
+        println!("  {:<18} {} {}",
+            "NET PROFIT".bright_white().bold(),
+            format!("{:.6}", opportunity.profit_eth).bright_green().bold(),
+            tokens.native_symbol()
+        );
+
+        for hop in &opportunity.hops {
+            let hop_token_in = tokens.symbol(hop.token_in);
+            let hop_token_out = tokens.symbol(hop.token_out);
+            println!("  {:<18} {} {} {} {} {} {}",
+                "".to_string(),
+                tokens.format_amount(hop.token_in, hop.amount_in).bright_white(),
+                hop_token_in.bright_cyan(),
+                "→".bright_black(),
+                tokens.format_amount(hop.token_out, hop.amount_out).bright_white(),
+                hop_token_out.bright_cyan(),
+                format!("via {} {:?}", hop.dex_name, hop.pool).bright_black()
+            );
+        }
+        println!();
+    }
+
+    println!("{}", "═".repeat(70).bright_cyan());
+    println!();
+
+    Ok(())
+}
+
+fn handle_list_pools(
+    aggregator: &Aggregator,
+    token_filter: Option<&str>,
+    min_tvl: Option<f64>,
+    sort_by_tvl: bool,
+    json_output: bool,
+) -> Result<()> {
+    let tokens = aggregator.tokens();
+    let mut pools = if let Some(token_str) = token_filter {
+        let token_addr = tokens.resolve(token_str)?;
         aggregator.get_pools_with_token(token_addr)
     } else {
         aggregator.get_pools()
     };
 
+    if let Some(min_tvl) = min_tvl {
+        pools.retain(|pool| pool.liquidity_usd.is_some_and(|tvl| tvl >= min_tvl));
+    }
+    if sort_by_tvl {
+        pools.sort_by(|a, b| b.liquidity_usd.unwrap_or(-1.0).total_cmp(&a.liquidity_usd.unwrap_or(-1.0)));
+    }
+
     if json_output {
         println!("{}", serde_json::to_string_pretty(&pools).unwrap());
     } else {
         if pools.is_empty() {
             println!("\n{}", "━".repeat(60).bright_yellow());
-            println!("{}  {}", "".to_string(), "No Pools Found".bright_yellow().bold());
+            println!("  {}", "No Pools Found".bright_yellow().bold());
             println!("{}", "━".repeat(60).bright_yellow());
             println!("\n  {}", "Tip: Fetch pools first with:".bright_black());
             println!("  {}", "dex fetch-pools --factory 0x5C69... --limit 100".bright_cyan());
@@ -392,7 +1612,7 @@ fn handle_list_pools(aggregator: &Aggregator, token_filter: Option<&str>, json_o
         }
 
         println!("\n{}", "━".repeat(60).bright_cyan());
-        println!("{}  {} - {} pools", "".to_string(), "Cached Pools".bright_cyan().bold(), pools.len().to_string().bright_yellow().bold());
+        println!("  {} - {} pools", "Cached Pools".bright_cyan().bold(), pools.len().to_string().bright_yellow().bold());
         println!("{}", "━".repeat(60).bright_cyan());
         println!();
 
@@ -404,15 +1624,21 @@ fn handle_list_pools(aggregator: &Aggregator, token_filter: Option<&str>, json_o
             "Token1".bright_white().bold().to_string(),
             "Reserve0".bright_white().bold().to_string(),
             "Reserve1".bright_white().bold().to_string(),
+            "TVL (USD)".bright_white().bold().to_string(),
         ]);
 
         for pool in pools.iter().take(20) {
             table.add_row(vec![
                 pool.dex_name.bright_cyan().to_string(),
-                format!("{:?}", pool.token0)[..10].bright_black().to_string(),
-                format!("{:?}", pool.token1)[..10].bright_black().to_string(),
-                utils::format_token_amount(pool.reserve0, 18).bright_green().to_string(),
-                utils::format_token_amount(pool.reserve1, 18).bright_green().to_string(),
+                tokens.symbol(pool.token0).bright_black().to_string(),
+                tokens.symbol(pool.token1).bright_black().to_string(),
+                tokens.format_amount(pool.token0, pool.reserve0).bright_green().to_string(),
+                tokens.format_amount(pool.token1, pool.reserve1).bright_green().to_string(),
+                pool.liquidity_usd
+                    .map(|tvl| format!("${}", utils::format_with_commas(tvl)))
+                    .unwrap_or_else(|| "-".to_string())
+                    .bright_yellow()
+                    .to_string(),
             ]);
         }
 
@@ -430,19 +1656,21 @@ fn handle_list_pools(aggregator: &Aggregator, token_filter: Option<&str>, json_o
 fn handle_cache(aggregator: &Aggregator, action: CacheAction, json_output: bool) -> Result<()> {
     match action {
         CacheAction::Export { path } => {
+            let path = path.unwrap_or_else(|| aggregator.get_config().cache_path.clone());
             aggregator.export_cache(&path)?;
             if !json_output {
-                println!("\n{} {}", "".to_string(), "Cache Exported".bright_green().bold());
+                println!("\n {}", "Cache Exported".bright_green().bold());
                 println!("  Location: {}", path.bright_cyan());
                 println!();
             }
         }
         CacheAction::Import { path } => {
+            let path = path.unwrap_or_else(|| aggregator.get_config().cache_path.clone());
             let count = aggregator.import_cache(&path)?;
             if json_output {
                 println!("{}", serde_json::json!({"pools_imported": count}));
             } else {
-                println!("\n{} {}", "".to_string(), "Cache Imported".bright_green().bold());
+                println!("\n {}", "Cache Imported".bright_green().bold());
                 println!("  Pools loaded: {}", count.to_string().bright_yellow().bold());
                 println!("  From: {}", path.bright_cyan());
                 println!();
@@ -454,6 +1682,9 @@ fn handle_cache(aggregator: &Aggregator, action: CacheAction, json_output: bool)
                 let output = serde_json::json!({
                     "total_pools": stats.total_pools,
                     "dex_counts": stats.dex_counts,
+                    "total_liquidity_usd": stats.total_liquidity_usd,
+                    "dex_liquidity_usd": stats.dex_liquidity_usd,
+                    "unvalued_pools": stats.unvalued_pools,
                 });
                 println!("{}", serde_json::to_string_pretty(&output).map_err(|e| {
                     rust_aggregator::AggregatorError::Other(anyhow::anyhow!("JSON error: {}", e))
@@ -468,14 +1699,21 @@ fn handle_cache(aggregator: &Aggregator, action: CacheAction, json_output: bool)
                     "TOTAL POOLS".bright_white().bold(),
                     stats.total_pools.to_string().bright_yellow().bold()
                 );
+                println!("  {:<20} {} {}",
+                    "TOTAL LIQUIDITY".bright_white().bold(),
+                    format!("${}", utils::format_with_commas(stats.total_liquidity_usd)).bright_green().bold(),
+                    format!("({} pools unvalued)", stats.unvalued_pools).bright_black()
+                );
                 println!();
                 
                 if !stats.dex_counts.is_empty() {
                     println!("  {}", "BREAKDOWN BY DEX".bright_white().bold());
                     for (dex, count) in stats.dex_counts {
-                        println!("  {:<20} {} pools", 
+                        let liquidity = stats.dex_liquidity_usd.get(&dex).copied().unwrap_or(0.0);
+                        println!("  {:<20} {} pools  {}", 
                             format!("  {}", dex).bright_cyan(),
-                            count.to_string().bright_yellow()
+                            count.to_string().bright_yellow(),
+                            format!("${}", utils::format_with_commas(liquidity)).bright_green()
                         );
                     }
                     println!();
@@ -498,12 +1736,12 @@ fn handle_cache(aggregator: &Aggregator, action: CacheAction, json_output: bool)
     Ok(())
 }
 
-fn print_quote(quote: &rust_aggregator::RouteQuote) {
+fn print_quote(tokens: &TokenRegistry, quote: &rust_aggregator::RouteQuote) {
     // Get decimals and symbols for input and output tokens
-    let token_in_decimals = utils::get_token_decimals(quote.token_in);
-    let token_out_decimals = utils::get_token_decimals(quote.token_out);
-    let token_in_symbol = utils::get_token_symbol(quote.token_in);
-    let token_out_symbol = utils::get_token_symbol(quote.token_out);
+    let token_in_decimals = tokens.display_decimals(quote.token_in);
+    let token_out_decimals = tokens.display_decimals(quote.token_out);
+    let token_in_symbol = tokens.symbol(quote.token_in);
+    let token_out_symbol = tokens.symbol(quote.token_out);
     
     println!();
     println!("{}", "═".repeat(70).bright_green());
@@ -518,8 +1756,8 @@ fn print_quote(quote: &rust_aggregator::RouteQuote) {
         .enumerate()
         .map(|(i, addr)| {
             // Parse address and get symbol
-            let token_addr = utils::parse_address(addr).unwrap_or_else(|_| quote.token_in);
-            let symbol = utils::get_token_symbol(token_addr);
+            let token_addr = utils::parse_address(addr).unwrap_or(quote.token_in);
+            let symbol = tokens.symbol(token_addr);
             
             if i == 0 {
                 format!("{}", symbol.bright_cyan().bold())
@@ -535,6 +1773,26 @@ fn print_quote(quote: &rust_aggregator::RouteQuote) {
         quote.hop_count().to_string().bright_yellow().bold(),
         if quote.hop_count() == 1 { "hop" } else { "hops" }.bright_black()
     );
+    match quote.block {
+        Some(block) => println!("  {:<20} {} {}",
+            "STATE".bright_white().bold(),
+            format!("block {}", block.number).bright_yellow(),
+            format!("({:?})", block.hash).bright_black()
+        ),
+        None => println!("  {:<20} {}", "STATE".bright_white().bold(), "not pinned to one block".bright_black()),
+    }
+    if let Some(oldest_block) = quote.oldest_block {
+        let age = match quote.staleness_blocks {
+            Some(0) => "current".to_string(),
+            Some(age) => format!("{} blocks old", age),
+            None => "age unknown".to_string(),
+        };
+        println!("  {:<20} {} {}",
+            "FRESHNESS".bright_white().bold(),
+            format!("oldest pool at block {}", oldest_block).bright_yellow(),
+            format!("({})", age).bright_black()
+        );
+    }
     println!();
 
     // Show hop-by-hop breakdown for multi-hop routes
@@ -545,23 +1803,16 @@ fn print_quote(quote: &rust_aggregator::RouteQuote) {
         println!();
         
         for (i, hop) in quote.hops.iter().enumerate() {
-            let hop_token_in = utils::get_token_symbol(hop.token_in);
-            let hop_token_out = utils::get_token_symbol(hop.token_out);
-            let hop_token_in_decimals = utils::get_token_decimals(hop.token_in);
-            let hop_token_out_decimals = utils::get_token_decimals(hop.token_out);
+            let hop_token_in = tokens.symbol(hop.token_in);
+            let hop_token_out = tokens.symbol(hop.token_out);
+            let hop_token_in_decimals = tokens.display_decimals(hop.token_in);
+            let hop_token_out_decimals = tokens.display_decimals(hop.token_out);
             
             let hop_amount_in = utils::format_token_amount(hop.amount_in, hop_token_in_decimals);
             let hop_amount_out = utils::format_token_amount(hop.amount_out, hop_token_out_decimals);
             
-            // Calculate hop rate
-            let hop_in_f64 = hop.amount_in.as_u128() as f64 / 10f64.powi(hop_token_in_decimals as i32);
-            let hop_out_f64 = hop.amount_out.as_u128() as f64 / 10f64.powi(hop_token_out_decimals as i32);
-            let hop_rate = if hop_in_f64 > 0.0 {
-                hop_out_f64 / hop_in_f64
-            } else {
-                0.0
-            };
-            
+            let hop_rate = format_price(hop.execution_price(tokens));
+
             println!("  {} {}", 
                 format!("HOP {}", i + 1).bright_white().bold(),
                 format!("{} → {}", hop_token_in, hop_token_out).bright_cyan(),
@@ -576,7 +1827,7 @@ fn print_quote(quote: &rust_aggregator::RouteQuote) {
             );
             println!("  {:<18} {}", 
                 "".to_string(),
-                format!("@ {:.6} {} per {}", hop_rate, hop_token_out, hop_token_in).bright_black()
+                format!("@ {} {} per {}", hop_rate, hop_token_out, hop_token_in).bright_black()
             );
             println!("  {:<18} {} {}", 
                 "".to_string(),
@@ -592,15 +1843,6 @@ fn print_quote(quote: &rust_aggregator::RouteQuote) {
     println!("{}", "─".repeat(70).bright_black());
     println!();
     
-    // Calculate proper exchange rate using decimals
-    let amount_in_f64 = quote.amount_in.as_u128() as f64 / 10f64.powi(token_in_decimals as i32);
-    let amount_out_f64 = quote.amount_out.as_u128() as f64 / 10f64.powi(token_out_decimals as i32);
-    let rate = if amount_in_f64 > 0.0 {
-        amount_out_f64 / amount_in_f64
-    } else {
-        0.0
-    };
-
     println!("  {:<20} {} {}", 
         "INPUT".bright_white().bold(),
         utils::format_token_amount(quote.amount_in, token_in_decimals).bright_cyan().bold(),
@@ -613,7 +1855,11 @@ fn print_quote(quote: &rust_aggregator::RouteQuote) {
     );
     println!("  {:<20} {}", 
         "EXCHANGE RATE".bright_white().bold(), 
-        format!("{:.6} {}/{}", rate, token_out_symbol, token_in_symbol).bright_yellow().bold()
+        format!("{} {}/{}", format_price(quote.execution_price(tokens)), token_out_symbol, token_in_symbol).bright_yellow().bold()
+    );
+    println!("  {:<20} {}",
+        "INVERSE RATE".bright_white().bold(),
+        format!("{} {}/{}", format_price(quote.inverse_price(tokens)), token_in_symbol, token_out_symbol).bright_black()
     );
     println!();
 
@@ -654,7 +1900,80 @@ fn print_quote(quote: &rust_aggregator::RouteQuote) {
     println!();
 }
 
+/// Price with 6 decimal places (6 significant digits below 1), or "n/a" if it couldn't be computed
+fn format_price(price: Result<rust_decimal::Decimal>) -> String {
+    match price {
+        Ok(p) if p < rust_decimal::Decimal::ONE => p.round_sf(6).unwrap_or(p).normalize().to_string(),
+        Ok(p) => format!("{:.6}", p),
+        Err(_) => "n/a".to_string(),
+    }
+}
+
+fn print_simulation(tokens: &TokenRegistry, quote: &rust_aggregator::RouteQuote) {
+    let token_out_decimals = tokens.display_decimals(quote.token_out);
+    let token_out_symbol = tokens.symbol(quote.token_out);
+    let simulated = quote.simulated_amount_out.unwrap_or_default();
+
+    println!("{}", "─".repeat(70).bright_black());
+    println!("{:^70}", "LOCAL SIMULATION".bright_blue().bold());
+    println!("{}", "─".repeat(70).bright_black());
+    println!();
+    println!("  {:<20} {} {}",
+        "SIMULATED OUTPUT".bright_white().bold(),
+        utils::format_token_amount(simulated, token_out_decimals).bright_white(),
+        token_out_symbol.bright_cyan()
+    );
+    println!("  {:<20} {}",
+        "SIMULATED GAS".bright_white().bold(),
+        quote.simulated_gas_used.unwrap_or_default().to_string().bright_white()
+    );
+    println!();
+}
+
+fn print_verification(tokens: &TokenRegistry, verification: &rust_aggregator::QuoteVerification, token_out: ethers::types::Address) {
+    let token_out_decimals = tokens.display_decimals(token_out);
+    let token_out_symbol = tokens.symbol(token_out);
+
+    println!("{}", "─".repeat(70).bright_black());
+    println!("{:^70}", "ON-CHAIN VERIFICATION".bright_blue().bold());
+    println!("{}", "─".repeat(70).bright_black());
+    println!();
+
+    for segment in &verification.segments {
+        let path: Vec<String> = segment.path.iter().map(|addr| tokens.symbol(*addr)).collect();
+        let deviation = format!("{:+} bps", segment.deviation_bps);
+        let colored_deviation = if segment.deviation_bps.unsigned_abs() <= 1 {
+            deviation.bright_green()
+        } else if segment.deviation_bps.unsigned_abs() <= 50 {
+            deviation.bright_yellow()
+        } else {
+            deviation.bright_red()
+        };
+
+        println!("  {} {}",
+            segment.dex_name.bright_yellow().bold(),
+            path.join(" → ").bright_cyan()
+        );
+        println!("  {:<18} {}", "".to_string(), format!("router {:?}", segment.router).bright_black());
+        println!("  {:<18} {}", "".to_string(), colored_deviation.bold());
+        println!();
+    }
+
+    println!("  {:<20} {} {}",
+        "EXPECTED OUTPUT".bright_white().bold(),
+        utils::format_token_amount(verification.expected_amount_out, token_out_decimals).bright_white(),
+        token_out_symbol.bright_cyan()
+    );
+    println!("  {:<20} {} {}",
+        "ON-CHAIN OUTPUT".bright_white().bold(),
+        utils::format_token_amount(verification.onchain_amount_out, token_out_decimals).bright_white(),
+        token_out_symbol.bright_cyan()
+    );
+    println!();
+}
+
 fn print_alternative_routes(
+    tokens: &TokenRegistry,
     alternatives: &[rust_aggregator::RouteQuote],
     limit: usize,
     _token_in: ethers::types::Address,
@@ -662,8 +1981,8 @@ fn print_alternative_routes(
 ) {
     use ethers::types::Address;
     
-    let token_out_decimals = utils::get_token_decimals(token_out);
-    let token_out_symbol = utils::get_token_symbol(token_out);
+    let token_out_decimals = tokens.display_decimals(token_out);
+    let token_out_symbol = tokens.symbol(token_out);
 
     println!("{}", "═".repeat(70).bright_yellow());
     println!("{:^70}", 
@@ -690,7 +2009,7 @@ fn print_alternative_routes(
             .collect();
 
         let route_symbols: Vec<String> = unique_tokens.iter()
-            .map(|addr| utils::get_token_symbol(*addr))
+            .map(|addr| tokens.symbol(*addr))
             .collect();
 
         println!("  {:<18} {}", 
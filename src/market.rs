@@ -0,0 +1,282 @@
+use crate::math;
+use crate::rpc::RpcProvider;
+use crate::tokens::TokenRegistry;
+use crate::types::{AggregatorError, PoolInfo, Result, TokenInfo};
+use ethers::prelude::*;
+use ethers::types::{Address, U256};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Chain state used to build a market context
+#[derive(Debug, Clone)]
+pub struct ChainState {
+    /// Number of the block the state was read at
+    pub block_number: u64,
+
+    /// Effective gas price in wei (base fee + priority fee when available)
+    pub gas_price: U256,
+
+    /// EIP-1559 base fee of the block
+    pub base_fee_per_gas: Option<U256>,
+
+    /// Suggested EIP-1559 priority fee
+    pub priority_fee_per_gas: Option<U256>,
+}
+
+/// Read the block number and gas price at `at` from the provider
+/// Uses base fee + priority fee on EIP-1559 chains, eth_gasPrice otherwise. For a past block the
+/// priority fee is the median tip paid in it, from eth_feeHistory; eth_gasPrice only knows the
+/// present, so past blocks of chains without EIP-1559 are an error
+pub async fn fetch_chain_state(provider: &RpcProvider, at: BlockNumber) -> Result<ChainState> {
+    let block = provider
+        .get_block(at)
+        .await
+        .map_err(|e| AggregatorError::RpcError(format!("Failed to get block {}: {}", at, e)))?
+        .ok_or_else(|| AggregatorError::RpcError(format!("Block {} not available", at)))?;
+
+    let block_number = block.number.map(|n| n.as_u64()).unwrap_or_default();
+
+    if at != BlockNumber::Latest {
+        let base_fee = block.base_fee_per_gas.ok_or_else(|| {
+            AggregatorError::RpcError(format!("Block {} has no base fee to price gas at", block_number))
+        })?;
+        let history = provider
+            .fee_history(1u64, at, &[50.0])
+            .await
+            .map_err(|e| AggregatorError::RpcError(format!("Failed to get fee history at block {}: {}", block_number, e)))?;
+        let priority_fee = history.reward.first().and_then(|rewards| rewards.first()).copied().unwrap_or_default();
+
+        return Ok(ChainState {
+            block_number,
+            gas_price: base_fee.saturating_add(priority_fee),
+            base_fee_per_gas: Some(base_fee),
+            priority_fee_per_gas: Some(priority_fee),
+        });
+    }
+
+    let legacy_gas_price = || async {
+        provider
+            .get_gas_price()
+            .await
+            .map_err(|e| AggregatorError::RpcError(format!("Failed to get gas price: {}", e)))
+    };
+
+    let Some(base_fee) = block.base_fee_per_gas else {
+        return Ok(ChainState {
+            block_number,
+            gas_price: legacy_gas_price().await?,
+            base_fee_per_gas: None,
+            priority_fee_per_gas: None,
+        });
+    };
+
+    // Not every node implements eth_maxPriorityFeePerGas; derive it from eth_gasPrice if needed
+    let priority_fee = match provider
+        .request::<_, U256>("eth_maxPriorityFeePerGas", ())
+        .await
+    {
+        Ok(fee) => fee,
+        Err(e) => {
+            debug!("eth_maxPriorityFeePerGas unavailable: {}", e);
+            legacy_gas_price().await?.saturating_sub(base_fee)
+        }
+    };
+
+    Ok(ChainState {
+        block_number,
+        gas_price: base_fee.saturating_add(priority_fee),
+        base_fee_per_gas: Some(base_fee),
+        priority_fee_per_gas: Some(priority_fee),
+    })
+}
+
+/// Derive the ETH/USD price from the deepest cached WETH/stablecoin pools
+/// Takes the deepest pool for each stablecoin and averages them weighted by stablecoin depth
+pub fn derive_eth_price_usd(pools: &[PoolInfo], weth: Address, stablecoins: &[TokenInfo]) -> Option<f64> {
+    let mut weighted_sum = 0.0;
+    let mut total_weight = 0.0;
+
+    for stable in stablecoins {
+        let stable_decimals = stable.decimals;
+
+        let deepest = pools
+            .iter()
+            .filter_map(|pool| {
+                let (weth_reserve, stable_reserve) = pool.get_reserves(&weth)?;
+                if pool.get_other_token(&weth)? != stable.address || weth_reserve.is_zero() {
+                    return None;
+                }
+
+                let weth_amount = math::to_f64(weth_reserve) / 1e18;
+                let stable_amount =
+                    math::to_f64(stable_reserve) / 10f64.powi(stable_decimals as i32);
+                Some((stable_amount, stable_amount / weth_amount))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+
+        if let Some((depth, price)) = deepest {
+            weighted_sum += price * depth;
+            total_weight += depth;
+        }
+    }
+
+    if total_weight > 0.0 {
+        Some(weighted_sum / total_weight)
+    } else {
+        None
+    }
+}
+
+/// Minimum USD depth of the priced side for a pool to set the price of its other token
+const MIN_PRICING_DEPTH_USD: f64 = 1_000.0;
+
+/// Derive USD prices for every token linked to a stablecoin or WETH through cached pools
+/// Stablecoins are $1 and WETH is `eth_price_usd`; other tokens take their price from the deepest
+/// pool pairing them with an already priced token, tokens fewer hops from an anchor first
+pub fn derive_token_prices_usd(
+    pools: &[PoolInfo],
+    weth: Address,
+    eth_price_usd: f64,
+    tokens: &TokenRegistry,
+) -> HashMap<Address, f64> {
+    let mut prices: HashMap<Address, f64> = tokens
+        .stablecoins()
+        .iter()
+        .map(|stable| (stable.address, 1.0))
+        .collect();
+    prices.insert(weth, eth_price_usd);
+
+    loop {
+        // (depth, price) of the deepest pool pricing each token at this hop distance
+        let mut next: HashMap<Address, (f64, f64)> = HashMap::new();
+
+        for pool in pools {
+            for (priced, unpriced) in [(pool.token0, pool.token1), (pool.token1, pool.token0)] {
+                let Some(priced_usd) = prices.get(&priced).copied() else { continue };
+                if prices.contains_key(&unpriced) {
+                    continue;
+                }
+                let (Some(priced_decimals), Some(unpriced_decimals)) = (tokens.decimals(priced), tokens.decimals(unpriced)) else {
+                    continue;
+                };
+                let Some((priced_reserve, unpriced_reserve)) = pool.get_reserves(&priced) else { continue };
+
+                let depth = math::to_units_f64(priced_reserve, priced_decimals) * priced_usd;
+                let amount = math::to_units_f64(unpriced_reserve, unpriced_decimals);
+                if depth < MIN_PRICING_DEPTH_USD || amount <= 0.0 {
+                    continue;
+                }
+
+                let candidate = (depth, depth / amount);
+                let best = next.entry(unpriced).or_insert(candidate);
+                if candidate.0 > best.0 {
+                    *best = candidate;
+                }
+            }
+        }
+
+        if next.is_empty() {
+            return prices;
+        }
+        prices.extend(next.into_iter().map(|(token, (_, price))| (token, price)));
+    }
+}
+
+/// USD value of a pool's reserves
+/// A V2 pool holds equal value on both sides, so an unpriced side counts as much as the priced one
+pub fn pool_liquidity_usd(pool: &PoolInfo, prices: &HashMap<Address, f64>, tokens: &TokenRegistry) -> Option<f64> {
+    let side = |token: Address, reserve: U256| {
+        Some(math::to_units_f64(reserve, tokens.decimals(token)?) * prices.get(&token)?)
+    };
+
+    match (side(pool.token0, pool.reserve0), side(pool.token1, pool.reserve1)) {
+        (Some(value0), Some(value1)) => Some(value0 + value1),
+        (Some(value), None) | (None, Some(value)) => Some(value * 2.0),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(address: u64, token0: Address, token1: Address, reserve0: u128, reserve1: u128) -> PoolInfo {
+        PoolInfo {
+            address: Address::from_low_u64_be(address),
+            token0,
+            token1,
+            reserve0: U256::from(reserve0),
+            reserve1: U256::from(reserve1),
+            fee_bps: 30,
+            dex_name: "TestDEX".to_string(),
+            last_updated: 0,
+            block_hash: None,
+            liquidity_usd: None,
+        }
+    }
+
+    #[test]
+    fn test_derive_eth_price_prefers_deepest_pool() {
+        let tokens = TokenRegistry::with_defaults(1);
+        let weth = tokens.resolve("WETH").unwrap();
+        let usdc = tokens.resolve("USDC").unwrap();
+
+        // USDC is token0 in the real pair; 6 decimals
+        let pools = vec![
+            pool(100, usdc, weth, 2_000_000_000_000, 1_000_000_000_000_000_000_000), // 2M USDC / 1000 WETH
+            pool(101, usdc, weth, 3_000_000_000, 1_000_000_000_000_000_000),         // 3k USDC / 1 WETH
+        ];
+
+        let price = derive_eth_price_usd(&pools, weth, &tokens.stablecoins()).unwrap();
+        assert!((price - 2000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_derive_eth_price_without_anchor() {
+        let tokens = TokenRegistry::with_defaults(1);
+        let weth = tokens.resolve("WETH").unwrap();
+
+        assert!(derive_eth_price_usd(&[], weth, &tokens.stablecoins()).is_none());
+    }
+
+    #[test]
+    fn test_token_prices_follow_routes_to_anchors() {
+        let tokens = TokenRegistry::with_defaults(1);
+        let weth = tokens.resolve("WETH").unwrap();
+        let usdc = tokens.resolve("USDC").unwrap();
+        let uni = tokens.resolve("UNI").unwrap();
+        let link = tokens.resolve("LINK").unwrap();
+
+        let pools = vec![
+            // 1000 UNI / 5 WETH at $2000 per ETH: $10 per UNI
+            pool(100, uni, weth, 1_000_000_000_000_000_000_000, 5_000_000_000_000_000_000),
+            // 500 LINK / 1000 UNI, two hops from WETH: $20 per LINK
+            pool(101, link, uni, 500_000_000_000_000_000_000, 1_000_000_000_000_000_000_000),
+            // Dust pool with a wild LINK price is too shallow to count
+            pool(102, link, usdc, 1_000_000_000_000_000_000, 10_000_000),
+        ];
+
+        let prices = derive_token_prices_usd(&pools, weth, 2000.0, &tokens);
+        assert!((prices[&uni] - 10.0).abs() < 1e-9);
+        assert!((prices[&link] - 20.0).abs() < 1e-9);
+        assert_eq!(prices[&usdc], 1.0);
+
+        // $10k UNI + $10k WETH
+        let tvl = pool_liquidity_usd(&pools[0], &prices, &tokens).unwrap();
+        assert!((tvl - 20_000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_unpriced_side_mirrors_priced_side() {
+        let tokens = TokenRegistry::with_defaults(1);
+        let weth = tokens.resolve("WETH").unwrap();
+        let unknown = Address::from_low_u64_be(0x1234);
+        let pools = vec![pool(100, unknown, weth, 1_000, 1_000_000_000_000_000_000)];
+
+        let prices = derive_token_prices_usd(&pools, weth, 2000.0, &tokens);
+        assert_eq!(pool_liquidity_usd(&pools[0], &prices, &tokens), Some(4000.0));
+
+        let unpriced = pool(101, unknown, Address::from_low_u64_be(0x5678), 1_000, 1_000);
+        assert_eq!(pool_liquidity_usd(&unpriced, &prices, &tokens), None);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::types::{AggregatorError, Result};
+use ethers::types::U256;
+use rust_decimal::Decimal;
+
+/// Largest scale a Decimal can hold
+const MAX_SCALE: i32 = 28;
+
+/// 2^64, the weight of one U256 limb
+const LIMB: f64 = 18_446_744_073_709_551_616.0;
+
+/// Convert a U256 to f64
+/// Every U256 is within f64 range; only digits beyond f64's 53-bit mantissa are lost
+pub fn to_f64(value: U256) -> f64 {
+    value.0.iter().rev().fold(0.0, |acc, limb| acc * LIMB + *limb as f64)
+}
+
+/// Convert base units of a token into token units as f64
+pub fn to_units_f64(amount: U256, decimals: u8) -> f64 {
+    to_f64(amount) / 10f64.powi(decimals as i32)
+}
+
+/// Convert base units of a token into token units as a Decimal
+/// Digits beyond Decimal's 28 significant digits are truncated; values whose integer part
+/// doesn't fit in 96 bits are a MathError
+pub fn to_decimal(amount: U256, decimals: u8) -> Result<Decimal> {
+    let max_mantissa = U256::from(Decimal::MAX.mantissa() as u128);
+    let mut mantissa = amount;
+    let mut scale = decimals as i32;
+
+    while mantissa > max_mantissa || scale > MAX_SCALE {
+        mantissa /= 10;
+        scale -= 1;
+    }
+
+    let value = Decimal::from_i128_with_scale(mantissa.as_u128() as i128, scale.max(0) as u32);
+    (scale..0).try_fold(value, |value, _| value.checked_mul(Decimal::TEN).ok_or(AggregatorError::MathError))
+}
+
+/// Narrow a U256 to u32, failing instead of truncating
+pub fn to_u32(value: U256) -> Result<u32> {
+    if value > U256::from(u32::MAX) {
+        return Err(AggregatorError::MathError);
+    }
+    Ok(value.as_u32())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::str::FromStr;
+
+    fn any_u256() -> impl Strategy<Value = U256> {
+        any::<[u64; 4]>().prop_map(U256)
+    }
+
+    #[test]
+    fn test_to_decimal() {
+        assert_eq!(to_decimal(U256::exp10(18), 18).unwrap(), Decimal::ONE);
+
+        // 10^12 tokens of an 18-decimals token is 10^30 base units, more than 96 bits
+        let amount = U256::exp10(30) + U256::from(123_456);
+        assert_eq!(to_decimal(amount, 18).unwrap(), Decimal::from_str("1000000000000.0000000000001234").unwrap());
+
+        // Scales above 28 are truncated rather than rejected
+        assert_eq!(to_decimal(U256::from(15) * U256::exp10(29), 30).unwrap(), Decimal::from_str("1.5").unwrap());
+
+        assert!(matches!(to_decimal(U256::MAX, 0), Err(AggregatorError::MathError)));
+    }
+
+    #[test]
+    fn test_to_f64() {
+        assert_eq!(to_f64(U256::from(1_500u64)), 1500.0);
+        assert_eq!(to_f64(U256::from(u128::MAX)), u128::MAX as f64);
+        assert_eq!(to_units_f64(U256::from(1_500_000u64), 6), 1.5);
+        assert!(to_f64(U256::MAX) > 1e77);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_to_f64_is_finite_and_close(value in any_u256()) {
+            let float = to_f64(value);
+            prop_assert!(float.is_finite());
+
+            // Round-trip through the decimal string agrees to f64 precision
+            let parsed: f64 = value.to_string().parse().unwrap();
+            prop_assert!((float - parsed).abs() <= parsed * 1e-15);
+        }
+
+        #[test]
+        fn prop_to_decimal_never_panics_and_keeps_leading_digits(value in any_u256(), decimals in 0u8..=77) {
+            if let Ok(decimal) = to_decimal(value, decimals) {
+                let expected = to_units_f64(value, decimals);
+                let actual: f64 = decimal.to_string().parse().unwrap();
+                // Decimal can't go below 10^-28, so tiny amounts may truncate to zero
+                prop_assert!((actual - expected).abs() <= expected * 1e-12 + 1e-27);
+            }
+        }
+
+        #[test]
+        fn prop_to_decimal_succeeds_for_amounts_that_fit(value in any::<u64>(), decimals in 0u8..=77) {
+            prop_assert!(to_decimal(U256::from(value), decimals).is_ok());
+        }
+
+        #[test]
+        fn prop_to_u32_matches_range(value in any_u256()) {
+            prop_assert_eq!(to_u32(value).is_ok(), value <= U256::from(u32::MAX));
+        }
+    }
+}
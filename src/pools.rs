@@ -1,13 +1,20 @@
-use crate::config::Config;
-use crate::types::{AggregatorError, PoolInfo, Result};
+use crate::config::{Config, DexConfig};
+use crate::journal::{JournalEntry, ReserveJournal};
+use crate::rpc::RpcProvider;
+use crate::tokens::TokenRegistry;
+use crate::types::{AggregatorError, PoolInfo, Result, SnapshotBlock, TokenInfo};
+use crate::{market, utils};
 use dashmap::DashMap;
+use ethers::abi::AbiDecode;
 use ethers::prelude::*;
 use ethers::types::{Address, U256};
+use futures::stream::{self, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
+use std::ops::Range;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, RwLock};
 use tracing::{debug, info, warn};
 
 // UniswapV2 Factory ABI (simplified)
@@ -16,6 +23,7 @@ abigen!(
     r#"[
         function allPairsLength() external view returns (uint256)
         function allPairs(uint256) external view returns (address)
+        function getPair(address, address) external view returns (address)
     ]"#,
 );
 
@@ -26,31 +34,88 @@ abigen!(
         function token0() external view returns (address)
         function token1() external view returns (address)
         function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast)
+        function factory() external view returns (address)
+        event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to)
+        event Sync(uint112 reserve0, uint112 reserve1)
     ]"#,
 );
 
+/// Passes over the pool indices that still failed after the RPC client's own retries
+const RETRY_PASSES: usize = 2;
+
+/// Pairs read per multicall when ranking a factory's pairs by liquidity
+const SCAN_BATCH: usize = 250;
+
+/// Blocks per eth_getLogs request when syncing reserves
+const LOG_BLOCK_RANGE: u64 = 2000;
+
+/// Pool addresses per eth_getLogs request when syncing reserves
+const LOG_ADDRESS_BATCH: usize = 500;
+
 /// Pool manager for fetching and caching pool data
 pub struct PoolManager {
-    provider: Arc<Provider<Http>>,
+    provider: Arc<RpcProvider>,
     pools: Arc<DashMap<Address, PoolInfo>>,
+    tokens: Arc<TokenRegistry>,
+    dexes: Vec<DexConfig>,
+    concurrency: usize,
+    checkpoints: Arc<DashMap<Address, FetchCheckpoint>>,
+    snapshot: Arc<RwLock<Option<SnapshotBlock>>>,
+    journal: Arc<Mutex<ReserveJournal>>,
+    cache_path: String,
+    checkpoint_interval: usize,
+    fetch_block: Option<u64>,
 }
 
 impl PoolManager {
     /// Create a new pool manager
-    pub fn new(provider: Arc<Provider<Http>>, _config: Config) -> Self {
+    pub fn new(provider: Arc<RpcProvider>, config: Config) -> Self {
         Self {
             provider,
             pools: Arc::new(DashMap::new()),
+            tokens: Arc::new(TokenRegistry::with_defaults(config.chain_id)),
+            dexes: config.dexes,
+            concurrency: config.fetch_concurrency.max(1),
+            checkpoints: Arc::new(DashMap::new()),
+            snapshot: Arc::new(RwLock::new(None)),
+            journal: Arc::new(Mutex::new(ReserveJournal::default())),
+            cache_path: config.cache_path,
+            checkpoint_interval: config.checkpoint_interval,
+            fetch_block: config.fetch_block,
         }
     }
 
+    /// Share a token registry; discovered token metadata is added to it
+    pub fn with_tokens(mut self, tokens: Arc<TokenRegistry>) -> Self {
+        self.tokens = tokens;
+        self
+    }
+
     /// Fetch pools from a factory contract
     pub async fn fetch_pools(
         &self,
         factory_address: Address,
         dex_name: String,
         limit: Option<usize>,
-    ) -> Result<Vec<PoolInfo>> {
+    ) -> Result<FetchReport> {
+        let block = self.pinned_block().await?;
+        self.fetch_pools_with_progress(factory_address, dex_name, limit, false, block, &|_| {}).await
+    }
+
+    /// Fetch pools from a factory contract at `block`, `fetch_concurrency` pools at a time,
+    /// reporting each step to `progress`
+    /// With `resume`, pair indices below the factory's checkpoint are skipped apart from those
+    /// that failed. The checkpoint is updated as pools arrive and the cache is written every
+    /// `checkpoint_interval` pools, so an interrupted fetch can be resumed
+    pub async fn fetch_pools_with_progress(
+        &self,
+        factory_address: Address,
+        dex_name: String,
+        limit: Option<usize>,
+        resume: bool,
+        block: SnapshotBlock,
+        progress: &(dyn Fn(&FetchEvent) + Sync),
+    ) -> Result<FetchReport> {
         info!("Fetching pools from {} factory: {:?}", dex_name, factory_address);
 
         let factory = UniswapV2Factory::new(factory_address, self.provider.clone());
@@ -58,69 +123,762 @@ impl PoolManager {
         // Get total number of pairs
         let pair_count = factory
             .all_pairs_length()
+            .block(block.id())
             .call()
             .await
-            .map_err(|e| AggregatorError::ContractError(format!("Failed to get pair count: {}", e)))?;
+            .map_err(|e| AggregatorError::ContractError(format!("Failed to get pair count at block {}: {}", block.number, e)))?;
 
-        info!("Total pairs in factory: {}", pair_count);
+        info!("Total pairs in factory at block {}: {}", block.number, pair_count);
 
-        let fetch_limit = limit.unwrap_or(pair_count.as_usize()).min(pair_count.as_usize());
-        info!("Fetching {} pools", fetch_limit);
+        let pair_count = pair_count.min(U256::from(usize::MAX)).as_usize();
+        let fetch_limit = limit.unwrap_or(pair_count).min(pair_count);
+        let checkpoint = if resume { self.checkpoint(&factory_address) } else { None };
+        let resumed_from = checkpoint.as_ref().map(|checkpoint| checkpoint.next_index);
+        let (start, retry) = match checkpoint {
+            Some(checkpoint) => {
+                info!(
+                    "Resuming {} from pair index {} with {} failed pools to retry",
+                    dex_name,
+                    checkpoint.next_index,
+                    checkpoint.failed.len()
+                );
+                (checkpoint.next_index.min(fetch_limit), checkpoint.failed)
+            }
+            None => (0, Vec::new()),
+        };
 
         let mut pools = Vec::new();
+        let mut pending: Vec<usize> = retry.iter().copied().chain(start..fetch_limit).collect();
+        let mut failures = Vec::new();
+        let mut tracker = IndexTracker::new(start, retry);
+        let mut since_checkpoint = 0;
+        let requested = pending.len();
 
-        // Fetch pools in batches
-        for i in 0..fetch_limit {
-            match self.fetch_pool_at_index(&factory, i, &dex_name).await {
-                Ok(pool) => {
-                    self.pools.insert(pool.address, pool.clone());
-                    pools.push(pool);
-                    
-                    if (i + 1) % 10 == 0 {
-                        info!("Fetched {}/{} pools", i + 1, fetch_limit);
+        info!("Fetching {} pools, {} at a time", pending.len(), self.concurrency);
+        progress(&FetchEvent::Started { dex: dex_name.clone(), total: pending.len() });
+
+        // Retry failed indices once the rest are done, giving rate limits time to reset
+        for pass in 0..=RETRY_PASSES {
+            if pending.is_empty() {
+                break;
+            }
+            if pass > 0 {
+                debug!("Retrying {} failed pools from {} (pass {}/{})", pending.len(), dex_name, pass, RETRY_PASSES);
+                progress(&FetchEvent::Retrying { dex: dex_name.clone(), pass, pending: pending.len() });
+            }
+
+            failures.clear();
+            let total = pending.len();
+            let mut results = stream::iter(std::mem::take(&mut pending))
+                .map(|i| {
+                    let (factory, dex_name) = (&factory, &dex_name);
+                    async move { (i, self.fetch_pool_at_index(factory, i, dex_name, block).await) }
+                })
+                .buffer_unordered(self.concurrency);
+
+            let mut done = 0;
+            while let Some((i, result)) = results.next().await {
+                match result {
+                    Ok(pool) => {
+                        self.insert_pool(pool.clone());
+                        pools.push(pool);
+                        tracker.complete(i, true);
+                    }
+                    Err(e) => {
+                        debug!("Failed to fetch pool at index {}: {}", i, e);
+                        failures.push(FetchFailure { index: i, error: e.to_string() });
+                        tracker.complete(i, false);
+                    }
+                }
+                self.checkpoints.insert(factory_address, tracker.checkpoint(&dex_name, factory_address));
+
+                since_checkpoint += 1;
+                if self.checkpoint_interval > 0 && since_checkpoint >= self.checkpoint_interval {
+                    since_checkpoint = 0;
+                    self.write_checkpoint();
+                }
+                done += 1;
+                progress(&FetchEvent::Progress {
+                    dex: dex_name.clone(),
+                    pass,
+                    done,
+                    total,
+                    failed: failures.len(),
+                });
+            }
+            failures.sort_by_key(|failure| failure.index);
+            pending = failures.iter().map(|failure| failure.index).collect();
+        }
+
+        for failure in &failures {
+            warn!("Could not fetch pool at index {}: {}", failure.index, failure.error);
+        }
+        info!("Successfully fetched {} pools from {}", pools.len(), dex_name);
+        progress(&FetchEvent::Finished {
+            dex: dex_name.clone(),
+            fetched: pools.len(),
+            failed: failures.len(),
+        });
+        self.record_snapshot(block);
+
+        Ok(FetchReport {
+            dex_name,
+            factory: factory_address,
+            block,
+            requested,
+            resumed_from,
+            pools,
+            failures,
+        })
+    }
+
+    /// Fetch the `limit` pools of a factory with the most USD liquidity
+    /// Every pair's tokens and reserves are read with multicall at `block` and valued at
+    /// `prices`; pairs with no priced token can't be ranked and are left out
+    pub async fn fetch_top_pools(
+        &self,
+        factory_address: Address,
+        dex_name: String,
+        limit: usize,
+        prices: &HashMap<Address, f64>,
+        block: SnapshotBlock,
+        progress: &(dyn Fn(&FetchEvent) + Sync),
+    ) -> Result<FetchReport> {
+        info!("Ranking pools from {} factory by liquidity: {:?}", dex_name, factory_address);
+
+        let factory = UniswapV2Factory::new(factory_address, self.provider.clone());
+        let pair_count = factory
+            .all_pairs_length()
+            .block(block.id())
+            .call()
+            .await
+            .map_err(|e| AggregatorError::ContractError(format!("Failed to get pair count at block {}: {}", block.number, e)))?;
+        let pair_count = pair_count.min(U256::from(usize::MAX)).as_usize();
+
+        info!("Scanning {} pairs at block {}, keeping the top {}", pair_count, block.number, limit);
+        progress(&FetchEvent::Started { dex: dex_name.clone(), total: pair_count });
+
+        let batches = (0..pair_count).step_by(SCAN_BATCH).map(|start| start..(start + SCAN_BATCH).min(pair_count));
+        let mut results = stream::iter(batches)
+            .map(|indices| {
+                let (factory, dex_name) = (&factory, &dex_name);
+                async move { (indices.clone(), self.scan_pairs(factory, indices, dex_name, block).await) }
+            })
+            .buffer_unordered(self.concurrency);
+
+        let mut ranked: Vec<(f64, PoolInfo)> = Vec::new();
+        let mut failures = Vec::new();
+        let mut done = 0;
+        while let Some((indices, result)) = results.next().await {
+            done += indices.len();
+            match result {
+                Ok(pools) => {
+                    ranked.extend(pools.into_iter().filter_map(|pool| {
+                        Some((market::pool_liquidity_usd(&pool, prices, &self.tokens)?, pool))
+                    }));
+                    // Keep memory bounded on factories with hundreds of thousands of pairs
+                    if ranked.len() > limit.saturating_mul(2).saturating_add(SCAN_BATCH) {
+                        rank(&mut ranked, limit);
                     }
                 }
                 Err(e) => {
-                    warn!("Failed to fetch pool at index {}: {}", i, e);
+                    debug!("Failed to scan pairs {}..{}: {}", indices.start, indices.end, e);
+                    let error = e.to_string();
+                    failures.extend(indices.map(|index| FetchFailure { index, error: error.clone() }));
                 }
             }
+            progress(&FetchEvent::Progress {
+                dex: dex_name.clone(),
+                pass: 0,
+                done,
+                total: pair_count,
+                failed: failures.len(),
+            });
+        }
+        drop(results);
+        rank(&mut ranked, limit);
+        failures.sort_by_key(|failure| failure.index);
+
+        let pools: Vec<PoolInfo> = ranked.into_iter().map(|(_, pool)| pool).collect();
+        let tokens: HashSet<Address> = pools.iter().flat_map(|pool| [pool.token0, pool.token1]).collect();
+        stream::iter(tokens)
+            .for_each_concurrent(self.concurrency, |token| self.discover_token(token))
+            .await;
+        for pool in &pools {
+            self.insert_pool(pool.clone());
+        }
+
+        if !failures.is_empty() {
+            warn!("Could not scan {} pairs from {}", failures.len(), dex_name);
         }
+        info!("Kept the {} most liquid pools from {}", pools.len(), dex_name);
+        progress(&FetchEvent::Finished {
+            dex: dex_name.clone(),
+            fetched: pools.len(),
+            failed: failures.len(),
+        });
+        self.record_snapshot(block);
+
+        Ok(FetchReport {
+            dex_name,
+            factory: factory_address,
+            block,
+            requested: pair_count,
+            resumed_from: None,
+            pools,
+            failures,
+        })
+    }
+
+    /// Read the pairs at `indices` and their tokens and reserves, in two multicalls at `block`
+    /// Pairs whose calls revert are left out
+    async fn scan_pairs(
+        &self,
+        factory: &UniswapV2Factory<RpcProvider>,
+        indices: Range<usize>,
+        dex_name: &str,
+        block: SnapshotBlock,
+    ) -> Result<Vec<PoolInfo>> {
+        let multicall_error = |e| AggregatorError::ContractError(format!("Multicall failed: {}", e));
+        let mut multicall = Multicall::new_with_chain_id(self.provider.clone(), Some(MULTICALL_ADDRESS), None::<u64>)
+            .map_err(multicall_error)?;
+        // Multicall::block only takes block numbers
+        multicall.block = Some(block.id());
+
+        for index in indices {
+            multicall.add_call(factory.all_pairs(U256::from(index)), true);
+        }
+        let pairs: Vec<Address> = multicall
+            .call_raw()
+            .await
+            .map_err(multicall_error)?
+            .into_iter()
+            .filter_map(|result| result.ok()?.into_address())
+            .collect();
 
+        multicall.clear_calls();
+        for &address in &pairs {
+            let pair = UniswapV2Pair::new(address, self.provider.clone());
+            multicall
+                .add_call(pair.token_0(), true)
+                .add_call(pair.token_1(), true)
+                .add_call(pair.get_reserves(), true);
+        }
+        let results = multicall.call_raw().await.map_err(multicall_error)?;
+
+        let fee_bps = self.fee_bps(dex_name);
+        Ok(pairs
+            .into_iter()
+            .zip(results.chunks(3))
+            .filter_map(|(address, results)| {
+                let [token0, token1, reserves] = results else { return None };
+                let reserves = reserves.clone().ok()?.into_tuple()?;
+                Some(PoolInfo {
+                    address,
+                    token0: token0.clone().ok()?.into_address()?,
+                    token1: token1.clone().ok()?.into_address()?,
+                    reserve0: reserves.first()?.clone().into_uint()?,
+                    reserve1: reserves.get(1)?.clone().into_uint()?,
+                    fee_bps,
+                    dex_name: dex_name.to_string(),
+                    last_updated: block.number,
+                    block_hash: Some(block.hash),
+                    liquidity_usd: None,
+                })
+            })
+            .collect())
+    }
+
+    /// Fetch the first `limit` pairs of a ranked seed list, e.g. one exported from a subgraph,
+    /// at `block`
+    pub async fn fetch_seeded_pools(
+        &self,
+        factory_address: Address,
+        dex_name: String,
+        pairs: &[Address],
+        limit: Option<usize>,
+        block: SnapshotBlock,
+        progress: &(dyn Fn(&FetchEvent) + Sync),
+    ) -> Result<FetchReport> {
+        let pairs = &pairs[..limit.unwrap_or(pairs.len()).min(pairs.len())];
+        info!("Fetching the top {} seeded pools of {} at block {}", pairs.len(), dex_name, block.number);
+        progress(&FetchEvent::Started { dex: dex_name.clone(), total: pairs.len() });
+
+        let mut results = stream::iter(pairs.iter().copied().enumerate())
+            .map(|(i, address)| {
+                let dex_name = dex_name.clone();
+                async move { (i, self.fetch_pool_info_at(address, dex_name, block).await) }
+            })
+            .buffer_unordered(self.concurrency);
+
+        let mut pools = Vec::new();
+        let mut failures = Vec::new();
+        let mut done = 0;
+        while let Some((i, result)) = results.next().await {
+            match result {
+                Ok(pool) => {
+                    self.insert_pool(pool.clone());
+                    pools.push(pool);
+                }
+                Err(e) => failures.push(FetchFailure { index: i, error: e.to_string() }),
+            }
+            done += 1;
+            progress(&FetchEvent::Progress {
+                dex: dex_name.clone(),
+                pass: 0,
+                done,
+                total: pairs.len(),
+                failed: failures.len(),
+            });
+        }
+        drop(results);
+        failures.sort_by_key(|failure| failure.index);
+
+        for failure in &failures {
+            warn!("Could not fetch seeded pool {:?}: {}", pairs[failure.index], failure.error);
+        }
         info!("Successfully fetched {} pools from {}", pools.len(), dex_name);
+        progress(&FetchEvent::Finished {
+            dex: dex_name.clone(),
+            fetched: pools.len(),
+            failed: failures.len(),
+        });
+        self.record_snapshot(block);
+
+        Ok(FetchReport {
+            dex_name,
+            factory: factory_address,
+            block,
+            requested: pairs.len(),
+            resumed_from: None,
+            pools,
+            failures,
+        })
+    }
+
+    /// Number and hash of a block
+    pub async fn block_at(&self, number: BlockNumber) -> Result<SnapshotBlock> {
+        let block = self
+            .provider
+            .get_block(number)
+            .await
+            .map_err(|e| AggregatorError::RpcError(format!("Failed to get block {}: {}", number, e)))?
+            .ok_or_else(|| AggregatorError::RpcError(format!("Block {} not available", number)))?;
+
+        match (block.number, block.hash) {
+            (Some(number), Some(hash)) => Ok(SnapshotBlock {
+                number: number.as_u64(),
+                hash,
+            }),
+            _ => Err(AggregatorError::RpcError(format!("Block {} has no number or hash", number))),
+        }
+    }
+
+    /// Block a fetch pins all of its reads to: the configured `fetch_block`, else the latest
+    /// Resolve it once per command so every DEX is read at the same block
+    pub async fn pinned_block(&self) -> Result<SnapshotBlock> {
+        self.block_at(self.fetch_block.map_or(BlockNumber::Latest, BlockNumber::from)).await
+    }
+
+    /// Latest block pools were fetched at
+    pub fn snapshot(&self) -> Option<SnapshotBlock> {
+        *self.snapshot.read().unwrap()
+    }
+
+    fn record_snapshot(&self, block: SnapshotBlock) {
+        let mut snapshot = self.snapshot.write().unwrap();
+        if snapshot.is_none_or(|snapshot| snapshot.number <= block.number) {
+            *snapshot = Some(block);
+        }
+    }
+
+    /// Bring cached reserves up to the chain head by applying the pools' Sync events
+    /// Events are read from the oldest cached pool's block on, since pools fetched at different
+    /// blocks each miss the events after their own. Each block's changes are journaled. A reorg of the last synced block is detected by a
+    /// parent-hash mismatch; the changed pools are then rolled back to the newest journaled
+    /// block still on the canonical chain and the canonical events are applied from there
+    pub async fn sync_reserves(&self) -> Result<SyncReport> {
+        if let Some(block) = self.fetch_block {
+            return Err(AggregatorError::ConfigError(format!(
+                "Pools are pinned to block {}; only latest snapshots can be synced",
+                block
+            )));
+        }
+        let from = self
+            .snapshot()
+            .ok_or_else(|| AggregatorError::CacheError("No snapshot block to sync from; fetch pools first".to_string()))?;
+        self.journal.lock().unwrap().anchor(self.sync_start().unwrap_or(from));
+
+        let head = self.block_at(BlockNumber::Latest).await?;
+        let (reorg, rolled_back) = match self.detect_reorg(from, head).await? {
+            Some((reorg, rolled_back)) => (Some(reorg), rolled_back),
+            None => (None, Vec::new()),
+        };
+        // Rolling back may have moved pools back to the ancestor
+        let start = self.sync_start().unwrap_or(from);
+
+        let (events, mut updated) = self.apply_sync_events(start, head).await?;
+        // Pools read at or after `start` now have every event up to the head applied
+        self.restamp(head, |pool| pool.last_updated >= start.number && pool.last_updated < head.number);
+        // Journaled even without changes, so a rollback knows pools stamped with it were synced
+        self.journal.lock().unwrap().record(JournalEntry {
+            block: head,
+            previous: Vec::new(),
+            fetched: Vec::new(),
+        });
+        *self.snapshot.write().unwrap() = Some(head);
+        info!("Synced {} pools to block {} with {} Sync events", updated.len(), head.number, events);
+        updated.extend(rolled_back);
+
+        Ok(SyncReport {
+            from,
+            to: head,
+            events,
+            pools_updated: updated.into_iter().collect(),
+            reorg,
+        })
+    }
+
+    /// Roll back to the common ancestor if `tip` is no longer on the canonical chain, returning
+    /// the reorg and the pools rolled back
+    async fn detect_reorg(&self, tip: SnapshotBlock, head: SnapshotBlock) -> Result<Option<(Reorg, Vec<Address>)>> {
+        let canonical = if head.number > tip.number {
+            self.parent_hash(tip.number + 1).await? == tip.hash
+        } else {
+            self.block_at(tip.number.into()).await.is_ok_and(|block| block.hash == tip.hash)
+        };
+        if canonical {
+            return Ok(None);
+        }
+
+        let rollback_points = self.journal.lock().unwrap().rollback_points();
+        let journaled: HashSet<H256> = rollback_points.iter().map(|block| block.hash).collect();
+        for block in rollback_points {
+            if block.number > head.number || self.block_at(block.number.into()).await?.hash != block.hash {
+                continue;
+            }
+
+            let (fetched, restored) = {
+                let mut journal = self.journal.lock().unwrap();
+                (journal.fetched_after(block), journal.roll_back(block))
+            };
+            let mut rolled_back: Vec<Address> = restored.iter().map(|pool| pool.address).collect();
+            for pool in restored {
+                self.pools.insert(pool.address, pool);
+            }
+            // Pools synced to a later block that the journal didn't restore had no changes since
+            // the ancestor; the rest were fetched after it and are read again
+            self.restamp(block, |pool| {
+                pool.last_updated > block.number
+                    && !fetched.contains(&pool.address)
+                    && pool.block_hash.is_some_and(|hash| journaled.contains(&hash))
+            });
+            rolled_back.extend(self.reread_unjournaled(block).await);
+            warn!(
+                "Reorg replaced block {}; rolled back {} pools to block {}",
+                tip.number,
+                rolled_back.len(),
+                block.number
+            );
+            let reorg = Reorg {
+                orphaned: tip,
+                ancestor: block,
+                pools_rolled_back: rolled_back.len(),
+            };
+            return Ok(Some((reorg, rolled_back)));
+        }
+
+        Err(AggregatorError::CacheError(format!(
+            "Block {} was reorged out and no journaled block is still canonical; fetch pools again",
+            tip.number
+        )))
+    }
+
+    /// Read the pools still newer than `ancestor` after a rollback again at it
+    /// These were fetched rather than synced, possibly at an orphaned block; pools that can't be
+    /// read at the ancestor are dropped from the cache
+    async fn reread_unjournaled(&self, ancestor: SnapshotBlock) -> Vec<Address> {
+        let pools: Vec<PoolInfo> = self
+            .pools
+            .iter()
+            .filter(|pool| pool.last_updated > ancestor.number)
+            .map(|pool| pool.value().clone())
+            .collect();
+
+        let results: Vec<(PoolInfo, Result<PoolInfo>)> = stream::iter(pools)
+            .map(|pool| async move {
+                let result = self.fetch_pool_info_at(pool.address, pool.dex_name.clone(), ancestor).await;
+                (pool, result)
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+
+        let mut reread = Vec::new();
+        for (pool, result) in results {
+            match result {
+                Ok(mut fresh) => {
+                    fresh.liquidity_usd = pool.liquidity_usd;
+                    self.insert_pool(fresh);
+                    reread.push(pool.address);
+                }
+                Err(e) => {
+                    warn!("Dropping pool {:?} read at block {}: {}", pool.address, pool.last_updated, e);
+                    self.pools.remove(&pool.address);
+                }
+            }
+        }
+        reread
+    }
+
+    /// Mark the pools matching `filter` as current at `block`
+    fn restamp(&self, block: SnapshotBlock, filter: impl Fn(&PoolInfo) -> bool) {
+        for mut pool in self.pools.iter_mut() {
+            if filter(pool.value()) {
+                pool.last_updated = block.number;
+                pool.block_hash = Some(block.hash);
+            }
+        }
+    }
+
+    /// Oldest block a cached pool was read or synced at; events after it may be missing
+    fn sync_start(&self) -> Option<SnapshotBlock> {
+        self.pools
+            .iter()
+            .filter_map(|pool| pool.snapshot_block())
+            .min_by_key(|block| block.number)
+    }
+
+    async fn parent_hash(&self, number: u64) -> Result<H256> {
+        self.provider
+            .get_block(number)
+            .await
+            .map_err(|e| AggregatorError::RpcError(format!("Failed to get block {}: {}", number, e)))?
+            .map(|block| block.parent_hash)
+            .ok_or_else(|| AggregatorError::RpcError(format!("Block {} not available", number)))
+    }
+
+    /// Apply the Sync events of cached pools in the blocks after `from` up to `to`, journaling
+    /// each block's changes; returns the number of events and the pools they changed
+    async fn apply_sync_events(&self, from: SnapshotBlock, to: SnapshotBlock) -> Result<(usize, HashSet<Address>)> {
+        let addresses: Vec<Address> = self.pools.iter().map(|entry| *entry.key()).collect();
+        let mut logs = Vec::new();
+        let mut start = from.number + 1;
+        while start <= to.number {
+            let end = (start + LOG_BLOCK_RANGE - 1).min(to.number);
+            for batch in addresses.chunks(LOG_ADDRESS_BATCH) {
+                let filter = Filter::new()
+                    .address(batch.to_vec())
+                    .event(&SyncFilter::abi_signature())
+                    .from_block(start)
+                    .to_block(end);
+                logs.extend(
+                    self.provider
+                        .get_logs(&filter)
+                        .await
+                        .map_err(|e| AggregatorError::RpcError(format!("Failed to get Sync events: {}", e)))?,
+                );
+            }
+            start = end + 1;
+        }
+        Ok(self.apply_sync_logs(logs))
+    }
+
+    /// Apply Sync event logs to the cached pools they belong to, journaling each block's
+    /// changes; events a pool's state already includes are skipped
+    fn apply_sync_logs(&self, mut logs: Vec<Log>) -> (usize, HashSet<Address>) {
+        logs.retain(|log| log.removed != Some(true));
+        logs.sort_by_key(|log| (log.block_number, log.log_index));
+
+        let mut events = 0;
+        let mut updated = HashSet::new();
+        let mut journal = self.journal.lock().unwrap();
+        for block_logs in logs.chunk_by(|a, b| a.block_number == b.block_number) {
+            let (Some(number), Some(hash)) = (block_logs[0].block_number, block_logs[0].block_hash) else {
+                continue;
+            };
+            let block = SnapshotBlock { number: number.as_u64(), hash };
+
+            let mut previous: HashMap<Address, PoolInfo> = HashMap::new();
+            for log in block_logs {
+                let Ok(sync) = parse_log::<SyncFilter>(log.clone()) else { continue };
+                let Some(mut pool) = self.pools.get_mut(&log.address) else { continue };
+                // Pools read at or after the event's block already include it
+                if pool.last_updated >= block.number {
+                    continue;
+                }
+
+                previous.entry(pool.address).or_insert_with(|| pool.clone());
+                pool.reserve0 = U256::from(sync.reserve_0);
+                pool.reserve1 = U256::from(sync.reserve_1);
+                events += 1;
+                updated.insert(pool.address);
+            }
+
+            // Stamp the block only after all of its events, which may touch a pool more than once
+            for address in previous.keys() {
+                if let Some(mut pool) = self.pools.get_mut(address) {
+                    pool.last_updated = block.number;
+                    pool.block_hash = Some(block.hash);
+                }
+            }
+            if !previous.is_empty() {
+                journal.record(JournalEntry {
+                    block,
+                    previous: previous.into_values().collect(),
+                    fetched: Vec::new(),
+                });
+            }
+        }
+
+        (events, updated)
+    }
+
+    /// Where the last fetch from a factory got to, kept with the cache
+    pub fn checkpoint(&self, factory: &Address) -> Option<FetchCheckpoint> {
+        self.checkpoints.get(factory).map(|entry| entry.value().clone())
+    }
+
+    /// Write the partial cache during a fetch; failures are logged since the fetch goes on
+    fn write_checkpoint(&self) {
+        match self.write_cache(&self.cache_path) {
+            Ok(count) => debug!("Checkpointed {} pools to {}", count, self.cache_path),
+            Err(e) => warn!("Failed to checkpoint pools: {}", e),
+        }
+    }
+
+    /// Find and fetch the pools each configured DEX has for `pairs`, leaving out cached ones
+    /// Pair addresses are computed with CREATE2 when the DEX has an init code hash and read
+    /// from the factory's getPair otherwise; pairs that were never created are skipped
+    pub async fn discover_pools(&self, pairs: &[(Address, Address)]) -> Result<Vec<PoolInfo>> {
+        let lookups: Vec<(&DexConfig, Address, Address)> = self
+            .dexes
+            .iter()
+            .flat_map(|dex| pairs.iter().filter(|(a, b)| a != b).map(move |&(a, b)| (dex, a, b)))
+            .collect();
+        let total = lookups.len();
+        let block = self.pinned_block().await?;
+        info!("Looking up {} pairs on {} DEXes at block {}", pairs.len(), self.dexes.len(), block.number);
+
+        let results: Vec<Result<Option<PoolInfo>>> = stream::iter(lookups)
+            .map(|(dex, token_a, token_b)| self.discover_pool(dex, token_a, token_b, block))
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+
+        let mut pools = Vec::new();
+        let mut errors = Vec::new();
+        for result in results {
+            match result {
+                Ok(Some(pool)) => {
+                    self.insert_pool(pool.clone());
+                    pools.push(pool);
+                }
+                Ok(None) => {}
+                Err(e) => errors.push(e),
+            }
+        }
+
+        // Some lookups failing still leaves the rest usable; all of them failing means the RPC is down
+        if total > 0 && errors.len() == total {
+            return Err(errors.swap_remove(0));
+        }
+        for e in &errors {
+            warn!("Pool lookup failed: {}", e);
+        }
+        info!("Discovered {} new pools", pools.len());
+        if !pools.is_empty() {
+            self.record_snapshot(block);
+        }
         Ok(pools)
     }
 
+    /// Fetch a DEX's pool for a token pair, or `None` if it doesn't exist or is cached
+    async fn discover_pool(
+        &self,
+        dex: &DexConfig,
+        token_a: Address,
+        token_b: Address,
+        block: SnapshotBlock,
+    ) -> Result<Option<PoolInfo>> {
+        let pair_address = match dex.init_code_hash {
+            Some(init_code_hash) => {
+                let address = utils::uniswap_v2_pair_address(dex.factory, init_code_hash, token_a, token_b);
+                if self.pools.contains_key(&address) {
+                    return Ok(None);
+                }
+                let code = self
+                    .provider
+                    .get_code(address, Some(block.id()))
+                    .await
+                    .map_err(|e| AggregatorError::RpcError(format!("Failed to get code of {:?}: {}", address, e)))?;
+                if code.is_empty() {
+                    return Ok(None);
+                }
+                address
+            }
+            None => {
+                let factory = UniswapV2Factory::new(dex.factory, self.provider.clone());
+                let address = factory
+                    .get_pair(token_a, token_b)
+                    .block(block.id())
+                    .call()
+                    .await
+                    .map_err(|e| AggregatorError::ContractError(format!("Failed to get pair from {}: {}", dex.name, e)))?;
+                if address.is_zero() || self.pools.contains_key(&address) {
+                    return Ok(None);
+                }
+                address
+            }
+        };
+
+        debug!("Found {} pair {:?} for {:?}/{:?}", dex.name, pair_address, token_a, token_b);
+        self.fetch_pool_info_at(pair_address, dex.name.clone(), block).await.map(Some)
+    }
+
     /// Fetch a single pool at a specific index
     async fn fetch_pool_at_index(
         &self,
-        factory: &UniswapV2Factory<Provider<Http>>,
+        factory: &UniswapV2Factory<RpcProvider>,
         index: usize,
         dex_name: &str,
+        block: SnapshotBlock,
     ) -> Result<PoolInfo> {
         // Get pair address
         let pair_address = factory
             .all_pairs(U256::from(index))
+            .block(block.id())
             .call()
             .await
             .map_err(|e| AggregatorError::ContractError(format!("Failed to get pair address: {}", e)))?;
 
         // Fetch pool info
-        self.fetch_pool_info(pair_address, dex_name.to_string()).await
+        self.fetch_pool_info_at(pair_address, dex_name.to_string(), block).await
     }
 
-    /// Fetch information for a specific pool
+    /// Fetch information for a specific pool at the pinned block: `fetch_block` if set, else the latest
     pub async fn fetch_pool_info(&self, pair_address: Address, dex_name: String) -> Result<PoolInfo> {
+        let block = self.pinned_block().await?;
+        let pool = self.fetch_pool_info_at(pair_address, dex_name, block).await?;
+        self.record_snapshot(block);
+        Ok(pool)
+    }
+
+    /// Fetch information for a specific pool, reading its state at `block`
+    pub async fn fetch_pool_info_at(&self, pair_address: Address, dex_name: String, block: SnapshotBlock) -> Result<PoolInfo> {
         let pair = UniswapV2Pair::new(pair_address, self.provider.clone());
 
         // Get tokens
         let token0 = pair
             .token_0()
+            .block(block.id())
             .call()
             .await
             .map_err(|e| AggregatorError::ContractError(format!("Failed to get token0: {}", e)))?;
 
         let token1 = pair
             .token_1()
+            .block(block.id())
             .call()
             .await
             .map_err(|e| AggregatorError::ContractError(format!("Failed to get token1: {}", e)))?;
@@ -128,31 +886,129 @@ impl PoolManager {
         // Get reserves
         let reserves = pair
             .get_reserves()
+            .block(block.id())
             .call()
             .await
             .map_err(|e| AggregatorError::ContractError(format!("Failed to get reserves: {}", e)))?;
 
-        let block_number = self
-            .provider
-            .get_block_number()
-            .await
-            .map_err(|e| AggregatorError::RpcError(format!("Failed to get block number: {}", e)))?;
-
         let pool = PoolInfo {
             address: pair_address,
             token0,
             token1,
             reserve0: U256::from(reserves.0),
             reserve1: U256::from(reserves.1),
-            fee_bps: 30, // UniswapV2 default fee is 0.3%
+            fee_bps: self.fee_bps(&dex_name),
             dex_name,
-            last_updated: block_number.as_u64(),
+            last_updated: block.number,
+            block_hash: Some(block.hash),
+            liquidity_usd: None,
         };
 
+        for token in [token0, token1] {
+            self.discover_token(token).await;
+        }
+
         debug!("Fetched pool: {:?}", pool.address);
         Ok(pool)
     }
 
+    /// Swap fee of a configured DEX; 0.3% (the UniswapV2 fee) for unknown DEXes
+    fn fee_bps(&self, dex_name: &str) -> u32 {
+        self.dexes
+            .iter()
+            .find(|dex| dex.name.eq_ignore_ascii_case(dex_name))
+            .map_or(30, |dex| dex.fee_bps)
+    }
+
+    /// Read decimals, symbol and name from a token contract
+    /// Accepts both string and bytes32 (e.g. MKR) symbol and name return types
+    pub async fn fetch_token_info(&self, token: Address) -> Result<TokenInfo> {
+        let decimals = self.call_token(token, "decimals()").await?;
+        let decimals = U256::decode(&decimals)
+            .ok()
+            .filter(|d| *d <= U256::from(u8::MAX))
+            .ok_or_else(|| AggregatorError::ContractError(format!("Invalid decimals() from {:?}", token)))?
+            .as_u32() as u8;
+
+        let symbol = self.call_token(token, "symbol()").await?;
+        let symbol = decode_string_or_bytes32(&symbol)
+            .ok_or_else(|| AggregatorError::ContractError(format!("Invalid symbol() from {:?}", token)))?;
+
+        // name() is optional in ERC20; fall back to the symbol
+        let name = self
+            .call_token(token, "name()")
+            .await
+            .ok()
+            .and_then(|name| decode_string_or_bytes32(&name))
+            .unwrap_or_else(|| symbol.clone());
+
+        Ok(TokenInfo {
+            address: token,
+            symbol,
+            name,
+            decimals,
+        })
+    }
+
+    /// Fetch metadata for a token the registry doesn't know yet
+    async fn discover_token(&self, token: Address) {
+        if self.tokens.contains(token) {
+            return;
+        }
+
+        match self.fetch_token_info(token).await {
+            Ok(info) => {
+                debug!("Discovered token {} ({:?}), {} decimals", info.symbol, token, info.decimals);
+                self.tokens.insert_discovered(info);
+            }
+            Err(e) => debug!("Failed to read metadata of {:?}: {}", token, e),
+        }
+    }
+
+    async fn call_token(&self, token: Address, signature: &str) -> Result<Bytes> {
+        let tx = TransactionRequest::new()
+            .to(token)
+            .data(ethers::utils::id(signature).to_vec());
+
+        self.provider
+            .call(&tx.into(), None)
+            .await
+            .map_err(|e| AggregatorError::ContractError(format!("{} failed on {:?}: {}", signature, token, e)))
+    }
+
+    /// Read the factory that deployed a pair
+    pub async fn fetch_pair_factory(&self, pair_address: Address) -> Result<Address> {
+        UniswapV2Pair::new(pair_address, self.provider.clone())
+            .factory()
+            .call()
+            .await
+            .map_err(|e| AggregatorError::ContractError(format!("Failed to get factory: {}", e)))
+    }
+
+    /// Insert or replace a pool in the cache
+    /// Pools read at a journaled block are noted, so a reorg reads them again instead of
+    /// taking them for synced
+    pub fn insert_pool(&self, pool: PoolInfo) {
+        self.journal.lock().unwrap().note_fetched(&pool);
+        self.pools.insert(pool.address, pool);
+    }
+
+    /// Set the USD liquidity of every cached pool
+    pub fn set_liquidity_usd(&self, value: impl Fn(&PoolInfo) -> Option<f64>) {
+        for mut entry in self.pools.iter_mut() {
+            entry.liquidity_usd = value(entry.value());
+        }
+    }
+
+    /// Set the USD liquidity of the cached pools among `addresses`
+    pub fn set_liquidity_usd_of(&self, addresses: &[Address], value: impl Fn(&PoolInfo) -> Option<f64>) {
+        for address in addresses {
+            if let Some(mut pool) = self.pools.get_mut(address) {
+                pool.liquidity_usd = value(pool.value());
+            }
+        }
+    }
+
     /// Get all cached pools
     pub fn get_all_pools(&self) -> Vec<PoolInfo> {
         self.pools.iter().map(|entry| entry.value().clone()).collect()
@@ -190,9 +1046,23 @@ impl PoolManager {
 
     /// Export pools to JSON file
     pub fn export_to_file(&self, path: &str) -> Result<()> {
+        let count = self.write_cache(path)?;
+        info!("Exported {} pools to {}", count, path);
+        Ok(())
+    }
+
+    /// Write pools, discovered tokens and fetch checkpoints to `path`, returning the pool count
+    fn write_cache(&self, path: &str) -> Result<usize> {
         let pools = self.get_all_pools();
+        let mut checkpoints: Vec<FetchCheckpoint> =
+            self.checkpoints.iter().map(|entry| entry.value().clone()).collect();
+        checkpoints.sort_by(|a, b| a.dex_name.cmp(&b.dex_name));
         let cache_data = CacheData {
             pools,
+            tokens: self.tokens.discovered_tokens(),
+            block: self.snapshot(),
+            journal: self.journal.lock().unwrap().clone(),
+            checkpoints,
             timestamp: chrono::Utc::now().timestamp() as u64,
         };
 
@@ -205,11 +1075,13 @@ impl PoolManager {
         let json = serde_json::to_string_pretty(&cache_data)
             .map_err(|e| AggregatorError::CacheError(format!("Failed to serialize cache: {}", e)))?;
 
-        fs::write(path, json)
+        // Write to a temporary file first so an interrupted write can't corrupt the cache
+        let temp_path = format!("{}.tmp", path);
+        fs::write(&temp_path, json)
+            .and_then(|_| fs::rename(&temp_path, path))
             .map_err(|e| AggregatorError::CacheError(format!("Failed to write cache file: {}", e)))?;
 
-        info!("Exported {} pools to {}", cache_data.pools.len(), path);
-        Ok(())
+        Ok(cache_data.pools.len())
     }
 
     /// Import pools from JSON file
@@ -224,6 +1096,18 @@ impl PoolManager {
         for pool in cache_data.pools {
             self.pools.insert(pool.address, pool);
         }
+        for token in cache_data.tokens {
+            self.tokens.insert_discovered(token);
+        }
+        for checkpoint in cache_data.checkpoints {
+            self.checkpoints.insert(checkpoint.factory, checkpoint);
+        }
+        if let Some(block) = cache_data.block {
+            self.record_snapshot(block);
+        }
+        if !cache_data.journal.is_empty() {
+            *self.journal.lock().unwrap() = cache_data.journal;
+        }
 
         // Format timestamp to human-readable date
         let datetime = chrono::DateTime::from_timestamp(cache_data.timestamp as i64, 0)
@@ -241,22 +1125,29 @@ impl PoolManager {
         let total_pools = pools.len();
         
         let mut dex_counts: HashMap<String, usize> = HashMap::new();
-        let total_liquidity_usd = 0.0; // Placeholder for now
-        
+        let mut dex_liquidity_usd: HashMap<String, f64> = HashMap::new();
+
         for pool in &pools {
             *dex_counts.entry(pool.dex_name.clone()).or_insert(0) += 1;
+            *dex_liquidity_usd.entry(pool.dex_name.clone()).or_insert(0.0) += pool.liquidity_usd.unwrap_or(0.0);
         }
 
         CacheStats {
             total_pools,
             dex_counts,
-            total_liquidity_usd,
+            // Float sums of nothing are -0.0, which would display as "$-0.00"
+            total_liquidity_usd: dex_liquidity_usd.values().fold(0.0, |total, value| total + value),
+            dex_liquidity_usd,
+            unvalued_pools: pools.iter().filter(|pool| pool.liquidity_usd.is_none()).count(),
         }
     }
 
     /// Clear all cached pools
     pub fn clear(&self) {
         self.pools.clear();
+        self.checkpoints.clear();
+        *self.snapshot.write().unwrap() = None;
+        *self.journal.lock().unwrap() = ReserveJournal::default();
         info!("Cleared all cached pools");
     }
 }
@@ -265,15 +1156,194 @@ impl PoolManager {
 #[derive(Debug, Serialize, Deserialize)]
 struct CacheData {
     pools: Vec<PoolInfo>,
+    /// Token metadata read from chain; absent in caches written by older versions
+    #[serde(default)]
+    tokens: Vec<TokenInfo>,
+    /// Latest block pools were fetched at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    block: Option<SnapshotBlock>,
+    /// Reserve changes of recent syncs, for rolling back after a reorg
+    #[serde(default, skip_serializing_if = "ReserveJournal::is_empty")]
+    journal: ReserveJournal,
+    /// Progress of interrupted or limited fetches, per factory
+    #[serde(default)]
+    checkpoints: Vec<FetchCheckpoint>,
     timestamp: u64,
 }
 
+/// Tracks the lowest pair index whose fetch hasn't finished, as pools complete out of order
+struct IndexTracker {
+    next: usize,
+    /// Finished indices at or above `next`
+    completed: BTreeSet<usize>,
+    failed: BTreeSet<usize>,
+}
+
+impl IndexTracker {
+    fn new(start: usize, failed: Vec<usize>) -> Self {
+        Self {
+            next: start,
+            completed: BTreeSet::new(),
+            failed: failed.into_iter().collect(),
+        }
+    }
+
+    fn complete(&mut self, index: usize, fetched: bool) {
+        if fetched {
+            self.failed.remove(&index);
+        } else {
+            self.failed.insert(index);
+        }
+
+        if index >= self.next {
+            self.completed.insert(index);
+            while self.completed.remove(&self.next) {
+                self.next += 1;
+            }
+        }
+    }
+
+    fn checkpoint(&self, dex_name: &str, factory: Address) -> FetchCheckpoint {
+        FetchCheckpoint {
+            dex_name: dex_name.to_string(),
+            factory,
+            next_index: self.next,
+            failed: self.failed.range(..self.next).copied().collect(),
+        }
+    }
+}
+
+/// Sort pools by USD liquidity, most liquid first, and keep the top `limit`
+fn rank(pools: &mut Vec<(f64, PoolInfo)>, limit: usize) {
+    pools.sort_by(|a, b| b.0.total_cmp(&a.0));
+    pools.truncate(limit);
+}
+
+/// Load a ranked seed list: a JSON object mapping DEX names to pair addresses, most liquid first
+pub fn load_seed_list(path: &str) -> Result<HashMap<String, Vec<Address>>> {
+    let json = fs::read_to_string(path)
+        .map_err(|e| AggregatorError::ConfigError(format!("Failed to read seed list {}: {}", path, e)))?;
+    serde_json::from_str(&json)
+        .map_err(|e| AggregatorError::ConfigError(format!("Failed to parse seed list {}: {}", path, e)))
+}
+
+/// Decode an ABI string, or a NUL-padded bytes32 as returned by older tokens
+fn decode_string_or_bytes32(data: &[u8]) -> Option<String> {
+    if let Ok(value) = String::decode(data) {
+        if !value.is_empty() {
+            return Some(value);
+        }
+    }
+
+    if data.len() == 32 {
+        let end = data.iter().position(|b| *b == 0).unwrap_or(32);
+        return String::from_utf8(data[..end].to_vec()).ok().filter(|s| !s.is_empty());
+    }
+
+    None
+}
+
+/// Outcome of fetching pools from a factory
+#[derive(Debug, Clone)]
+pub struct FetchReport {
+    pub dex_name: String,
+    pub factory: Address,
+
+    /// Block every pool was read at
+    pub block: SnapshotBlock,
+
+    /// Number of pair indices fetched
+    pub requested: usize,
+
+    /// Checkpointed pair index the fetch continued from
+    pub resumed_from: Option<usize>,
+
+    /// Pools fetched and added to the cache
+    pub pools: Vec<PoolInfo>,
+
+    /// Indices that still failed after every retry
+    pub failures: Vec<FetchFailure>,
+}
+
+/// Outcome of syncing cached reserves to the chain head
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncReport {
+    /// Block the cache was synced to before
+    pub from: SnapshotBlock,
+
+    /// Head block the cache is now synced to
+    pub to: SnapshotBlock,
+
+    /// Sync events applied
+    pub events: usize,
+
+    /// Pools whose reserves changed, by Sync events or by rolling back a reorg
+    pub pools_updated: Vec<Address>,
+
+    /// Reorg found and rolled back before syncing
+    pub reorg: Option<Reorg>,
+}
+
+/// Reorg that replaced the last synced block
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Reorg {
+    /// Synced block that is no longer canonical
+    pub orphaned: SnapshotBlock,
+
+    /// Newest journaled block still on the canonical chain, which pools were rolled back to
+    pub ancestor: SnapshotBlock,
+
+    pub pools_rolled_back: usize,
+}
+
+/// Progress of a pool fetch, reported as it happens
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum FetchEvent {
+    /// Pair count is known and fetching starts
+    Started { dex: String, total: usize },
+
+    /// A pair index was fetched or failed; pass 0 covers every index, later passes the retries
+    Progress { dex: String, pass: usize, done: usize, total: usize, failed: usize },
+
+    /// Indices that failed are fetched again
+    Retrying { dex: String, pass: usize, pending: usize },
+
+    /// Every pass is done
+    Finished { dex: String, fetched: usize, failed: usize },
+}
+
+/// How far a fetch from a factory got, saved with the cache so `--resume` can continue it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FetchCheckpoint {
+    pub dex_name: String,
+    pub factory: Address,
+
+    /// Every pair index below this one was fetched or is listed in `failed`
+    pub next_index: usize,
+
+    /// Indices below `next_index` that could not be fetched
+    #[serde(default)]
+    pub failed: Vec<usize>,
+}
+
+/// A factory pair index that could not be fetched
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchFailure {
+    pub index: usize,
+    pub error: String,
+}
+
 /// Cache statistics
 #[derive(Debug)]
 pub struct CacheStats {
     pub total_pools: usize,
     pub dex_counts: HashMap<String, usize>,
+    /// Sum of valued pools' liquidity
     pub total_liquidity_usd: f64,
+    pub dex_liquidity_usd: HashMap<String, f64>,
+    /// Pools whose tokens have no USD price
+    pub unvalued_pools: usize,
 }
 
 #[cfg(test)]
@@ -283,10 +1353,198 @@ mod tests {
     #[test]
     fn test_cache_stats() {
         let config = Config::default();
-        let provider = Arc::new(Provider::<Http>::try_from(config.rpc_url.clone()).unwrap());
+        let provider = Arc::new(crate::rpc::connect(&config).unwrap());
         let manager = PoolManager::new(provider, config);
 
         let stats = manager.get_cache_stats();
         assert_eq!(stats.total_pools, 0);
     }
+
+    #[test]
+    fn test_decode_string_or_bytes32() {
+        use ethers::abi::AbiEncode;
+
+        assert_eq!(decode_string_or_bytes32(&"USDC".to_string().encode()), Some("USDC".to_string()));
+
+        // MKR returns its symbol as bytes32
+        let mut mkr = [0u8; 32];
+        mkr[..3].copy_from_slice(b"MKR");
+        assert_eq!(decode_string_or_bytes32(&mkr), Some("MKR".to_string()));
+
+        assert_eq!(decode_string_or_bytes32(&[0u8; 32]), None);
+        assert_eq!(decode_string_or_bytes32(&[1, 2, 3]), None);
+    }
+
+    #[test]
+    fn test_cache_round_trips_discovered_tokens() {
+        let config = Config::default();
+        let provider = Arc::new(crate::rpc::connect(&config).unwrap());
+        let manager = PoolManager::new(provider.clone(), config.clone());
+        let token = TokenInfo {
+            address: Address::from_low_u64_be(0xabc),
+            symbol: "NEW".to_string(),
+            name: "New Token".to_string(),
+            decimals: 9,
+        };
+        manager.tokens.insert_discovered(token);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pools.json");
+        manager.export_to_file(path.to_str().unwrap()).unwrap();
+
+        let restored = PoolManager::new(provider, config);
+        restored.import_from_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(restored.tokens.decimals(Address::from_low_u64_be(0xabc)), Some(9));
+    }
+
+    #[test]
+    fn test_fetch_events_serialize_as_tagged_json() {
+        let event = FetchEvent::Progress {
+            dex: "Uniswap".to_string(),
+            pass: 0,
+            done: 3,
+            total: 10,
+            failed: 1,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"progress","dex":"Uniswap","pass":0,"done":3,"total":10,"failed":1}"#
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_tracks_lowest_unfinished_index() {
+        let mut tracker = IndexTracker::new(10, vec![3]);
+        tracker.complete(11, true);
+        tracker.complete(12, false);
+        assert_eq!(tracker.checkpoint("Uniswap", Address::zero()).next_index, 10);
+
+        tracker.complete(10, true);
+        tracker.complete(3, true);
+        let checkpoint = tracker.checkpoint("Uniswap", Address::zero());
+        assert_eq!((checkpoint.next_index, checkpoint.failed), (13, vec![12]));
+    }
+
+    #[test]
+    fn test_cache_round_trips_checkpoints() {
+        let config = Config::default();
+        let provider = Arc::new(crate::rpc::connect(&config).unwrap());
+        let manager = PoolManager::new(provider.clone(), config.clone());
+        let checkpoint = FetchCheckpoint {
+            dex_name: "Uniswap".to_string(),
+            factory: Address::from_low_u64_be(0xf),
+            next_index: 30_000,
+            failed: vec![17, 29_000],
+        };
+        manager.checkpoints.insert(checkpoint.factory, checkpoint.clone());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pools.json");
+        manager.export_to_file(path.to_str().unwrap()).unwrap();
+
+        let restored = PoolManager::new(provider, config);
+        restored.import_from_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(restored.checkpoint(&checkpoint.factory), Some(checkpoint));
+    }
+
+    #[test]
+    fn test_rank_keeps_most_liquid() {
+        let pool = |n: u64| PoolInfo {
+            address: Address::from_low_u64_be(n),
+            token0: Address::from_low_u64_be(1),
+            token1: Address::from_low_u64_be(2),
+            reserve0: U256::one(),
+            reserve1: U256::one(),
+            fee_bps: 30,
+            dex_name: "Uniswap".to_string(),
+            last_updated: 0,
+            block_hash: None,
+            liquidity_usd: None,
+        };
+        let mut pools = vec![(10.0, pool(1)), (5_000.0, pool(2)), (300.0, pool(3))];
+
+        rank(&mut pools, 2);
+        let addresses: Vec<u64> = pools.iter().map(|(_, pool)| pool.address.to_low_u64_be()).collect();
+        assert_eq!(addresses, [2, 3]);
+    }
+
+    #[test]
+    fn test_revalue_only_given_pools() {
+        let config = Config::default();
+        let provider = Arc::new(crate::rpc::connect(&config).unwrap());
+        let manager = PoolManager::new(provider, config);
+        for n in [1, 2] {
+            manager.insert_pool(PoolInfo {
+                address: Address::from_low_u64_be(n),
+                token0: Address::from_low_u64_be(10),
+                token1: Address::from_low_u64_be(11),
+                reserve0: U256::one(),
+                reserve1: U256::one(),
+                fee_bps: 30,
+                dex_name: "Uniswap".to_string(),
+                last_updated: 0,
+                block_hash: None,
+                liquidity_usd: Some(100.0),
+            });
+        }
+
+        manager.set_liquidity_usd_of(&[Address::from_low_u64_be(2), Address::from_low_u64_be(3)], |_| Some(500.0));
+        let liquidity = |n: u64| manager.get_pool(&Address::from_low_u64_be(n)).unwrap().liquidity_usd;
+        assert_eq!((liquidity(1), liquidity(2)), (Some(100.0), Some(500.0)));
+    }
+
+    #[test]
+    fn test_sync_starts_from_oldest_pool() {
+        let config = Config::default();
+        let provider = Arc::new(crate::rpc::connect(&config).unwrap());
+        let manager = PoolManager::new(provider, config);
+        let block = |number: u64| SnapshotBlock { number, hash: H256::from_low_u64_be(number) };
+        let pool = |n: u64, number: u64| PoolInfo {
+            address: Address::from_low_u64_be(n),
+            token0: Address::from_low_u64_be(10),
+            token1: Address::from_low_u64_be(11),
+            reserve0: U256::from(100),
+            reserve1: U256::from(100),
+            fee_bps: 30,
+            dex_name: "Uniswap".to_string(),
+            last_updated: number,
+            block_hash: Some(block(number).hash),
+            liquidity_usd: None,
+        };
+        let sync = |n: u64, number: u64, reserve0: u64| Log {
+            address: Address::from_low_u64_be(n),
+            topics: vec![SyncFilter::signature()],
+            data: abi::encode(&[abi::Token::Uint(reserve0.into()), abi::Token::Uint(100.into())]).into(),
+            block_number: Some(number.into()),
+            block_hash: Some(block(number).hash),
+            log_index: Some(n.into()),
+            ..Default::default()
+        };
+
+        // Pool 1 was fetched at block 10 and pool 2 at block 20, the snapshot block
+        manager.insert_pool(pool(1, 10));
+        manager.insert_pool(pool(2, 20));
+        manager.record_snapshot(block(20));
+        assert_eq!(manager.sync_start(), Some(block(10)));
+
+        let (events, updated) = manager.apply_sync_logs(vec![sync(1, 15, 150), sync(2, 15, 150), sync(2, 25, 250)]);
+        assert_eq!((events, updated.len()), (2, 2));
+        let reserve0 = |n: u64| manager.get_pool(&Address::from_low_u64_be(n)).unwrap().reserve0.as_u64();
+        // Pool 2 already includes block 15
+        assert_eq!((reserve0(1), reserve0(2)), (150, 250));
+        assert_eq!(manager.sync_start(), Some(block(15)));
+    }
+
+    #[test]
+    fn test_load_seed_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seeds.json");
+        fs::write(&path, r#"{"Uniswap": ["0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"]}"#).unwrap();
+
+        let seeds = load_seed_list(path.to_str().unwrap()).unwrap();
+        assert_eq!(seeds["Uniswap"].len(), 1);
+
+        fs::write(&path, r#"["0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"]"#).unwrap();
+        assert!(load_seed_list(path.to_str().unwrap()).is_err());
+    }
 }
@@ -0,0 +1,59 @@
+use crate::math;
+use crate::types::{AggregatorError, Result};
+use ethers::types::U256;
+use rust_decimal::Decimal;
+
+/// Units of the output token per unit of the input token
+/// MathError if the input is zero or the result doesn't fit a Decimal
+pub fn price(amount_in: U256, decimals_in: u8, amount_out: U256, decimals_out: u8) -> Result<Decimal> {
+    let amount_in = math::to_decimal(amount_in, decimals_in)?;
+    let amount_out = math::to_decimal(amount_out, decimals_out)?;
+    amount_out
+        .checked_div(amount_in)
+        .map(|p| p.normalize())
+        .ok_or(AggregatorError::MathError)
+}
+
+/// Units of the input token per unit of the output token
+pub fn inverse(price: Decimal) -> Result<Decimal> {
+    Decimal::ONE
+        .checked_div(price)
+        .map(|p| p.normalize())
+        .ok_or(AggregatorError::MathError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_price_is_decimals_aware() {
+        // 1 WETH (18 decimals) -> 1800 USDC (6 decimals)
+        let weth = U256::exp10(18);
+        let usdc = U256::from(1_800_000_000u64);
+
+        assert_eq!(price(weth, 18, usdc, 6).unwrap(), Decimal::from(1800));
+        assert_eq!(price(usdc, 6, weth, 18).unwrap(), Decimal::from_str("0.0005555555555555555555555556").unwrap());
+        assert_eq!(inverse(Decimal::from(1800)).unwrap(), price(usdc, 6, weth, 18).unwrap());
+        assert!(price(U256::zero(), 18, usdc, 6).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_price_never_panics(
+            amount_in in any::<[u64; 4]>().prop_map(U256),
+            amount_out in any::<[u64; 4]>().prop_map(U256),
+            decimals_in in 0u8..=36,
+            decimals_out in 0u8..=36,
+        ) {
+            if let Ok(p) = price(amount_in, decimals_in, amount_out, decimals_out) {
+                prop_assert!(!p.is_sign_negative());
+                if !p.is_zero() {
+                    let _ = inverse(p);
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,9 @@
+use crate::price;
+use crate::tokens::TokenRegistry;
 use crate::types::{AggregatorError, PoolInfo, Result, RouteHop};
 use crate::utils;
 use ethers::types::{Address, U256};
+use rust_decimal::Decimal;
 use tracing::debug;
 
 /// Quote engine for calculating swap outputs
@@ -167,14 +170,24 @@ pub struct QuoteResult {
 }
 
 impl QuoteResult {
-    /// Get the exchange rate
-    pub fn exchange_rate(&self) -> f64 {
-        if self.amount_in.is_zero() {
-            return 0.0;
-        }
-        let amount_in = self.amount_in.as_u128() as f64;
-        let amount_out = self.amount_out.as_u128() as f64;
-        amount_out / amount_in
+    /// Units of token_out received per unit of token_in
+    pub fn execution_price(&self, tokens: &TokenRegistry) -> Result<Decimal> {
+        price::price(
+            self.amount_in,
+            tokens.require_decimals(self.token_in)?,
+            self.amount_out,
+            tokens.require_decimals(self.token_out)?,
+        )
+    }
+
+    /// Units of token_in paid per unit of token_out
+    pub fn inverse_price(&self, tokens: &TokenRegistry) -> Result<Decimal> {
+        price::inverse(self.execution_price(tokens)?)
+    }
+
+    /// Spot price of the pool before the swap, for comparison with the execution price
+    pub fn spot_price(&self, tokens: &TokenRegistry) -> Result<Decimal> {
+        self.pool.spot_price(&self.token_in, tokens)
     }
 }
 
@@ -192,6 +205,8 @@ mod tests {
             fee_bps: 30,
             dex_name: "TestDEX".to_string(),
             last_updated: 0,
+            block_hash: None,
+            liquidity_usd: None,
         }
     }
 
@@ -242,4 +257,28 @@ mod tests {
         let result = QuoteEngine::find_best_direct_pool(&pools, token_in, token_out, amount_in);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_prices_use_token_decimals() {
+        let tokens = TokenRegistry::with_defaults(1);
+        let weth = tokens.resolve("WETH").unwrap();
+        let usdc = tokens.resolve("USDC").unwrap();
+
+        // 1000 WETH / 2M USDC
+        let pool = PoolInfo {
+            token0: usdc,
+            token1: weth,
+            reserve0: U256::from(2_000_000_000_000u64),
+            reserve1: U256::exp10(21),
+            ..create_test_pool()
+        };
+
+        let result = QuoteEngine::calculate_pool_output(&pool, weth, U256::exp10(18)).unwrap();
+        let spot = result.spot_price(&tokens).unwrap();
+        let execution = result.execution_price(&tokens).unwrap();
+
+        assert_eq!(spot, Decimal::from(2000));
+        assert!(execution < spot && execution > Decimal::from(1990));
+        assert_eq!(result.inverse_price(&tokens).unwrap(), price::inverse(execution).unwrap());
+    }
 }
@@ -0,0 +1,328 @@
+use crate::pools::SwapFilter;
+use crate::swap::TransferFilter;
+use crate::tokens::TokenRegistry;
+use crate::types::{AggregatorError, PoolInfo, Result, RouteHop, RouteQuote};
+use crate::utils;
+use crate::verify::deviation_bps;
+use ethers::contract::parse_log;
+use ethers::types::{Address, Log, TransactionReceipt, H256, U256};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// An ERC20 Transfer event found in a receipt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenTransfer {
+    /// Token contract that emitted the event
+    pub token: Address,
+
+    /// Sender
+    pub from: Address,
+
+    /// Recipient
+    pub to: Address,
+
+    /// Amount transferred
+    pub value: U256,
+}
+
+/// A swap transaction rebuilt from its receipt logs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutedSwap {
+    /// Transaction hash
+    pub tx_hash: H256,
+
+    /// Block the transaction was included in
+    pub block_number: Option<u64>,
+
+    /// Transaction sender
+    pub from: Address,
+
+    /// Receipt status
+    pub success: bool,
+
+    /// Gas used by the transaction
+    pub gas_used: U256,
+
+    /// Price paid per unit of gas
+    pub effective_gas_price: Option<U256>,
+
+    /// Executed path, one hop per V2 Swap event; gas_estimate holds the gas actually used
+    pub route: RouteQuote,
+
+    /// Every Transfer event in the receipt
+    pub transfers: Vec<TokenTransfer>,
+}
+
+/// Comparison of an executed swap with the quote it was based on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteDiff {
+    /// The executed path went through the quoted pools in the quoted order
+    pub path_matches: bool,
+
+    /// Output promised by the quote
+    pub expected_amount_out: U256,
+
+    /// Output of the last executed hop
+    pub realized_amount_out: U256,
+
+    /// How much less than quoted was received (zero if more)
+    pub shortfall: U256,
+
+    /// Realized vs quoted output in basis points (positive = received more)
+    pub deviation_bps: i64,
+
+    /// Gas estimate of the quote
+    pub gas_estimate: U256,
+
+    /// Gas used by the transaction
+    pub gas_used: U256,
+
+    /// Quoted output per unit of input, in token units (None if decimals are unknown)
+    pub quoted_price: Option<Decimal>,
+
+    /// Executed output per unit of input, in token units (None if decimals are unknown)
+    pub effective_price: Option<Decimal>,
+}
+
+impl ExecutedSwap {
+    /// Rebuild an executed swap from a receipt and the pools its Swap events came from
+    /// `pools` must hold the pool for every Swap event, in log order
+    pub fn from_receipt(receipt: &TransactionReceipt, pools: &[PoolInfo]) -> Result<Self> {
+        let swaps = decode_swaps(&receipt.logs);
+        if swaps.len() != pools.len() {
+            return Err(AggregatorError::InvalidRoute(format!(
+                "Receipt has {} Swap events but {} pools were resolved",
+                swaps.len(),
+                pools.len()
+            )));
+        }
+
+        let hops = swaps
+            .iter()
+            .zip(pools)
+            .map(|((_, swap), pool)| {
+                executed_hop(pool, swap).ok_or_else(|| {
+                    AggregatorError::InvalidRoute(format!("Swap event on {:?} has no input side", pool.address))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let gas_used = receipt.gas_used.unwrap_or_default();
+
+        Ok(Self {
+            tx_hash: receipt.transaction_hash,
+            block_number: receipt.block_number.map(|n| n.as_u64()),
+            from: receipt.from,
+            success: receipt.status.map(|s| s.as_u64() == 1).unwrap_or(true),
+            gas_used,
+            effective_gas_price: receipt.effective_gas_price,
+            route: executed_route(hops, gas_used)?,
+            transfers: decode_transfers(&receipt.logs),
+        })
+    }
+
+    /// Diff against the quote the swap was built from
+    pub fn diff(&self, quote: &RouteQuote, tokens: &TokenRegistry) -> QuoteDiff {
+        let realized = self.route.amount_out;
+        let path_matches = quote.hops.len() == self.route.hops.len()
+            && quote
+                .hops
+                .iter()
+                .zip(&self.route.hops)
+                .all(|(quoted, executed)| quoted.pool == executed.pool && quoted.token_out == executed.token_out);
+
+        QuoteDiff {
+            path_matches,
+            expected_amount_out: quote.amount_out,
+            realized_amount_out: realized,
+            shortfall: quote.amount_out.saturating_sub(realized),
+            deviation_bps: deviation_bps(quote.amount_out, realized),
+            gas_estimate: quote.gas_estimate,
+            gas_used: self.gas_used,
+            quoted_price: quote.execution_price(tokens).ok(),
+            effective_price: self.route.execution_price(tokens).ok(),
+        }
+    }
+}
+
+/// All V2 Swap events in a set of logs, with the pool that emitted them
+pub fn decode_swaps(logs: &[Log]) -> Vec<(Address, SwapFilter)> {
+    logs.iter()
+        .filter_map(|log| parse_log::<SwapFilter>(log.clone()).ok().map(|swap| (log.address, swap)))
+        .collect()
+}
+
+/// All ERC20 Transfer events in a set of logs
+pub fn decode_transfers(logs: &[Log]) -> Vec<TokenTransfer> {
+    logs.iter()
+        .filter_map(|log| {
+            parse_log::<TransferFilter>(log.clone()).ok().map(|transfer| TokenTransfer {
+                token: log.address,
+                from: transfer.from,
+                to: transfer.to,
+                value: transfer.value,
+            })
+        })
+        .collect()
+}
+
+/// Hop for one Swap event; the input side is whichever token has a non-zero amountIn
+fn executed_hop(pool: &PoolInfo, swap: &SwapFilter) -> Option<RouteHop> {
+    let (token_in, token_out, amount_in, amount_out) = if !swap.amount_0_in.is_zero() {
+        (pool.token0, pool.token1, swap.amount_0_in, swap.amount_1_out)
+    } else if !swap.amount_1_in.is_zero() {
+        (pool.token1, pool.token0, swap.amount_1_in, swap.amount_0_out)
+    } else {
+        return None;
+    };
+
+    Some(RouteHop {
+        pool: pool.address,
+        token_in,
+        token_out,
+        dex_name: pool.dex_name.clone(),
+        amount_in,
+        amount_out,
+        fee: utils::calculate_fee(amount_in, pool.fee_bps),
+        gas_estimate: U256::zero(),
+    })
+}
+
+fn executed_route(hops: Vec<RouteHop>, gas_used: U256) -> Result<RouteQuote> {
+    let (first, last) = match (hops.first(), hops.last()) {
+        (Some(first), Some(last)) => (first.clone(), last.clone()),
+        _ => return Err(AggregatorError::InvalidRoute("Receipt contains no V2 Swap events".to_string())),
+    };
+
+    let mut tokens = vec![first.token_in];
+    tokens.extend(hops.iter().map(|hop| hop.token_out));
+    let description = tokens
+        .iter()
+        .map(|t| format!("{:?}", t))
+        .collect::<Vec<_>>()
+        .join(" → ");
+
+    Ok(RouteQuote {
+        token_in: first.token_in,
+        token_out: last.token_out,
+        amount_in: first.amount_in,
+        amount_out: last.amount_out,
+        total_fee: hops.iter().fold(U256::zero(), |acc, hop| acc.saturating_add(hop.fee)),
+        hops,
+        gas_estimate: gas_used,
+        price_impact_bps: 0,
+        score: 0.0,
+        description,
+        simulated_amount_out: None,
+        simulated_gas_used: None,
+        block: None,
+        oldest_block: None,
+        staleness_blocks: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::contract::EthEvent;
+    use ethers::types::U64;
+
+    fn word(value: u64) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        U256::from(value).to_big_endian(&mut bytes);
+        bytes
+    }
+
+    fn pool(address: u64, token0: u64, token1: u64) -> PoolInfo {
+        PoolInfo {
+            address: Address::from_low_u64_be(address),
+            token0: Address::from_low_u64_be(token0),
+            token1: Address::from_low_u64_be(token1),
+            reserve0: U256::zero(),
+            reserve1: U256::zero(),
+            fee_bps: 30,
+            dex_name: "Uniswap".to_string(),
+            last_updated: 0,
+            block_hash: None,
+            liquidity_usd: None,
+        }
+    }
+
+    fn swap_log(pool: u64, amounts: [u64; 4]) -> Log {
+        Log {
+            address: Address::from_low_u64_be(pool),
+            topics: vec![
+                SwapFilter::signature(),
+                H256::from(Address::from_low_u64_be(0xaa)),
+                H256::from(Address::from_low_u64_be(0xbb)),
+            ],
+            data: amounts.iter().flat_map(|a| word(*a)).collect::<Vec<u8>>().into(),
+            ..Default::default()
+        }
+    }
+
+    fn transfer_log(token: u64, to: u64, value: u64) -> Log {
+        Log {
+            address: Address::from_low_u64_be(token),
+            topics: vec![
+                TransferFilter::signature(),
+                H256::from(Address::from_low_u64_be(0xaa)),
+                H256::from(Address::from_low_u64_be(to)),
+            ],
+            data: word(value).to_vec().into(),
+            ..Default::default()
+        }
+    }
+
+    fn receipt(logs: Vec<Log>) -> TransactionReceipt {
+        TransactionReceipt {
+            logs,
+            gas_used: Some(U256::from(150_000)),
+            status: Some(U64::one()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rebuilds_two_hop_route() {
+        // 1000 of token 1 -> 900 of token 2 (token 2 is token0 in the second pool) -> 800 of token 3
+        let receipt = receipt(vec![
+            transfer_log(1, 100, 1000),
+            swap_log(100, [1000, 0, 0, 900]),
+            swap_log(101, [900, 0, 0, 800]),
+            transfer_log(3, 0xbb, 800),
+        ]);
+        let pools = vec![pool(100, 1, 2), pool(101, 2, 3)];
+
+        let executed = ExecutedSwap::from_receipt(&receipt, &pools).unwrap();
+
+        assert_eq!(executed.route.hops.len(), 2);
+        assert_eq!(executed.route.token_in, Address::from_low_u64_be(1));
+        assert_eq!(executed.route.token_out, Address::from_low_u64_be(3));
+        assert_eq!(executed.route.amount_in, U256::from(1000));
+        assert_eq!(executed.route.amount_out, U256::from(800));
+        assert_eq!(executed.transfers.len(), 2);
+    }
+
+    #[test]
+    fn test_diff_reports_shortfall() {
+        let receipt = receipt(vec![swap_log(100, [0, 1000, 950, 0])]);
+        let executed = ExecutedSwap::from_receipt(&receipt, &[pool(100, 2, 1)]).unwrap();
+
+        let mut quote = executed.route.clone();
+        quote.amount_out = U256::from(1000);
+        quote.gas_estimate = U256::from(120_000);
+
+        let diff = executed.diff(&quote, &TokenRegistry::with_defaults(1));
+        assert!(diff.path_matches);
+        assert_eq!(diff.shortfall, U256::from(50));
+        assert_eq!(diff.deviation_bps, -500);
+        assert_eq!(diff.gas_used, U256::from(150_000));
+    }
+
+    #[test]
+    fn test_receipt_without_swaps_is_rejected() {
+        let receipt = receipt(vec![transfer_log(1, 2, 10)]);
+        assert!(ExecutedSwap::from_receipt(&receipt, &[]).is_err());
+    }
+}
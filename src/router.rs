@@ -4,10 +4,17 @@ use ethers::types::{Address, U256};
 use std::collections::{HashMap, HashSet, VecDeque};
 use tracing::{debug, info};
 
+/// Score penalty per block of reserve age, in basis points of the route's output
+const STALENESS_PENALTY_BPS_PER_BLOCK: f64 = 1.0;
+
+/// Cap on the staleness penalty, in basis points of the route's output
+const MAX_STALENESS_PENALTY_BPS: f64 = 500.0;
+
 /// Router for finding optimal swap routes
 pub struct Router {
     optimization: OptimizationStrategy,
     max_hops: usize,
+    connectors: HashSet<Address>,
 }
 
 impl Router {
@@ -16,9 +23,16 @@ impl Router {
         Self {
             optimization,
             max_hops: max_hops.min(4), // Cap at 4 hops for performance
+            connectors: HashSet::new(),
         }
     }
 
+    /// Only let multi-hop routes pass through these tokens; empty allows any token
+    pub fn with_connectors(mut self, connectors: HashSet<Address>) -> Self {
+        self.connectors = connectors;
+        self
+    }
+
     /// Find the best route between two tokens
     pub fn find_best_route(
         &self,
@@ -41,6 +55,20 @@ impl Router {
         amount_in: U256,
         context: &MarketContext,
         limit: usize,
+    ) -> Result<Vec<RouteQuote>> {
+        let graph = RouteGraph::new(pools);
+        self.find_top_routes_in(&graph, token_in, token_out, amount_in, context, limit)
+    }
+
+    /// Find top N routes using a prebuilt graph snapshot, sorted by score
+    pub fn find_top_routes_in(
+        &self,
+        graph: &RouteGraph,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+        context: &MarketContext,
+        limit: usize,
     ) -> Result<Vec<RouteQuote>> {
         info!(
             "Finding best route from {:?} to {:?} with {} strategy",
@@ -48,7 +76,7 @@ impl Router {
         );
 
         // Find all possible routes
-        let routes = self.find_all_routes(pools, token_in, token_out)?;
+        let routes = self.find_all_routes(graph, token_in, token_out)?;
 
         if routes.is_empty() {
             return Err(AggregatorError::NoRouteFound {
@@ -63,7 +91,7 @@ impl Router {
         let mut route_quotes = Vec::new();
 
         for route in routes {
-            match self.calculate_route_quote(&route, pools, amount_in, context) {
+            match self.calculate_route_quote(&route, graph, amount_in, context) {
                 Ok(quote) => route_quotes.push(quote),
                 Err(e) => {
                     debug!("Failed to calculate route quote: {}", e);
@@ -79,7 +107,7 @@ impl Router {
         }
 
         // Sort by score (best first)
-        route_quotes.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        route_quotes.sort_by(|a, b| b.score.total_cmp(&a.score));
 
         if !route_quotes.is_empty() {
             info!(
@@ -95,12 +123,11 @@ impl Router {
     /// Find all possible routes up to max_hops
     fn find_all_routes(
         &self,
-        pools: &[PoolInfo],
+        graph: &RouteGraph,
         token_in: Address,
         token_out: Address,
     ) -> Result<Vec<Route>> {
-        // Build adjacency map: token -> (pool, other_token)
-        let adjacency = self.build_adjacency_map(pools);
+        let adjacency = &graph.adjacency;
 
         let mut all_routes = Vec::new();
 
@@ -118,7 +145,7 @@ impl Router {
 
         // Try multi-hop routes (2+ hops) using BFS
         if self.max_hops > 1 {
-            let multi_hop_routes = self.bfs_routes(&adjacency, token_in, token_out, self.max_hops);
+            let multi_hop_routes = self.bfs_routes(adjacency, token_in, token_out, self.max_hops);
             all_routes.extend(multi_hop_routes);
         }
 
@@ -129,7 +156,7 @@ impl Router {
     }
 
     /// Build adjacency map for token graph
-    fn build_adjacency_map(&self, pools: &[PoolInfo]) -> HashMap<Address, Vec<(Address, Address)>> {
+    fn build_adjacency_map(pools: &[PoolInfo]) -> HashMap<Address, Vec<(Address, Address)>> {
         let mut adjacency: HashMap<Address, Vec<(Address, Address)>> = HashMap::new();
 
         for pool in pools {
@@ -140,12 +167,12 @@ impl Router {
 
             adjacency
                 .entry(pool.token0)
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push((pool.address, pool.token1));
 
             adjacency
                 .entry(pool.token1)
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push((pool.address, pool.token0));
         }
 
@@ -192,8 +219,8 @@ impl Router {
                             tokens: new_path_tokens.clone(),
                             pools: new_path_pools.clone(),
                         });
-                    } else {
-                        // Continue searching
+                    } else if self.connectors.is_empty() || self.connectors.contains(next_token) {
+                        // Continue searching through connector tokens
                         queue.push_back((*next_token, new_path_tokens, new_path_pools));
                     }
                 }
@@ -222,7 +249,7 @@ impl Router {
     fn calculate_route_quote(
         &self,
         route: &Route,
-        pools: &[PoolInfo],
+        graph: &RouteGraph,
         amount_in: U256,
         context: &MarketContext,
     ) -> Result<RouteQuote> {
@@ -230,7 +257,7 @@ impl Router {
         let route_pools: Vec<PoolInfo> = route
             .pools
             .iter()
-            .filter_map(|addr| pools.iter().find(|p| p.address == *addr).cloned())
+            .filter_map(|addr| graph.pools.get(addr).cloned())
             .collect();
 
         if route_pools.len() != route.pools.len() {
@@ -242,7 +269,7 @@ impl Router {
 
         // Calculate totals
         let amount_out = hops.last().map(|h| h.amount_out).unwrap_or(U256::zero());
-        let total_fee = hops.iter().map(|h| h.fee).fold(U256::zero(), |acc, f| acc + f);
+        let total_fee = hops.iter().map(|h| h.fee).fold(U256::zero(), |acc, f| acc.saturating_add(f));
         let gas_estimate = hops
             .iter()
             .map(|h| h.gas_estimate)
@@ -251,12 +278,27 @@ impl Router {
         // Calculate price impact (approximate for multi-hop)
         let price_impact_bps = self.estimate_route_price_impact(&hops);
 
+        // Reserves read at block 0 predate block tracking, so their age is unknown
+        let oldest_block = route_pools
+            .iter()
+            .map(|pool| pool.last_updated)
+            .min()
+            .filter(|block| *block > 0);
+        let staleness_blocks = oldest_block
+            .filter(|_| context.block_number > 0)
+            .map(|block| context.block_number.saturating_sub(block));
+
         // Calculate optimization score
-        let score = self.calculate_score(amount_out, gas_estimate, price_impact_bps, context);
+        let score = self.calculate_score(amount_out, gas_estimate, price_impact_bps, staleness_blocks, context)?;
 
         // Generate description
         let description = self.generate_route_description(&route.tokens);
 
+        let block = route_pools
+            .first()
+            .and_then(PoolInfo::snapshot_block)
+            .filter(|block| route_pools.iter().all(|pool| pool.snapshot_block() == Some(*block)));
+
         Ok(RouteQuote {
             token_in: route.tokens[0],
             token_out: *route.tokens.last().unwrap(),
@@ -268,6 +310,11 @@ impl Router {
             price_impact_bps,
             score,
             description,
+            simulated_amount_out: None,
+            simulated_gas_used: None,
+            block,
+            oldest_block,
+            staleness_blocks,
         })
     }
 
@@ -284,30 +331,32 @@ impl Router {
         amount_out: U256,
         gas_estimate: U256,
         price_impact_bps: u32,
+        staleness_blocks: Option<u64>,
         context: &MarketContext,
-    ) -> f64 {
+    ) -> Result<f64> {
         let (price_weight, gas_weight, slippage_weight) = self.optimization.get_weights();
 
-        // Normalize output amount (higher is better)
-        let output_score = amount_out.as_u128() as f64;
+        // Normalize output amount (higher is better), discounted for reserves that may have
+        // moved since they were read
+        let staleness_penalty_bps = (staleness_blocks.unwrap_or(0) as f64 * STALENESS_PENALTY_BPS_PER_BLOCK)
+            .min(MAX_STALENESS_PENALTY_BPS);
+        let output_score = crate::math::to_f64(amount_out) * (1.0 - staleness_penalty_bps / 10_000.0);
 
         // Calculate gas cost in USD (lower is better, so negate)
         let gas_cost_usd = crate::utils::estimate_gas_cost_usd(
             gas_estimate,
-            context.gas_price_gwei,
+            context.gas_price_wei,
             context.eth_price_usd,
-        );
+        )?;
         let gas_score = -gas_cost_usd * 1000.0; // Scale up for visibility
 
         // Slippage penalty (lower is better, so negate)
         let slippage_score = -(price_impact_bps as f64);
 
         // Composite score
-        let score = (output_score * price_weight)
+        Ok((output_score * price_weight)
             + (gas_score * gas_weight)
-            + (slippage_score * slippage_weight);
-
-        score
+            + (slippage_score * slippage_weight))
     }
 
     /// Generate human-readable route description
@@ -320,6 +369,32 @@ impl Router {
     }
 }
 
+/// Snapshot of the token graph, shared across many route searches
+pub struct RouteGraph {
+    pub(crate) pools: HashMap<Address, PoolInfo>,
+    pub(crate) adjacency: HashMap<Address, Vec<(Address, Address)>>,
+}
+
+impl RouteGraph {
+    /// Build a graph from a set of pools
+    pub fn new(pools: &[PoolInfo]) -> Self {
+        Self {
+            pools: pools.iter().map(|p| (p.address, p.clone())).collect(),
+            adjacency: Router::build_adjacency_map(pools),
+        }
+    }
+
+    /// Number of pools in the snapshot
+    pub fn pool_count(&self) -> usize {
+        self.pools.len()
+    }
+
+    /// Check if the snapshot has no pools
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+}
+
 /// A route through pools
 #[derive(Debug, Clone)]
 struct Route {
@@ -330,6 +405,7 @@ struct Route {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ethers::types::H256;
 
     fn create_test_pools() -> Vec<PoolInfo> {
         vec![
@@ -342,6 +418,8 @@ mod tests {
                 fee_bps: 30,
                 dex_name: "TestDEX".to_string(),
                 last_updated: 0,
+                block_hash: None,
+                liquidity_usd: None,
             },
             PoolInfo {
                 address: Address::from_low_u64_be(101),
@@ -352,6 +430,8 @@ mod tests {
                 fee_bps: 30,
                 dex_name: "TestDEX".to_string(),
                 last_updated: 0,
+                block_hash: None,
+                liquidity_usd: None,
             },
         ]
     }
@@ -359,8 +439,7 @@ mod tests {
     #[test]
     fn test_build_adjacency_map() {
         let pools = create_test_pools();
-        let router = Router::new(OptimizationStrategy::Price, 3);
-        let adjacency = router.build_adjacency_map(&pools);
+        let adjacency = Router::build_adjacency_map(&pools);
 
         assert!(adjacency.contains_key(&Address::from_low_u64_be(1)));
         assert!(adjacency.contains_key(&Address::from_low_u64_be(2)));
@@ -373,9 +452,98 @@ mod tests {
         let router = Router::new(OptimizationStrategy::Price, 3);
 
         let routes = router
-            .find_all_routes(&pools, Address::from_low_u64_be(1), Address::from_low_u64_be(3))
+            .find_all_routes(&RouteGraph::new(&pools), Address::from_low_u64_be(1), Address::from_low_u64_be(3))
             .unwrap();
 
         assert!(!routes.is_empty());
     }
+
+    #[test]
+    fn test_routes_only_pass_through_connectors() {
+        let pools = create_test_pools();
+        let graph = RouteGraph::new(&pools);
+        let (token_in, token_out) = (Address::from_low_u64_be(1), Address::from_low_u64_be(3));
+
+        let router = Router::new(OptimizationStrategy::Price, 3)
+            .with_connectors(HashSet::from([Address::from_low_u64_be(9)]));
+        assert!(router.find_all_routes(&graph, token_in, token_out).unwrap().is_empty());
+
+        let router = Router::new(OptimizationStrategy::Price, 3)
+            .with_connectors(HashSet::from([Address::from_low_u64_be(2)]));
+        assert_eq!(router.find_all_routes(&graph, token_in, token_out).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_shared_graph_matches_fresh_search() {
+        let pools = create_test_pools();
+        let graph = RouteGraph::new(&pools);
+        let router = Router::new(OptimizationStrategy::Price, 3);
+        let context = MarketContext::default();
+        let amount_in = U256::from(1_000_000_000_000_000_000u128);
+
+        let token_in = Address::from_low_u64_be(1);
+        let token_out = Address::from_low_u64_be(3);
+        let fresh = router
+            .find_top_routes(&pools, token_in, token_out, amount_in, &context, 1)
+            .unwrap();
+        let shared = router
+            .find_top_routes_in(&graph, token_in, token_out, amount_in, &context, 1)
+            .unwrap();
+
+        assert_eq!(graph.pool_count(), 2);
+        assert_eq!(fresh[0].amount_out, shared[0].amount_out);
+    }
+
+    #[test]
+    fn test_quote_block_requires_pools_from_one_snapshot() {
+        let mut pools = create_test_pools();
+        let router = Router::new(OptimizationStrategy::Price, 3);
+        let context = MarketContext::default();
+        let amount_in = U256::from(1_000_000_000_000_000_000u128);
+        let (token_in, token_out) = (Address::from_low_u64_be(1), Address::from_low_u64_be(3));
+
+        for pool in &mut pools {
+            pool.last_updated = 16;
+            pool.block_hash = Some(H256::from_low_u64_be(0xb10c));
+        }
+        let quote = router
+            .find_top_routes(&pools, token_in, token_out, amount_in, &context, 1)
+            .unwrap();
+        assert_eq!(quote[0].block.map(|block| block.number), Some(16));
+
+        pools[1].last_updated = 17;
+        pools[1].block_hash = Some(H256::from_low_u64_be(0xb10d));
+        let quote = router
+            .find_top_routes(&pools, token_in, token_out, amount_in, &context, 1)
+            .unwrap();
+        assert_eq!(quote[0].block, None);
+    }
+
+    #[test]
+    fn test_stale_routes_are_annotated_and_penalized() {
+        let mut fresh = create_test_pools().remove(0);
+        fresh.last_updated = 100;
+        let mut stale = fresh.clone();
+        stale.address = Address::from_low_u64_be(200);
+        stale.last_updated = 10;
+
+        let router = Router::new(OptimizationStrategy::Price, 1);
+        let context = MarketContext { block_number: 100, ..MarketContext::default() };
+        let quotes = router
+            .find_top_routes(
+                &[stale, fresh],
+                Address::from_low_u64_be(1),
+                Address::from_low_u64_be(2),
+                U256::from(1_000_000_000_000_000_000u128),
+                &context,
+                2,
+            )
+            .unwrap();
+
+        assert_eq!(quotes[0].hops[0].pool, Address::from_low_u64_be(100));
+        assert_eq!((quotes[0].oldest_block, quotes[0].staleness_blocks), (Some(100), Some(0)));
+        assert_eq!((quotes[1].oldest_block, quotes[1].staleness_blocks), (Some(10), Some(90)));
+        assert_eq!(quotes[0].amount_out, quotes[1].amount_out);
+        assert!(quotes[0].score > quotes[1].score);
+    }
 }
@@ -0,0 +1,395 @@
+use crate::rpc::RpcProvider;
+use crate::swap::{ApproveCall, BalanceOfCall, SwapTxBuilder};
+use crate::types::{AggregatorError, Result, RouteHop, RouteQuote};
+use ethers::abi::{AbiDecode, AbiEncode};
+use ethers::prelude::*;
+use ethers::types::{Address, BlockId, U256};
+use revm::db::{CacheDB, EthersDB};
+use revm::primitives::{
+    AccountInfo, Address as RevmAddress, Bytecode, ExecutionResult, TransactTo, B256,
+    U256 as RevmU256,
+};
+use revm::primitives::db::DatabaseRef;
+use revm::{Database, EVM};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tracing::debug;
+
+/// Account used as sender and recipient of simulated swaps
+const SIMULATION_SENDER: Address = H160([
+    0x5e, 0x11, 0xa7, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x01,
+]);
+
+/// Storage slots probed when looking for an ERC20 balance mapping
+const MAX_BALANCE_SLOT: u64 = 50;
+
+/// Gas limit for each simulated call
+const SIMULATION_GAS_LIMIT: u64 = 10_000_000;
+
+/// Outcome of executing a route in the local EVM
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationResult {
+    /// Output tokens actually received by the recipient
+    pub amount_out: U256,
+
+    /// Gas used by the router calls (excludes approvals)
+    pub gas_used: u64,
+
+    /// Block whose state the simulation ran against
+    pub block_number: u64,
+}
+
+/// Executes routes in an in-process revm instance backed by a fork provider
+/// Contract code and storage are pulled lazily from the provider and cached for the run
+pub struct RouteSimulator {
+    provider: Arc<RpcProvider>,
+    routers: Vec<(String, Address)>,
+    weth: Address,
+    block: Option<u64>,
+}
+
+impl RouteSimulator {
+    /// Create a simulator from (dex_name, router) pairs
+    pub fn new(provider: Arc<RpcProvider>, routers: Vec<(String, Address)>, weth: Address) -> Self {
+        Self {
+            provider,
+            routers,
+            weth,
+            block: None,
+        }
+    }
+
+    /// Simulate against a specific block instead of the latest one
+    pub fn at_block(mut self, block: u64) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Execute the route's router calls and measure the received output
+    pub async fn simulate(&self, quote: &RouteQuote) -> Result<SimulationResult> {
+        let block_id = self
+            .block
+            .map(|n| BlockNumber::Number(n.into()))
+            .unwrap_or(BlockNumber::Latest);
+        let block = self
+            .provider
+            .get_block(block_id)
+            .await
+            .map_err(|e| AggregatorError::RpcError(format!("Failed to get block: {}", e)))?
+            .ok_or_else(|| AggregatorError::RpcError("Block not available".to_string()))?;
+        let chain_id = self
+            .provider
+            .get_chainid()
+            .await
+            .map_err(|e| AggregatorError::RpcError(format!("Failed to get chain id: {}", e)))?;
+
+        let block_number = block.number.map(|n| n.as_u64()).unwrap_or_default();
+        let context = SimulationContext {
+            provider: self.provider.clone(),
+            builder: SwapTxBuilder::new(self.routers.clone(), self.weth, SIMULATION_SENDER)
+                // Zero amountOutMin so the run reports the real output instead of reverting on slippage
+                .slippage_bps(10000),
+            block_number,
+            timestamp: block.timestamp,
+            chain_id: chain_id.as_u64(),
+        };
+        let quote = quote.clone();
+
+        // EthersDB blocks on provider calls, so keep it off the async workers
+        tokio::task::spawn_blocking(move || context.run(&quote))
+            .await
+            .map_err(|e| AggregatorError::Other(anyhow::anyhow!("Simulation task failed: {}", e)))?
+    }
+}
+
+/// Everything the blocking simulation needs
+struct SimulationContext {
+    provider: Arc<RpcProvider>,
+    builder: SwapTxBuilder,
+    block_number: u64,
+    timestamp: U256,
+    chain_id: u64,
+}
+
+impl SimulationContext {
+    fn run(&self, quote: &RouteQuote) -> Result<SimulationResult> {
+        let ethers_db = EthersDB::new(self.provider.clone(), Some(BlockId::from(self.block_number)))
+            .ok_or_else(|| AggregatorError::RpcError("Failed to create fork database".to_string()))?;
+
+        let mut evm: EVM<CacheDB<ForkDB>> = EVM::new();
+        evm.database(CacheDB::new(ForkDB(Mutex::new(ethers_db))));
+        evm.env.cfg.chain_id = self.chain_id;
+        evm.env.block.number = RevmU256::from(self.block_number);
+        evm.env.block.timestamp = to_revm_u256(self.timestamp);
+
+        let db = evm.db().unwrap();
+        db.insert_account_info(
+            to_revm_address(SIMULATION_SENDER),
+            AccountInfo {
+                balance: RevmU256::from(10u128.pow(24)),
+                ..Default::default()
+            },
+        );
+
+        deal(&mut evm, quote.token_in, SIMULATION_SENDER, quote.amount_in)?;
+
+        let mut amount = quote.amount_in;
+        let mut gas_used = 0;
+
+        for hops in quote.dex_segments() {
+            let segment = segment_quote(hops, amount);
+            let tx = self.builder.build(&segment)?;
+
+            let approve = ApproveCall {
+                spender: tx.to,
+                amount,
+            };
+            transact(&mut evm, segment.token_in, approve.encode(), true)?;
+
+            let balance_before = balance_of(&mut evm, segment.token_out, SIMULATION_SENDER)?;
+            let (_, gas) = transact(&mut evm, tx.to, tx.data.to_vec(), true)?;
+            let balance_after = balance_of(&mut evm, segment.token_out, SIMULATION_SENDER)?;
+
+            amount = balance_after.saturating_sub(balance_before);
+            gas_used += gas;
+
+            debug!("Simulated {} segment: {} out, {} gas", tx.dex_name, amount, gas);
+        }
+
+        Ok(SimulationResult {
+            amount_out: amount,
+            gas_used,
+            block_number: self.block_number,
+        })
+    }
+}
+
+/// Same-DEX slice of a route, re-based on the amount actually received so far
+fn segment_quote(hops: &[RouteHop], amount_in: U256) -> RouteQuote {
+    let last = hops.last().unwrap();
+    RouteQuote {
+        token_in: hops[0].token_in,
+        token_out: last.token_out,
+        amount_in,
+        amount_out: last.amount_out,
+        hops: hops.to_vec(),
+        total_fee: U256::zero(),
+        gas_estimate: U256::zero(),
+        price_impact_bps: 0,
+        score: 0.0,
+        description: String::new(),
+        simulated_amount_out: None,
+        simulated_gas_used: None,
+        block: None,
+        oldest_block: None,
+        staleness_blocks: None,
+    }
+}
+
+/// Give `owner` a token balance by locating the balance mapping slot
+/// Probes Solidity (keccak(owner . slot)) and Vyper (keccak(slot . owner)) layouts
+fn deal(evm: &mut EVM<CacheDB<ForkDB>>, token: Address, owner: Address, amount: U256) -> Result<()> {
+    let token_address = to_revm_address(token);
+
+    for slot in 0..=MAX_BALANCE_SLOT {
+        let owner_word = H256::from(owner);
+        let slot_word = H256::from_low_u64_be(slot);
+
+        for key in [
+            ethers::utils::keccak256([owner_word.as_bytes(), slot_word.as_bytes()].concat()),
+            ethers::utils::keccak256([slot_word.as_bytes(), owner_word.as_bytes()].concat()),
+        ] {
+            let index = RevmU256::from_be_bytes(key);
+            let db = evm.db().unwrap();
+            let original = Database::storage(db, token_address, index).map_err(fork_error)?;
+            db.insert_account_storage(token_address, index, to_revm_u256(amount))
+                .map_err(fork_error)?;
+
+            if balance_of(evm, token, owner)? == amount {
+                debug!("Found balance slot {} for {:?}", slot, token);
+                return Ok(());
+            }
+
+            evm.db()
+                .unwrap()
+                .insert_account_storage(token_address, index, original)
+                .map_err(fork_error)?;
+        }
+    }
+
+    Err(AggregatorError::ContractError(format!(
+        "Could not locate the balance slot of token {:?}",
+        token
+    )))
+}
+
+fn balance_of(evm: &mut EVM<CacheDB<ForkDB>>, token: Address, owner: Address) -> Result<U256> {
+    let call = BalanceOfCall { account: owner };
+    let (output, _) = transact(evm, token, call.encode(), false)?;
+    Ok(U256::decode(output).unwrap_or_default())
+}
+
+/// Run a call from the simulation sender, returning output and gas used
+fn transact(
+    evm: &mut EVM<CacheDB<ForkDB>>,
+    to: Address,
+    data: Vec<u8>,
+    commit: bool,
+) -> Result<(Vec<u8>, u64)> {
+    evm.env.tx.caller = to_revm_address(SIMULATION_SENDER);
+    evm.env.tx.transact_to = TransactTo::Call(to_revm_address(to));
+    evm.env.tx.data = data.into();
+    evm.env.tx.value = RevmU256::ZERO;
+    evm.env.tx.gas_limit = SIMULATION_GAS_LIMIT;
+
+    let result = if commit {
+        evm.transact_commit()
+    } else {
+        evm.transact().map(|r| r.result)
+    }
+    .map_err(|e| AggregatorError::ContractError(format!("Simulation failed: {:?}", e)))?;
+
+    match result {
+        ExecutionResult::Success { output, gas_used, .. } => Ok((output.into_data().to_vec(), gas_used)),
+        ExecutionResult::Revert { output, .. } => Err(AggregatorError::ContractError(format!(
+            "Simulated call to {:?} reverted: {}",
+            to,
+            decode_revert(&output)
+        ))),
+        ExecutionResult::Halt { reason, .. } => Err(AggregatorError::ContractError(format!(
+            "Simulated call to {:?} halted: {:?}",
+            to, reason
+        ))),
+    }
+}
+
+/// Decode an Error(string) revert payload, falling back to hex
+fn decode_revert(output: &[u8]) -> String {
+    if output.len() > 4 && output[..4] == [0x08, 0xc3, 0x79, 0xa0] {
+        if let Ok(reason) = String::decode(&output[4..]) {
+            return reason;
+        }
+    }
+    format!("0x{}", hex::encode(output))
+}
+
+fn fork_error<E: std::fmt::Debug>(e: E) -> AggregatorError {
+    AggregatorError::RpcError(format!("Fork database error: {:?}", e))
+}
+
+fn to_revm_address(address: Address) -> RevmAddress {
+    RevmAddress::from(address.0)
+}
+
+fn to_revm_u256(value: U256) -> RevmU256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    RevmU256::from_be_bytes(bytes)
+}
+
+/// Shared-reference adapter so the fork provider can back a CacheDB
+struct ForkDB(Mutex<EthersDB<RpcProvider>>);
+
+impl DatabaseRef for ForkDB {
+    type Error = ();
+
+    fn basic(&self, address: RevmAddress) -> std::result::Result<Option<AccountInfo>, Self::Error> {
+        Database::basic(&mut *self.0.lock().unwrap(), address)
+    }
+
+    fn code_by_hash(&self, code_hash: B256) -> std::result::Result<Bytecode, Self::Error> {
+        Database::code_by_hash(&mut *self.0.lock().unwrap(), code_hash)
+    }
+
+    fn storage(&self, address: RevmAddress, index: RevmU256) -> std::result::Result<RevmU256, Self::Error> {
+        Database::storage(&mut *self.0.lock().unwrap(), address, index)
+    }
+
+    fn block_hash(&self, number: RevmU256) -> std::result::Result<B256, Self::Error> {
+        Database::block_hash(&mut *self.0.lock().unwrap(), number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_revert() {
+        let mut payload = vec![0x08, 0xc3, 0x79, 0xa0];
+        payload.extend("UniswapV2: K".to_string().encode());
+        assert_eq!(decode_revert(&payload), "UniswapV2: K");
+        assert_eq!(decode_revert(&[0xde, 0xad]), "0xdead");
+    }
+
+    #[test]
+    fn test_u256_conversion() {
+        let value = U256::from_dec_str("123456789012345678901234567890").unwrap();
+        assert_eq!(to_revm_u256(value).to_string(), "123456789012345678901234567890");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "requires anvil on PATH and FORK_URL pointing at a mainnet RPC"]
+    async fn test_simulate_against_forked_node() {
+        use crate::{Aggregator, Config, OptimizationStrategy};
+        use ethers::utils::Anvil;
+
+        let fork_url = std::env::var("FORK_URL").expect("FORK_URL not set");
+        let anvil = Anvil::new().fork(fork_url).spawn();
+        let config = Config {
+            rpc_url: anvil.endpoint(),
+            cache_path: "./does-not-exist.json".to_string(),
+            ..Config::default()
+        };
+        let aggregator = Aggregator::new(config).await.unwrap();
+
+        let weth = aggregator.tokens().resolve("WETH").unwrap();
+        let usdc = aggregator.tokens().resolve("USDC").unwrap();
+        let pair: Address = "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc".parse().unwrap();
+        aggregator.fetch_pool(pair, "Uniswap".to_string()).await.unwrap();
+
+        let mut quote = aggregator
+            .get_best_quote(weth, usdc, U256::exp10(18), OptimizationStrategy::Price)
+            .unwrap();
+        let result = aggregator.simulate_quote(&mut quote).await.unwrap();
+
+        assert_eq!(quote.simulated_amount_out, Some(result.amount_out));
+        assert!(result.gas_used > 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "requires anvil on PATH and FORK_URL pointing at a mainnet RPC"]
+    async fn test_simulate_at_quote_block() {
+        use crate::{Aggregator, Config, OptimizationStrategy};
+        use ethers::providers::{Http, Provider};
+        use ethers::utils::Anvil;
+
+        let fork_url = std::env::var("FORK_URL").expect("FORK_URL not set");
+        let anvil = Anvil::new().fork(fork_url).spawn();
+        let node = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        let config = Config {
+            rpc_url: anvil.endpoint(),
+            cache_path: "./does-not-exist.json".to_string(),
+            ..Config::default()
+        };
+        let aggregator = Aggregator::new(config).await.unwrap();
+
+        let weth = aggregator.tokens().resolve("WETH").unwrap();
+        let usdc = aggregator.tokens().resolve("USDC").unwrap();
+        let pair: Address = "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc".parse().unwrap();
+        aggregator.fetch_pool(pair, "Uniswap".to_string()).await.unwrap();
+
+        // The head moves past the block the quote was made at
+        let _: String = node.request("evm_mine", ()).await.unwrap();
+        let _: String = node.request("evm_mine", ()).await.unwrap();
+
+        let mut quote = aggregator
+            .get_best_quote(weth, usdc, U256::exp10(18), OptimizationStrategy::Price)
+            .unwrap();
+        let block = quote.block.unwrap();
+        let result = aggregator.simulate_quote(&mut quote).await.unwrap();
+
+        assert_eq!(result.block_number, block.number);
+    }
+}
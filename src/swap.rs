@@ -0,0 +1,379 @@
+use crate::execute::eip1559_fees;
+use crate::types::{AggregatorError, MarketContext, Result, RouteQuote};
+use ethers::abi::AbiEncode;
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// UniswapV2Router02 ABI (simplified)
+abigen!(
+    UniswapV2Router02,
+    r#"[
+        function swapExactTokensForTokens(uint256 amountIn, uint256 amountOutMin, address[] path, address to, uint256 deadline) external returns (uint256[] amounts)
+        function swapExactETHForTokens(uint256 amountOutMin, address[] path, address to, uint256 deadline) external payable returns (uint256[] amounts)
+        function swapExactTokensForETH(uint256 amountIn, uint256 amountOutMin, address[] path, address to, uint256 deadline) external returns (uint256[] amounts)
+        function getAmountsOut(uint256 amountIn, address[] path) external view returns (uint256[] amounts)
+    ]"#,
+);
+
+// ERC20 ABI (simplified)
+abigen!(
+    ERC20,
+    r#"[
+        function balanceOf(address account) external view returns (uint256)
+        function approve(address spender, uint256 amount) external returns (bool)
+        function allowance(address owner, address spender) external view returns (uint256)
+        event Transfer(address indexed from, address indexed to, uint256 value)
+    ]"#,
+);
+
+// WETH9 ABI (simplified)
+abigen!(
+    WETH9,
+    r#"[
+        event Withdrawal(address indexed src, uint256 wad)
+    ]"#,
+);
+
+/// A ready-to-sign swap transaction for a UniswapV2Router02-compatible router
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapTransaction {
+    /// Router contract
+    pub to: Address,
+
+    /// ETH sent with the call (non-zero only for swapExactETHForTokens)
+    pub value: U256,
+
+    /// Encoded calldata
+    pub data: Bytes,
+
+    /// Router method name
+    pub method: String,
+
+    /// DEX whose router executes the swap
+    pub dex_name: String,
+
+    /// Token path passed to the router
+    pub path: Vec<Address>,
+
+    /// Exact input amount
+    pub amount_in: U256,
+
+    /// Minimum accepted output
+    pub amount_out_min: U256,
+
+    /// Recipient of the output tokens
+    pub recipient: Address,
+
+    /// Unix timestamp after which the router reverts
+    pub deadline: U256,
+
+    /// Gas estimate carried over from the quote
+    pub gas_estimate: U256,
+}
+
+impl SwapTransaction {
+    /// Convert into a ready-to-sign EIP-1559 transaction request
+    /// The gas limit is the quote's estimate plus 20% headroom and fees come from the market context
+    pub fn to_typed_transaction(&self, from: Option<Address>, chain_id: u64, context: &MarketContext) -> TypedTransaction {
+        let (max_fee, priority_fee) = eip1559_fees(context);
+        let mut tx = Eip1559TransactionRequest::new()
+            .to(self.to)
+            .value(self.value)
+            .data(self.data.clone())
+            .chain_id(chain_id)
+            .gas(self.gas_estimate.saturating_mul(U256::from(120)) / 100)
+            .max_fee_per_gas(max_fee)
+            .max_priority_fee_per_gas(priority_fee);
+        if let Some(from) = from {
+            tx = tx.from(from);
+        }
+        tx.into()
+    }
+}
+
+/// Builds router calls from quotes
+/// Every hop of the quote must go through the same DEX so one router can execute it
+#[derive(Debug, Clone)]
+pub struct SwapTxBuilder {
+    routers: HashMap<String, Address>,
+    weth: Address,
+    recipient: Address,
+    slippage_bps: u32,
+    deadline_secs: u64,
+    eth_in: bool,
+    eth_out: bool,
+}
+
+impl SwapTxBuilder {
+    /// Create a builder from (dex_name, router) pairs
+    pub fn new(routers: Vec<(String, Address)>, weth: Address, recipient: Address) -> Self {
+        Self {
+            routers: routers
+                .into_iter()
+                .map(|(name, router)| (name.to_lowercase(), router))
+                .collect(),
+            weth,
+            recipient,
+            slippage_bps: 50,
+            deadline_secs: 1200,
+            eth_in: false,
+            eth_out: false,
+        }
+    }
+
+    /// Slippage tolerance used to derive amountOutMin
+    pub fn slippage_bps(mut self, slippage_bps: u32) -> Self {
+        self.slippage_bps = slippage_bps;
+        self
+    }
+
+    /// Seconds from now until the transaction expires
+    pub fn deadline_secs(mut self, deadline_secs: u64) -> Self {
+        self.deadline_secs = deadline_secs;
+        self
+    }
+
+    /// Pay with native ETH instead of WETH (swapExactETHForTokens)
+    pub fn eth_in(mut self, eth_in: bool) -> Self {
+        self.eth_in = eth_in;
+        self
+    }
+
+    /// Receive native ETH instead of WETH (swapExactTokensForETH)
+    pub fn eth_out(mut self, eth_out: bool) -> Self {
+        self.eth_out = eth_out;
+        self
+    }
+
+    /// Minimum output after applying the slippage tolerance
+    pub fn amount_out_min(&self, amount_out: U256) -> U256 {
+        let tolerance = 10000u32.saturating_sub(self.slippage_bps);
+        amount_out
+            .checked_mul(U256::from(tolerance))
+            .map(|v| v / U256::from(10000))
+            .unwrap_or_else(|| amount_out / U256::from(10000) * U256::from(tolerance))
+    }
+
+    /// Build the router transaction for a quote
+    pub fn build(&self, quote: &RouteQuote) -> Result<SwapTransaction> {
+        if self.slippage_bps > 10000 {
+            return Err(AggregatorError::InvalidAmount(format!(
+                "Slippage of {} bps exceeds 10000 (100%)",
+                self.slippage_bps
+            )));
+        }
+
+        let dex_name = Self::single_dex(quote)?;
+        let router = self
+            .routers
+            .get(&dex_name.to_lowercase())
+            .copied()
+            .ok_or_else(|| AggregatorError::ConfigError(format!("No router configured for {}", dex_name)))?;
+
+        let mut path = vec![quote.token_in];
+        path.extend(quote.hops.iter().map(|hop| hop.token_out));
+
+        let amount_out_min = self.amount_out_min(quote.amount_out);
+        let deadline = U256::from(chrono::Utc::now().timestamp() as u64 + self.deadline_secs);
+
+        let (method, data, value) = match (self.eth_in, self.eth_out) {
+            (true, true) => {
+                return Err(AggregatorError::InvalidRoute(
+                    "Cannot swap native ETH for native ETH".to_string(),
+                ))
+            }
+            (true, false) => {
+                if quote.token_in != self.weth {
+                    return Err(AggregatorError::InvalidRoute(
+                        "Native ETH input requires a route starting at WETH".to_string(),
+                    ));
+                }
+                let call = SwapExactETHForTokensCall {
+                    amount_out_min,
+                    path: path.clone(),
+                    to: self.recipient,
+                    deadline,
+                };
+                ("swapExactETHForTokens", call.encode(), quote.amount_in)
+            }
+            (false, true) => {
+                if quote.token_out != self.weth {
+                    return Err(AggregatorError::InvalidRoute(
+                        "Native ETH output requires a route ending at WETH".to_string(),
+                    ));
+                }
+                let call = SwapExactTokensForETHCall {
+                    amount_in: quote.amount_in,
+                    amount_out_min,
+                    path: path.clone(),
+                    to: self.recipient,
+                    deadline,
+                };
+                ("swapExactTokensForETH", call.encode(), U256::zero())
+            }
+            (false, false) => {
+                let call = SwapExactTokensForTokensCall {
+                    amount_in: quote.amount_in,
+                    amount_out_min,
+                    path: path.clone(),
+                    to: self.recipient,
+                    deadline,
+                };
+                ("swapExactTokensForTokens", call.encode(), U256::zero())
+            }
+        };
+
+        Ok(SwapTransaction {
+            to: router,
+            value,
+            data: data.into(),
+            method: method.to_string(),
+            dex_name,
+            path,
+            amount_in: quote.amount_in,
+            amount_out_min,
+            recipient: self.recipient,
+            deadline,
+            gas_estimate: quote.gas_estimate,
+        })
+    }
+
+    /// The DEX shared by every hop, or an error naming the DEXes that are mixed
+    fn single_dex(quote: &RouteQuote) -> Result<String> {
+        let first = quote
+            .hops
+            .first()
+            .ok_or_else(|| AggregatorError::InvalidRoute("Route has no hops".to_string()))?;
+
+        if quote
+            .hops
+            .iter()
+            .any(|hop| !hop.dex_name.eq_ignore_ascii_case(&first.dex_name))
+        {
+            let dexes: Vec<&str> = quote.hops.iter().map(|hop| hop.dex_name.as_str()).collect();
+            return Err(AggregatorError::InvalidRoute(format!(
+                "Route mixes DEXes ({}); a single router cannot execute it",
+                dexes.join(" → ")
+            )));
+        }
+
+        Ok(first.dex_name.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::RouteHop;
+    use ethers::abi::AbiDecode;
+
+    fn hop(token_in: u64, token_out: u64, dex: &str) -> RouteHop {
+        RouteHop {
+            pool: Address::from_low_u64_be(100 + token_in),
+            token_in: Address::from_low_u64_be(token_in),
+            token_out: Address::from_low_u64_be(token_out),
+            dex_name: dex.to_string(),
+            amount_in: U256::from(1000),
+            amount_out: U256::from(1000),
+            fee: U256::zero(),
+            gas_estimate: U256::from(100_000),
+        }
+    }
+
+    fn quote(hops: Vec<RouteHop>) -> RouteQuote {
+        RouteQuote {
+            token_in: hops[0].token_in,
+            token_out: hops.last().unwrap().token_out,
+            amount_in: U256::from(1000),
+            amount_out: U256::from(10000),
+            hops,
+            total_fee: U256::zero(),
+            gas_estimate: U256::from(200_000),
+            price_impact_bps: 0,
+            score: 0.0,
+            description: String::new(),
+            simulated_amount_out: None,
+            simulated_gas_used: None,
+            block: None,
+            oldest_block: None,
+            staleness_blocks: None,
+        }
+    }
+
+    fn builder() -> SwapTxBuilder {
+        SwapTxBuilder::new(
+            vec![
+                ("Uniswap".to_string(), Address::from_low_u64_be(0xaa)),
+                ("SushiSwap".to_string(), Address::from_low_u64_be(0xbb)),
+            ],
+            Address::from_low_u64_be(1),
+            Address::from_low_u64_be(0xcc),
+        )
+    }
+
+    #[test]
+    fn test_build_tokens_for_tokens() {
+        let quote = quote(vec![hop(1, 2, "SushiSwap"), hop(2, 3, "SushiSwap")]);
+        let tx = builder().slippage_bps(100).build(&quote).unwrap();
+
+        assert_eq!(tx.to, Address::from_low_u64_be(0xbb));
+        assert_eq!(tx.value, U256::zero());
+        assert_eq!(tx.amount_out_min, U256::from(9900));
+
+        let call = SwapExactTokensForTokensCall::decode(&tx.data).unwrap();
+        assert_eq!(call.path.len(), 3);
+        assert_eq!(call.to, Address::from_low_u64_be(0xcc));
+        assert_eq!(call.amount_out_min, U256::from(9900));
+    }
+
+    #[test]
+    fn test_build_eth_in_sets_value() {
+        let quote = quote(vec![hop(1, 2, "Uniswap")]);
+        let tx = builder().eth_in(true).build(&quote).unwrap();
+
+        assert_eq!(tx.method, "swapExactETHForTokens");
+        assert_eq!(tx.value, U256::from(1000));
+        assert!(SwapExactETHForTokensCall::decode(&tx.data).is_ok());
+    }
+
+    #[test]
+    fn test_mixed_dex_route_is_rejected() {
+        let quote = quote(vec![hop(1, 2, "Uniswap"), hop(2, 3, "SushiSwap")]);
+        let err = builder().build(&quote).unwrap_err();
+
+        assert!(matches!(err, AggregatorError::InvalidRoute(_)));
+        assert!(err.to_string().contains("mixes DEXes"));
+    }
+
+    #[test]
+    fn test_slippage_above_100_percent_is_rejected() {
+        let quote = quote(vec![hop(1, 2, "Uniswap")]);
+
+        assert!(builder().slippage_bps(10000).build(&quote).is_ok());
+        assert!(matches!(
+            builder().slippage_bps(10001).build(&quote),
+            Err(AggregatorError::InvalidAmount(_))
+        ));
+    }
+
+    #[test]
+    fn test_typed_transaction_is_ready_to_sign() {
+        let quote = quote(vec![hop(1, 2, "Uniswap")]);
+        let context = MarketContext {
+            base_fee_per_gas: Some(U256::from(10)),
+            priority_fee_per_gas: Some(U256::from(2)),
+            ..MarketContext::default()
+        };
+        let tx = builder().build(&quote).unwrap().to_typed_transaction(None, 1, &context);
+
+        assert_eq!(tx.gas(), Some(&U256::from(240_000)));
+        let TypedTransaction::Eip1559(inner) = tx else {
+            panic!("expected an EIP-1559 request");
+        };
+        assert_eq!(inner.max_fee_per_gas, Some(U256::from(22)));
+        assert_eq!(inner.max_priority_fee_per_gas, Some(U256::from(2)));
+    }
+}
@@ -0,0 +1,336 @@
+use crate::chains;
+use crate::types::{AggregatorError, Result, TokenInfo};
+use crate::utils;
+use dashmap::DashMap;
+use ethers::types::{Address, U256};
+use serde::Deserialize;
+
+/// Symbols accepted in place of the wrapped token they stand for, besides the native token
+const SYMBOL_ALIASES: &[(&str, &str)] = &[("BTC", "WBTC")];
+
+/// Stablecoins used as USD price anchors, when listed for the chain
+const STABLECOIN_SYMBOLS: &[&str] = &["USDC", "USDT", "DAI"];
+
+/// Uniswap token-list document (https://tokenlists.org)
+#[derive(Debug, Deserialize)]
+struct TokenList {
+    tokens: Vec<TokenListEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenListEntry {
+    chain_id: u64,
+    address: String,
+    symbol: String,
+    name: String,
+    decimals: u8,
+}
+
+/// Token metadata for one chain, loaded from token lists
+/// Later lists override earlier ones, so user lists take precedence over the bundled defaults
+/// Metadata read from token contracts fills in unlisted tokens; since anyone can deploy a
+/// token named "USDC", discovered symbols are shown but never resolved
+pub struct TokenRegistry {
+    chain_id: u64,
+    chain: Option<&'static chains::Chain>,
+    tokens: DashMap<Address, TokenInfo>,
+    symbols: DashMap<String, Address>,
+    discovered: DashMap<Address, TokenInfo>,
+}
+
+impl TokenRegistry {
+    /// Create an empty registry for a chain
+    pub fn new(chain_id: u64) -> Self {
+        Self {
+            chain_id,
+            chain: chains::by_id(chain_id),
+            tokens: DashMap::new(),
+            symbols: DashMap::new(),
+            discovered: DashMap::new(),
+        }
+    }
+
+    /// Create a registry pre-loaded with the chain's bundled token list (empty for unknown chains)
+    pub fn with_defaults(chain_id: u64) -> Self {
+        let registry = Self::new(chain_id);
+        if let Some(chain) = registry.chain {
+            registry
+                .load_list_str(chain.token_list)
+                .expect("bundled token list is valid");
+        }
+        registry
+    }
+
+    /// Chain the registry holds tokens for
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// Load a token-list JSON document, keeping entries for this chain
+    /// Returns the number of tokens added or replaced
+    pub fn load_list_str(&self, json: &str) -> Result<usize> {
+        let list: TokenList = serde_json::from_str(json)
+            .map_err(|e| AggregatorError::ParseError(format!("Invalid token list: {}", e)))?;
+
+        let mut loaded = 0;
+        for entry in list.tokens.into_iter().filter(|t| t.chain_id == self.chain_id) {
+            let address = utils::parse_address(&entry.address)?;
+            self.insert(TokenInfo {
+                address,
+                symbol: entry.symbol,
+                name: entry.name,
+                decimals: entry.decimals,
+            });
+            loaded += 1;
+        }
+
+        Ok(loaded)
+    }
+
+    /// Load a token-list JSON file
+    pub fn load_list_file(&self, path: &str) -> Result<usize> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| AggregatorError::ConfigError(format!("Failed to read token list {}: {}", path, e)))?;
+        self.load_list_str(&json)
+    }
+
+    /// Add or replace a token
+    pub fn insert(&self, token: TokenInfo) {
+        self.symbols.insert(token.symbol.to_uppercase(), token.address);
+        self.tokens.insert(token.address, token);
+    }
+
+    /// Add metadata read from a token contract; listed tokens keep their list entry
+    pub fn insert_discovered(&self, token: TokenInfo) {
+        self.discovered.insert(token.address, token);
+    }
+
+    /// Tokens discovered on-chain, for persisting in the cache
+    pub fn discovered_tokens(&self) -> Vec<TokenInfo> {
+        self.discovered.iter().map(|entry| entry.value().clone()).collect()
+    }
+
+    /// Whether metadata is available for a token, listed or discovered
+    pub fn contains(&self, address: Address) -> bool {
+        self.tokens.contains_key(&address) || self.discovered.contains_key(&address)
+    }
+
+    /// Number of listed tokens
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Whether no tokens are listed
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Metadata for a token, preferring the token lists over discovered metadata
+    pub fn get(&self, address: Address) -> Option<TokenInfo> {
+        self.tokens
+            .get(&address)
+            .or_else(|| self.discovered.get(&address))
+            .map(|entry| entry.value().clone())
+    }
+
+    /// Decimals of a token, if known
+    pub fn decimals(&self, address: Address) -> Option<u8> {
+        self.get(address).map(|token| token.decimals)
+    }
+
+    /// Decimals of a token, or an error naming it if unknown
+    pub fn require_decimals(&self, address: Address) -> Result<u8> {
+        self.decimals(address).ok_or_else(|| {
+            AggregatorError::InvalidTokenAddress(format!(
+                "Unknown decimals for {:?}; fetch its pools or add it to a token list (--token-list)",
+                address
+            ))
+        })
+    }
+
+    /// Symbol of a token, or a shortened address if unknown
+    pub fn symbol(&self, address: Address) -> String {
+        match self.get(address) {
+            Some(token) => token.symbol,
+            None => {
+                let addr = format!("{:?}", address);
+                format!("{}...{}", &addr[0..6], &addr[addr.len() - 4..])
+            }
+        }
+    }
+
+    /// Resolve a symbol (case-insensitive) or address
+    pub fn resolve(&self, input: &str) -> Result<Address> {
+        if input.starts_with("0x") || input.starts_with("0X") {
+            return utils::parse_address(input);
+        }
+
+        // Listed symbols win, so BSC's bridged "ETH" isn't mistaken for the native token
+        let symbol = input.to_uppercase();
+        let symbol = if self.symbols.contains_key(&symbol) {
+            symbol
+        } else if self.native_symbols().iter().any(|native| *native == symbol) {
+            self.wrapped_native_symbol().to_uppercase()
+        } else {
+            SYMBOL_ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == symbol)
+                .map(|(_, target)| target.to_string())
+                .unwrap_or(symbol)
+        };
+
+        self.symbols.get(&symbol).map(|entry| *entry.value()).ok_or_else(|| {
+            AggregatorError::ParseError(format!(
+                "Unknown token symbol: {}. Use full address (0x...) or a symbol from the token lists",
+                input
+            ))
+        })
+    }
+
+    /// Wrapped native token of the chain (WETH, WMATIC, WBNB, ...)
+    pub fn wrapped_native(&self) -> Result<Address> {
+        self.resolve(self.wrapped_native_symbol())
+    }
+
+    /// Symbol of the wrapped native token; WETH on chains without a preset
+    pub fn wrapped_native_symbol(&self) -> &'static str {
+        self.chain.map(|chain| chain.wrapped_native).unwrap_or("WETH")
+    }
+
+    /// Symbol of the native gas token (ETH, POL, BNB, ...)
+    pub fn native_symbol(&self) -> &'static str {
+        self.native_symbols()[0]
+    }
+
+    /// Symbols of the native gas token; ETH on chains without a preset
+    fn native_symbols(&self) -> &'static [&'static str] {
+        self.chain.map(|chain| chain.native_symbols).unwrap_or(&["ETH"])
+    }
+
+    /// Parse a human-readable amount of a token into base units
+    /// Parse errors name the token, e.g. "USDC: Too many decimal places in 1.1234567 (max 6)"
+    pub fn parse_amount(&self, address: Address, amount: &str) -> Result<U256> {
+        utils::parse_token_amount(amount, self.require_decimals(address)?).map_err(|e| match e {
+            AggregatorError::ParseError(msg) => AggregatorError::ParseError(format!("{}: {}", self.symbol(address), msg)),
+            other => other,
+        })
+    }
+
+    /// Format base units of a token for display; unknown tokens are shown in base units
+    pub fn format_amount(&self, address: Address, amount: U256) -> String {
+        utils::format_token_amount(amount, self.display_decimals(address))
+    }
+
+    /// Decimals used for display: the token's decimals, or 0 (base units) if unknown
+    pub fn display_decimals(&self, address: Address) -> u8 {
+        self.decimals(address).unwrap_or(0)
+    }
+
+    /// Listed USD stablecoins used as price anchors
+    pub fn stablecoins(&self) -> Vec<TokenInfo> {
+        STABLECOIN_SYMBOLS
+            .iter()
+            .filter_map(|symbol| self.resolve(symbol).ok())
+            .filter_map(|address| self.get(address))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER_LIST: &str = r#"{
+        "name": "user",
+        "tokens": [
+            { "chainId": 1, "address": "0x4fabb145d64652a948d72533023f6e7a623c7c53", "symbol": "BUSD", "name": "Binance USD", "decimals": 18 },
+            { "chainId": 1, "address": "0x0000000000000000000000000000000000000abc", "symbol": "NEW", "name": "New Token", "decimals": 9 },
+            { "chainId": 137, "address": "0x0000000000000000000000000000000000000def", "symbol": "POLY", "name": "Polygon Only", "decimals": 6 }
+        ]
+    }"#;
+
+    #[test]
+    fn test_defaults_agree_on_symbols_and_decimals() {
+        let registry = TokenRegistry::with_defaults(1);
+
+        let busd = registry.resolve("busd").unwrap();
+        assert_eq!(registry.symbol(busd), "BUSD");
+        assert_eq!(registry.decimals(busd), Some(18));
+
+        let usdc = registry.resolve("USDC").unwrap();
+        assert_eq!(registry.decimals(usdc), Some(6));
+        assert_eq!(registry.resolve("ETH").unwrap(), registry.resolve("WETH").unwrap());
+    }
+
+    #[test]
+    fn test_user_list_is_chain_filtered_and_overrides() {
+        let registry = TokenRegistry::with_defaults(1);
+        assert_eq!(registry.load_list_str(USER_LIST).unwrap(), 2);
+
+        let new = registry.resolve("NEW").unwrap();
+        assert_eq!(registry.decimals(new), Some(9));
+        assert!(registry.resolve("POLY").is_err());
+
+        let other_chain = TokenRegistry::with_defaults(137);
+        assert!(other_chain.resolve("NEW").is_err());
+
+        let unknown_chain = TokenRegistry::with_defaults(31337);
+        assert!(unknown_chain.is_empty());
+    }
+
+    #[test]
+    fn test_discovered_tokens_fill_gaps_but_do_not_resolve() {
+        let registry = TokenRegistry::with_defaults(1);
+        let usdc = registry.resolve("USDC").unwrap();
+        let spoof = Address::from_low_u64_be(0x500f);
+
+        registry.insert_discovered(TokenInfo {
+            address: spoof,
+            symbol: "USDC".to_string(),
+            name: "Fake USD Coin".to_string(),
+            decimals: 9,
+        });
+        registry.insert_discovered(TokenInfo {
+            address: usdc,
+            symbol: "USDC.e".to_string(),
+            name: "Wrong".to_string(),
+            decimals: 18,
+        });
+
+        assert_eq!(registry.decimals(spoof), Some(9));
+        assert_eq!(registry.resolve("USDC").unwrap(), usdc);
+        assert_eq!(registry.decimals(usdc), Some(6));
+    }
+
+    #[test]
+    fn test_native_symbol_resolves_to_wrapped_native_per_chain() {
+        let bsc = TokenRegistry::with_defaults(56);
+        assert_eq!(bsc.resolve("BNB").unwrap(), bsc.wrapped_native().unwrap());
+        // BSC lists bridged ETH under its own symbol
+        assert_ne!(bsc.resolve("ETH").unwrap(), bsc.wrapped_native().unwrap());
+
+        let polygon = TokenRegistry::with_defaults(137);
+        assert_eq!(polygon.resolve("matic").unwrap(), polygon.resolve("WMATIC").unwrap());
+        assert_ne!(polygon.resolve("WETH").unwrap(), polygon.wrapped_native().unwrap());
+    }
+
+    #[test]
+    fn test_unknown_token_is_not_scaled() {
+        let registry = TokenRegistry::with_defaults(1);
+        let unknown = Address::from_low_u64_be(0x1234);
+
+        assert!(registry.parse_amount(unknown, "1.5").is_err());
+        assert_eq!(registry.format_amount(unknown, U256::from(1500)), "1500");
+        assert_eq!(registry.symbol(unknown), "0x0000...1234");
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_token() {
+        let registry = TokenRegistry::with_defaults(1);
+        let usdc = registry.resolve("USDC").unwrap();
+
+        let err = registry.parse_amount(usdc, "1.1234567").unwrap_err().to_string();
+        assert!(err.contains("USDC: Too many decimal places in 1.1234567 (max 6)"), "{}", err);
+    }
+}
@@ -1,4 +1,7 @@
-use ethers::types::{Address, U256};
+use crate::price;
+use crate::tokens::TokenRegistry;
+use ethers::types::{Address, BlockId, H256, U256};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use thiserror::Error;
@@ -21,6 +24,9 @@ pub enum AggregatorError {
     #[error("Invalid token address: {0}")]
     InvalidTokenAddress(String),
 
+    #[error("Invalid route: {0}")]
+    InvalidRoute(String),
+
     #[error("Invalid amount: {0}")]
     InvalidAmount(String),
 
@@ -39,6 +45,9 @@ pub enum AggregatorError {
     #[error("Math overflow or underflow")]
     MathError,
 
+    #[error("Quote uses reserves {age} blocks old, more than the {max} allowed")]
+    StaleState { age: u64, max: u64 },
+
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
@@ -46,6 +55,21 @@ pub enum AggregatorError {
 /// Result type alias for aggregator operations
 pub type Result<T> = std::result::Result<T, AggregatorError>;
 
+/// Block a set of chain reads was pinned to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotBlock {
+    pub number: u64,
+    pub hash: H256,
+}
+
+impl SnapshotBlock {
+    /// Block id for reads pinned by hash (EIP-1898), which fail rather than read another
+    /// block if this one is reorged out
+    pub fn id(&self) -> BlockId {
+        BlockId::Hash(self.hash)
+    }
+}
+
 /// Information about a liquidity pool
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PoolInfo {
@@ -72,9 +96,25 @@ pub struct PoolInfo {
     
     /// Block number when last updated
     pub last_updated: u64,
+
+    /// Hash of the block the reserves were read at; absent in caches from older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<H256>,
+
+    /// USD value of both reserves, if either token could be priced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liquidity_usd: Option<f64>,
 }
 
 impl PoolInfo {
+    /// Block the reserves were read at, if its hash was recorded
+    pub fn snapshot_block(&self) -> Option<SnapshotBlock> {
+        self.block_hash.map(|hash| SnapshotBlock {
+            number: self.last_updated,
+            hash,
+        })
+    }
+
     /// Get the other token in the pair
     pub fn get_other_token(&self, token: &Address) -> Option<Address> {
         if token == &self.token0 {
@@ -97,14 +137,19 @@ impl PoolInfo {
         }
     }
 
-    /// Calculate current price ratio (token1 per token0)
-    pub fn price_ratio(&self) -> f64 {
-        if self.reserve0.is_zero() {
-            return 0.0;
-        }
-        let r0 = self.reserve0.as_u128() as f64;
-        let r1 = self.reserve1.as_u128() as f64;
-        r1 / r0
+    /// Spot price at current reserves: units of the other token per unit of `token_in`
+    /// Fails if `token_in` isn't in the pool, either token's decimals are unknown or the pool is empty
+    pub fn spot_price(&self, token_in: &Address, tokens: &TokenRegistry) -> Result<Decimal> {
+        let (token_out, (reserve_in, reserve_out)) = self
+            .get_other_token(token_in)
+            .zip(self.get_reserves(token_in))
+            .ok_or_else(|| AggregatorError::InvalidTokenAddress(format!("{:?} is not in pool {:?}", token_in, self.address)))?;
+        price::price(
+            reserve_in,
+            tokens.require_decimals(*token_in)?,
+            reserve_out,
+            tokens.require_decimals(token_out)?,
+        )
     }
 }
 
@@ -136,6 +181,18 @@ pub struct RouteHop {
     pub gas_estimate: U256,
 }
 
+impl RouteHop {
+    /// Units of token_out received per unit of token_in on this hop
+    pub fn execution_price(&self, tokens: &TokenRegistry) -> Result<Decimal> {
+        price::price(
+            self.amount_in,
+            tokens.require_decimals(self.token_in)?,
+            self.amount_out,
+            tokens.require_decimals(self.token_out)?,
+        )
+    }
+}
+
 /// Complete route information with quote
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RouteQuote {
@@ -168,17 +225,43 @@ pub struct RouteQuote {
     
     /// Route description
     pub description: String,
+
+    /// Output measured by local EVM simulation, if run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simulated_amount_out: Option<U256>,
+
+    /// Gas used by the router calls in simulation, if run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simulated_gas_used: Option<u64>,
+
+    /// Block every pool on the route was read at; `None` if they come from different blocks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<SnapshotBlock>,
+
+    /// Oldest block a route pool's reserves were read at; `None` if any pool's block is unknown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oldest_block: Option<u64>,
+
+    /// Blocks `oldest_block` is behind the chain head, when both are known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staleness_blocks: Option<u64>,
 }
 
 impl RouteQuote {
-    /// Get the effective exchange rate
-    pub fn exchange_rate(&self) -> f64 {
-        if self.amount_in.is_zero() {
-            return 0.0;
-        }
-        let amount_in = self.amount_in.as_u128() as f64;
-        let amount_out = self.amount_out.as_u128() as f64;
-        amount_out / amount_in
+    /// Units of token_out received per unit of token_in
+    /// Fails if either token's decimals are unknown
+    pub fn execution_price(&self, tokens: &TokenRegistry) -> Result<Decimal> {
+        price::price(
+            self.amount_in,
+            tokens.require_decimals(self.token_in)?,
+            self.amount_out,
+            tokens.require_decimals(self.token_out)?,
+        )
+    }
+
+    /// Units of token_in paid per unit of token_out
+    pub fn inverse_price(&self, tokens: &TokenRegistry) -> Result<Decimal> {
+        price::inverse(self.execution_price(tokens)?)
     }
 
     /// Get number of hops
@@ -186,6 +269,13 @@ impl RouteQuote {
         self.hops.len()
     }
 
+    /// Split hops into runs of consecutive hops on the same DEX
+    pub fn dex_segments(&self) -> Vec<&[RouteHop]> {
+        self.hops
+            .chunk_by(|a, b| a.dex_name.eq_ignore_ascii_case(&b.dex_name))
+            .collect()
+    }
+
     /// Generate a human-readable route path
     pub fn route_path(&self) -> String {
         if self.hops.is_empty() {
@@ -200,6 +290,19 @@ impl RouteQuote {
     }
 }
 
+/// A single swap to quote as part of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteRequest {
+    /// Input token
+    pub token_in: Address,
+
+    /// Output token
+    pub token_out: Address,
+
+    /// Input amount
+    pub amount_in: U256,
+}
+
 /// Token metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenInfo {
@@ -258,22 +361,35 @@ impl fmt::Display for OptimizationStrategy {
 /// Market context for intelligent routing
 #[derive(Debug, Clone)]
 pub struct MarketContext {
-    /// Current gas price in gwei
-    pub gas_price_gwei: u64,
+    /// Current gas price in wei
+    pub gas_price_wei: U256,
     
     /// ETH price in USD (for gas cost calculation)
     pub eth_price_usd: f64,
     
-    /// Current block number
+    /// Current block number; offline, the newest cached pool's block, and 0 with an empty cache
     pub block_number: u64,
+
+    /// Whether `block_number` is the chain head, or the past block being quoted, rather than the
+    /// offline fallback
+    pub block_known: bool,
+
+    /// EIP-1559 base fee in wei, if known
+    pub base_fee_per_gas: Option<U256>,
+
+    /// EIP-1559 priority fee in wei, if known
+    pub priority_fee_per_gas: Option<U256>,
 }
 
 impl Default for MarketContext {
     fn default() -> Self {
         Self {
-            gas_price_gwei: 30,
+            gas_price_wei: U256::from(30_000_000_000u64),
             eth_price_usd: 1800.0,
             block_number: 0,
+            block_known: false,
+            base_fee_per_gas: None,
+            priority_fee_per_gas: None,
         }
     }
 }
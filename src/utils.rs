@@ -1,5 +1,7 @@
+use crate::math;
 use crate::types::{AggregatorError, Result};
-use ethers::types::{Address, U256};
+use ethers::types::{Address, H256, U256};
+use ethers::utils::{get_create2_address_from_hash, keccak256};
 use std::str::FromStr;
 
 /// Calculate UniswapV2 output amount using the constant product formula
@@ -95,7 +97,7 @@ pub fn calculate_price_impact(
         .and_then(|v| v.checked_div(denominator))
         .unwrap_or(U256::from(10000));
 
-    impact.as_u32().min(10000)
+    math::to_u32(impact.min(U256::from(10000))).unwrap_or(10000)
 }
 
 /// Calculate the fee amount from an input amount
@@ -106,158 +108,74 @@ pub fn calculate_fee(amount: U256, fee_bps: u32) -> U256 {
         .unwrap_or(U256::zero())
 }
 
-/// Get token decimals for known tokens
-/// Returns 18 (default) if token is unknown
-pub fn get_token_decimals(token_address: Address) -> u8 {
-    let addr_str = format!("{:?}", token_address).to_lowercase();
-    
-    match addr_str.as_str() {
-        // Stablecoins (6 decimals)
-        "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48" => 6, // USDC
-        "0xdac17f958d2ee523a2206206994597c13d831ec7" => 6, // USDT
-        
-        // Stablecoins (18 decimals)
-        "0x6b175474e89094c44da98b954eedeac495271d0f" => 18, // DAI
-        "0x0000000000085d4780b73119b644ae5ecd22b376" => 18, // TUSD
-        "0x57ab1ec28d129707052df4df418d58a2d46d5f51" => 18, // sUSD
-        
-        // Major tokens (18 decimals)
-        "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2" => 18, // WETH
-        "0x2260fac5e5542a773aa44fbcfedf7c193bc2c599" => 8,  // WBTC
-        "0x9f8f72aa9304c8b593d555f12ef6589cc3a579a2" => 18, // MKR
-        "0x1f9840a85d5af5bf1d1762f925bdaddc4201f984" => 18, // UNI
-        "0x514910771af9ca656af840dff83e8264ecf986ca" => 18, // LINK
-        "0x7d1afa7b718fb893db30a3abc0cfc608aacfebb0" => 18, // MATIC
-        "0x0d8775f648430679a709e98d2b0cb6250d2887ef" => 18, // BAT
-        "0xdd974d5c2e2928dea5f71b9825b8b646686bd200" => 18, // KNC
-        
-        // Default: 18 decimals (most ERC20 tokens use 18)
-        _ => 18,
+/// Parse a token amount string into base units
+/// Examples: "1.0", "0.5", "1000", "1_000", "1e6", "2.5E-3"
+pub fn parse_token_amount(amount_str: &str, decimals: u8) -> Result<U256> {
+    let cleaned = amount_str.trim().replace('_', "");
+    if cleaned.is_empty() {
+        return Err(AggregatorError::ParseError("Amount is empty".to_string()));
     }
-}
-
-/// Get token symbol for known tokens
-/// Returns shortened address if token is unknown
-pub fn get_token_symbol(token_address: Address) -> String {
-    let addr_str = format!("{:?}", token_address).to_lowercase();
-    
-    match addr_str.as_str() {
-        // Stablecoins
-        "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48" => "USDC".to_string(),
-        "0xdac17f958d2ee523a2206206994597c13d831ec7" => "USDT".to_string(),
-        "0x6b175474e89094c44da98b954eedeac495271d0f" => "DAI".to_string(),
-        "0x0000000000085d4780b73119b644ae5ecd22b376" => "TUSD".to_string(),
-        "0x57ab1ec28d129707052df4df418d58a2d46d5f51" => "sUSD".to_string(),
-        "0x4fabb145d64652a948d72533023f6e7a623c7c53" => "BUSD".to_string(),
-        
-        // Major tokens
-        "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2" => "WETH".to_string(),
-        "0x2260fac5e5542a773aa44fbcfedf7c193bc2c599" => "WBTC".to_string(),
-        "0x9f8f72aa9304c8b593d555f12ef6589cc3a579a2" => "MKR".to_string(),
-        "0x1f9840a85d5af5bf1d1762f925bdaddc4201f984" => "UNI".to_string(),
-        "0x514910771af9ca656af840dff83e8264ecf986ca" => "LINK".to_string(),
-        "0x7d1afa7b718fb893db30a3abc0cfc608aacfebb0" => "MATIC".to_string(),
-        "0x0d8775f648430679a709e98d2b0cb6250d2887ef" => "BAT".to_string(),
-        "0xdd974d5c2e2928dea5f71b9825b8b646686bd200" => "KNC".to_string(),
-        "0x6b3595068778dd592e39a122f4f5a5cf09c90fe2" => "SUSHI".to_string(),
-        "0x7fc66500c84a76ad7e9c93437bfc5ac33e2ddae9" => "AAVE".to_string(),
-        "0xc00e94cb662c3520282e6f5717214004a7f26888" => "COMP".to_string(),
-        "0x95ad61b0a150d79219dcf64e1e6cc01f0b64c4ce" => "SHIB".to_string(),
-        "0x6810e776880c02933d47db1b9fc05908e5386b96" => "GNO".to_string(),
-        "0xa0b73e1ff0b80914ab6fe0444e65848c4c34450b" => "CRO".to_string(),
-        
-        // Default: return shortened address
-        _ => {
-            let addr = format!("{:?}", token_address);
-            if addr.len() > 10 {
-                format!("{}...{}", &addr[0..6], &addr[addr.len()-4..])
-            } else {
-                addr
-            }
-        }
+    if cleaned.starts_with('-') {
+        return Err(AggregatorError::ParseError(format!("Amount cannot be negative: {}", amount_str)));
     }
-}
 
-/// Parse token symbol or address to Address
-/// Accepts both symbols (e.g., "WETH", "USDC") and addresses (e.g., "0xc02a...")
-pub fn parse_token(input: &str) -> Result<Address> {
-    // If it starts with 0x, treat as address
-    if input.starts_with("0x") || input.starts_with("0X") {
-        return parse_address(input);
-    }
-    
-    // Otherwise, treat as symbol (case-insensitive)
-    let symbol_upper = input.to_uppercase();
-    let address_str = match symbol_upper.as_str() {
-        // Stablecoins
-        "USDC" => "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
-        "USDT" => "0xdac17f958d2ee523a2206206994597c13d831ec7",
-        "DAI" => "0x6b175474e89094c44da98b954eedeac495271d0f",
-        "TUSD" => "0x0000000000085d4780b73119b644ae5ecd22b376",
-        "SUSD" => "0x57ab1ec28d129707052df4df418d58a2d46d5f51",
-        "BUSD" => "0x4fabb145d64652a948d72533023f6e7a623c7c53",
-        
-        // Major tokens
-        "WETH" | "ETH" => "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
-        "WBTC" | "BTC" => "0x2260fac5e5542a773aa44fbcfedf7c193bc2c599",
-        "MKR" => "0x9f8f72aa9304c8b593d555f12ef6589cc3a579a2",
-        "UNI" => "0x1f9840a85d5af5bf1d1762f925bdaddc4201f984",
-        "LINK" => "0x514910771af9ca656af840dff83e8264ecf986ca",
-        "MATIC" => "0x7d1afa7b718fb893db30a3abc0cfc608aacfebb0",
-        "BAT" => "0x0d8775f648430679a709e98d2b0cb6250d2887ef",
-        "KNC" => "0xdd974d5c2e2928dea5f71b9825b8b646686bd200",
-        "SUSHI" => "0x6b3595068778dd592e39a122f4f5a5cf09c90fe2",
-        "AAVE" => "0x7fc66500c84a76ad7e9c93437bfc5ac33e2ddae9",
-        "COMP" => "0xc00e94cb662c3520282e6f5717214004a7f26888",
-        "SHIB" => "0x95ad61b0a150d79219dcf64e1e6cc01f0b64c4ce",
-        "GNO" => "0x6810e776880c02933d47db1b9fc05908e5386b96",
-        "CRO" => "0xa0b73e1ff0b80914ab6fe0444e65848c4c34450b",
-        
-        _ => return Err(AggregatorError::ParseError(
-            format!("Unknown token symbol: {}. Use full address (0x...) or supported symbol (WETH, USDC, DAI, etc.)", input)
-        )),
+    let (mantissa, exponent) = match cleaned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => {
+            let exponent = exponent
+                .parse::<i64>()
+                .map_err(|_| AggregatorError::ParseError(format!("Invalid exponent in {}", amount_str)))?;
+            (mantissa, exponent)
+        }
+        None => (cleaned.as_str(), 0),
     };
-    
-    parse_address(address_str)
-}
 
-/// Parse a token amount string with decimal support
-/// Examples: "1.0", "0.5", "1000"
-pub fn parse_token_amount(amount_str: &str, decimals: u8) -> Result<U256> {
-    let parts: Vec<&str> = amount_str.split('.').collect();
-    
-    if parts.is_empty() || parts.len() > 2 {
-        return Err(AggregatorError::ParseError(
-            format!("Invalid amount format: {}", amount_str)
-        ));
+    let (integer_part, decimal_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if (integer_part.is_empty() && decimal_part.is_empty()) || !is_digits(integer_part) || !is_digits(decimal_part) {
+        return Err(AggregatorError::ParseError(format!("Invalid amount format: {}", amount_str)));
     }
 
-    let integer_part = parts[0].parse::<u128>()
-        .map_err(|_| AggregatorError::ParseError(format!("Invalid integer part: {}", parts[0])))?;
-
-    let decimal_part = if parts.len() == 2 {
-        let dec_str = parts[1];
-        if dec_str.len() > decimals as usize {
-            return Err(AggregatorError::ParseError(
-                format!("Too many decimal places. Max: {}", decimals)
-            ));
+    // Trailing zeros don't add precision: "1.500" parses like "1.5"
+    let decimal_part = decimal_part.trim_end_matches('0');
+    let digits = format!("{}{}", integer_part, decimal_part);
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(U256::zero());
+    }
+    let value = U256::from_dec_str(digits).map_err(|_| AggregatorError::MathError)?;
+
+    // value * 10^shift is the amount in base units
+    let out_of_range = || AggregatorError::ParseError(format!("Exponent out of range in {}", amount_str));
+    let shift = exponent
+        .checked_add(decimals as i64)
+        .and_then(|shift| shift.checked_sub(decimal_part.len() as i64))
+        .ok_or_else(out_of_range)?;
+
+    if shift >= 0 {
+        if shift > 77 {
+            return Err(AggregatorError::MathError);
         }
-        // Pad with zeros to reach full decimals
-        let padded = format!("{:0<width$}", dec_str, width = decimals as usize);
-        padded.parse::<u128>()
-            .map_err(|_| AggregatorError::ParseError(format!("Invalid decimal part: {}", dec_str)))?
-    } else {
-        0
-    };
-
-    // Calculate: (integer_part * 10^decimals) + decimal_part
-    let multiplier = 10u128.pow(decimals as u32);
-    let total = integer_part
-        .checked_mul(multiplier)
-        .and_then(|v| v.checked_add(decimal_part))
-        .ok_or(AggregatorError::MathError)?;
+        return value
+            .checked_mul(U256::exp10(shift as usize))
+            .ok_or(AggregatorError::MathError);
+    }
 
-    Ok(U256::from(total))
+    // Digits below one base unit are only allowed if they are zeros, e.g. "1000e-21" with 18 decimals
+    let too_precise = || {
+        AggregatorError::ParseError(format!(
+            "Too many decimal places in {} (max {})",
+            amount_str, decimals
+        ))
+    };
+    let places = shift.checked_neg().ok_or_else(out_of_range)?;
+    if places > 77 {
+        return Err(too_precise());
+    }
+    let divisor = U256::exp10(places as usize);
+    if !(value % divisor).is_zero() {
+        return Err(too_precise());
+    }
+    Ok(value / divisor)
 }
 
 /// Format a token amount with decimals for display
@@ -266,7 +184,7 @@ pub fn format_token_amount(amount: U256, decimals: u8) -> String {
         return "0".to_string();
     }
 
-    let divisor = U256::from(10u128.pow(decimals as u32));
+    let divisor = U256::exp10(decimals as usize);
     let integer_part = amount / divisor;
     let remainder = amount % divisor;
 
@@ -309,11 +227,17 @@ pub fn parse_address(addr_str: &str) -> Result<Address> {
         .map_err(|_| AggregatorError::InvalidTokenAddress(addr_str.to_string()))
 }
 
+/// Address of a UniswapV2-style pair, computed offline with CREATE2
+/// The salt is keccak256 of the two token addresses sorted ascending, as in `UniswapV2Library.pairFor`
+pub fn uniswap_v2_pair_address(factory: Address, init_code_hash: H256, token_a: Address, token_b: Address) -> Address {
+    let (token0, token1) = if token_a < token_b { (token_a, token_b) } else { (token_b, token_a) };
+    let salt = keccak256([token0.as_bytes(), token1.as_bytes()].concat());
+    get_create2_address_from_hash(factory, salt, init_code_hash)
+}
+
 /// Convert wei to ether as f64
 pub fn wei_to_ether(wei: U256) -> f64 {
-    let eth_decimals = 18;
-    let divisor = 10f64.powi(eth_decimals);
-    wei.as_u128() as f64 / divisor
+    math::to_units_f64(wei, 18)
 }
 
 /// Convert gwei to wei
@@ -322,16 +246,21 @@ pub fn gwei_to_wei(gwei: u64) -> U256 {
 }
 
 /// Estimate gas cost in USD
-pub fn estimate_gas_cost_usd(gas_used: U256, gas_price_gwei: u64, eth_price_usd: f64) -> f64 {
-    let gas_price_wei = gwei_to_wei(gas_price_gwei);
-    let total_cost_wei = gas_used * gas_price_wei;
-    let total_cost_eth = wei_to_ether(total_cost_wei);
-    total_cost_eth * eth_price_usd
+pub fn estimate_gas_cost_usd(gas_used: U256, gas_price_wei: U256, eth_price_usd: f64) -> Result<f64> {
+    let total_cost_wei = gas_used
+        .checked_mul(gas_price_wei)
+        .ok_or(AggregatorError::MathError)?;
+    Ok(wei_to_ether(total_cost_wei) * eth_price_usd)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+
+    fn any_u256() -> impl Strategy<Value = U256> {
+        any::<[u64; 4]>().prop_map(U256)
+    }
 
     #[test]
     fn test_calculate_uniswap_v2_output() {
@@ -360,6 +289,38 @@ mod tests {
         assert_eq!(amount, U256::from(1_000_000_000u128));
     }
 
+    #[test]
+    fn test_parse_token_amount_extended_forms() {
+        assert_eq!(parse_token_amount("1_000", 6).unwrap(), U256::from(1_000_000_000u64));
+        assert_eq!(parse_token_amount("1e6", 6).unwrap(), U256::from(1_000_000_000_000u64));
+        assert_eq!(parse_token_amount("2.5E-3", 18).unwrap(), U256::from(2_500_000_000_000_000u64));
+        assert_eq!(parse_token_amount("1.500", 1).unwrap(), U256::from(15));
+        assert_eq!(parse_token_amount("1000e-21", 18).unwrap(), U256::one());
+
+        // Above u128
+        assert_eq!(parse_token_amount("1e30", 18).unwrap(), U256::exp10(48));
+        assert!(matches!(parse_token_amount("1e60", 18), Err(AggregatorError::MathError)));
+
+        for (input, message) in [
+            ("", "empty"),
+            ("  ", "empty"),
+            ("-1", "negative"),
+            ("1.1234567", "max 6"),
+            ("1e-7", "max 6"),
+            ("1e", "exponent"),
+            ("abc", "format"),
+            (".", "format"),
+            ("1e9223372036854775807", "out of range"),
+        ] {
+            let err = parse_token_amount(input, 6).unwrap_err().to_string();
+            assert!(err.contains(message), "{:?}: {}", input, err);
+        }
+
+        // Shifts to i64::MIN, which can't be negated
+        let err = parse_token_amount("1e-9223372036854775808", 0).unwrap_err().to_string();
+        assert!(err.contains("out of range"), "{}", err);
+    }
+
     #[test]
     fn test_format_token_amount() {
         let amount = U256::from(1_000_000_000_000_000_000u128);
@@ -378,9 +339,80 @@ mod tests {
         assert_eq!(format_with_commas(1000000.50), "1,000,000.50");
     }
 
+    #[test]
+    fn test_estimate_gas_cost_usd() {
+        // 100k gas at 30 gwei is 0.003 ETH
+        let cost = estimate_gas_cost_usd(U256::from(100_000), gwei_to_wei(30), 2000.0).unwrap();
+        assert!((cost - 6.0).abs() < 1e-9);
+        assert!(matches!(
+            estimate_gas_cost_usd(U256::MAX, gwei_to_wei(30), 2000.0),
+            Err(AggregatorError::MathError)
+        ));
+
+        // Sub-gwei L2 gas prices are not rounded up: 100k gas at 0.01 gwei is 1e-6 ETH
+        let cost = estimate_gas_cost_usd(U256::from(100_000), U256::from(10_000_000u64), 2000.0).unwrap();
+        assert!((cost - 0.002).abs() < 1e-12);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_price_impact_is_bounded(
+            amount_in in any_u256(),
+            reserve_in in any_u256(),
+            amount_out in any_u256(),
+            reserve_out in any_u256(),
+        ) {
+            prop_assert!(calculate_price_impact(amount_in, reserve_in, amount_out, reserve_out) <= 10000);
+        }
+
+        #[test]
+        fn prop_v2_output_never_panics(
+            amount_in in any_u256(),
+            reserve_in in any_u256(),
+            reserve_out in any_u256(),
+            fee_bps in 0u32..10000,
+        ) {
+            if let Ok(amount_out) = calculate_uniswap_v2_output(amount_in, reserve_in, reserve_out, fee_bps) {
+                prop_assert!(amount_out < reserve_out);
+            }
+        }
+
+        #[test]
+        fn prop_parse_round_trips_formatted_amounts(amount in any_u256(), decimals in 0u8..=36) {
+            prop_assert_eq!(parse_token_amount(&format_token_amount(amount, decimals), decimals).unwrap(), amount);
+        }
+
+        #[test]
+        fn prop_wei_to_ether_is_finite(wei in any_u256()) {
+            prop_assert!(wei_to_ether(wei).is_finite());
+        }
+
+        #[test]
+        fn prop_gas_cost_overflows_into_an_error(gas_used in any_u256(), gas_price_wei in any_u256()) {
+            match estimate_gas_cost_usd(gas_used, gas_price_wei, 2000.0) {
+                Ok(cost) => prop_assert!(cost.is_finite() && gas_used.checked_mul(gas_price_wei).is_some()),
+                Err(e) => prop_assert!(matches!(e, AggregatorError::MathError)),
+            }
+        }
+    }
+
     #[test]
     fn test_gwei_to_wei() {
         let wei = gwei_to_wei(30);
         assert_eq!(wei, U256::from(30_000_000_000u64));
     }
+
+    #[test]
+    fn test_uniswap_v2_pair_address() {
+        let factory = parse_address("0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f").unwrap();
+        let init_code_hash =
+            H256::from_str("0x96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f").unwrap();
+        let usdc = parse_address("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let weth = parse_address("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+
+        // Uniswap V2 USDC/WETH, in either token order
+        let pair = parse_address("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc").unwrap();
+        assert_eq!(uniswap_v2_pair_address(factory, init_code_hash, usdc, weth), pair);
+        assert_eq!(uniswap_v2_pair_address(factory, init_code_hash, weth, usdc), pair);
+    }
 }
@@ -0,0 +1,191 @@
+use crate::math;
+use crate::rpc::RpcProvider;
+use crate::swap::UniswapV2Router02;
+use crate::types::{AggregatorError, Result, RouteHop, RouteQuote};
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::debug;
+
+/// On-chain check of one same-DEX segment of a route
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentVerification {
+    /// DEX used by every hop in the segment
+    pub dex_name: String,
+
+    /// Router queried with getAmountsOut
+    pub router: Address,
+
+    /// Token path of the segment
+    pub path: Vec<Address>,
+
+    /// Amounts computed locally, starting with the segment input
+    pub expected_amounts: Vec<U256>,
+
+    /// Amounts returned by the router for the same input
+    pub onchain_amounts: Vec<U256>,
+
+    /// Deviation of the segment output in basis points (positive = router returns more)
+    pub deviation_bps: i64,
+}
+
+/// Result of verifying a quote against on-chain router math
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteVerification {
+    /// Per-segment results, each priced with the locally computed segment input
+    pub segments: Vec<SegmentVerification>,
+
+    /// Output computed locally
+    pub expected_amount_out: U256,
+
+    /// Output of the last segment according to the router
+    pub onchain_amount_out: U256,
+
+    /// Largest absolute segment deviation in basis points
+    pub max_deviation_bps: i64,
+}
+
+impl QuoteVerification {
+    /// Check that every segment is within a tolerance
+    pub fn is_within(&self, tolerance_bps: u32) -> bool {
+        self.max_deviation_bps.unsigned_abs() <= tolerance_bps as u64
+    }
+}
+
+/// Verifies quotes by calling getAmountsOut on each DEX router
+pub struct QuoteVerifier {
+    provider: Arc<RpcProvider>,
+    routers: HashMap<String, Address>,
+}
+
+impl QuoteVerifier {
+    /// Create a verifier from (dex_name, router) pairs
+    pub fn new(provider: Arc<RpcProvider>, routers: Vec<(String, Address)>) -> Self {
+        Self {
+            provider,
+            routers: routers
+                .into_iter()
+                .map(|(name, router)| (name.to_lowercase(), router))
+                .collect(),
+        }
+    }
+
+    /// Call getAmountsOut for every same-DEX segment of the quote and compare
+    pub async fn verify(&self, quote: &RouteQuote) -> Result<QuoteVerification> {
+        let mut segments = Vec::new();
+
+        for hops in quote.dex_segments() {
+            segments.push(self.verify_segment(hops).await?);
+        }
+
+        let onchain_amount_out = segments
+            .last()
+            .and_then(|s| s.onchain_amounts.last().copied())
+            .unwrap_or_default();
+        let max_deviation_bps = segments
+            .iter()
+            .map(|s| s.deviation_bps)
+            .max_by_key(|d| d.unsigned_abs())
+            .unwrap_or(0);
+
+        Ok(QuoteVerification {
+            segments,
+            expected_amount_out: quote.amount_out,
+            onchain_amount_out,
+            max_deviation_bps,
+        })
+    }
+
+    async fn verify_segment(&self, hops: &[RouteHop]) -> Result<SegmentVerification> {
+        let dex_name = hops[0].dex_name.clone();
+        let router_address = self
+            .routers
+            .get(&dex_name.to_lowercase())
+            .copied()
+            .ok_or_else(|| AggregatorError::ConfigError(format!("No router configured for {}", dex_name)))?;
+
+        let mut path = vec![hops[0].token_in];
+        path.extend(hops.iter().map(|hop| hop.token_out));
+
+        let mut expected_amounts = vec![hops[0].amount_in];
+        expected_amounts.extend(hops.iter().map(|hop| hop.amount_out));
+
+        let router = UniswapV2Router02::new(router_address, self.provider.clone());
+        let onchain_amounts = router
+            .get_amounts_out(hops[0].amount_in, path.clone())
+            .call()
+            .await
+            .map_err(|e| AggregatorError::ContractError(format!("getAmountsOut failed on {}: {}", dex_name, e)))?;
+
+        let expected = *expected_amounts.last().unwrap();
+        let onchain = onchain_amounts.last().copied().unwrap_or_default();
+        let deviation_bps = deviation_bps(expected, onchain);
+
+        debug!(
+            "Segment via {}: expected {} got {} ({} bps)",
+            dex_name, expected, onchain, deviation_bps
+        );
+
+        Ok(SegmentVerification {
+            dex_name,
+            router: router_address,
+            path,
+            expected_amounts,
+            onchain_amounts,
+            deviation_bps,
+        })
+    }
+}
+
+/// Signed deviation of `actual` from `expected` in basis points
+pub(crate) fn deviation_bps(expected: U256, actual: U256) -> i64 {
+    if expected.is_zero() {
+        return if actual.is_zero() { 0 } else { 10000 };
+    }
+
+    let expected = math::to_f64(expected);
+    let actual = math::to_f64(actual);
+    ((actual - expected) / expected * 10000.0).round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deviation_bps() {
+        assert_eq!(deviation_bps(U256::from(10000), U256::from(10000)), 0);
+        assert_eq!(deviation_bps(U256::from(10000), U256::from(9970)), -30);
+        assert_eq!(deviation_bps(U256::from(10000), U256::from(10100)), 100);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil on PATH and FORK_URL pointing at a mainnet RPC"]
+    async fn test_verify_against_forked_node() {
+        use crate::{Aggregator, Config, OptimizationStrategy};
+        use ethers::utils::Anvil;
+
+        let fork_url = std::env::var("FORK_URL").expect("FORK_URL not set");
+        let anvil = Anvil::new().fork(fork_url).spawn();
+        let config = Config {
+            rpc_url: anvil.endpoint(),
+            cache_path: "./does-not-exist.json".to_string(),
+            ..Config::default()
+        };
+        let aggregator = Aggregator::new(config).await.unwrap();
+
+        let weth = aggregator.tokens().resolve("WETH").unwrap();
+        let usdc = aggregator.tokens().resolve("USDC").unwrap();
+        let pair: Address = "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc".parse().unwrap();
+        aggregator.fetch_pool(pair, "Uniswap".to_string()).await.unwrap();
+
+        let quote = aggregator
+            .get_best_quote(weth, usdc, U256::exp10(18), OptimizationStrategy::Price)
+            .unwrap();
+        let verification = aggregator.verify_quote(&quote).await.unwrap();
+
+        assert_eq!(verification.segments.len(), 1);
+        assert!(verification.is_within(1));
+    }
+}
@@ -1,6 +1,7 @@
 use rust_aggregator::{
-    quote::QuoteEngine, types::PoolInfo, utils,
+    quote::QuoteEngine, types::{PoolInfo, TokenInfo}, utils, TokenRegistry,
 };
+use rust_decimal::Decimal;
 use ethers::types::{Address, U256};
 
 fn create_test_pool(reserve0: u128, reserve1: u128) -> PoolInfo {
@@ -13,6 +14,8 @@ fn create_test_pool(reserve0: u128, reserve1: u128) -> PoolInfo {
         fee_bps: 30,
         dex_name: "TestDEX".to_string(),
         last_updated: 0,
+        block_hash: None,
+        liquidity_usd: None,
     }
 }
 
@@ -39,8 +42,8 @@ fn test_uniswap_v2_formula() {
 fn test_price_impact_calculation() {
     // Small trade should have low price impact
     let pool = create_test_pool(
-        1000_000_000_000_000_000_000,  // 1000 ETH
-        2000_000_000_000_000_000_000,  // 2000 ETH
+        1_000_000_000_000_000_000_000,  // 1000 ETH
+        2_000_000_000_000_000_000_000,  // 2000 ETH
     );
 
     let small_amount = U256::from(1_000_000_000_000_000_000u128); // 1 ETH
@@ -176,7 +179,17 @@ fn test_exchange_rate_calculation() {
     assert!(result.is_ok());
     let quote = result.unwrap();
     
-    // Exchange rate should be approximately 1800 (accounting for fees and slippage)
-    let rate = quote.exchange_rate();
-    assert!(rate > 0.0);
+    let tokens = TokenRegistry::new(1);
+    for (address, symbol, decimals) in [(pool.token0, "ETH", 18), (pool.token1, "USDC", 6)] {
+        tokens.insert(TokenInfo { address, symbol: symbol.to_string(), name: symbol.to_string(), decimals });
+    }
+
+    // Spot is exactly 1800; execution is a little less after fees and slippage
+    let rate = quote.execution_price(&tokens).unwrap();
+    assert_eq!(quote.spot_price(&tokens).unwrap(), Decimal::from(1800));
+    assert!(rate < Decimal::from(1800) && rate > Decimal::from(1770));
+
+    // The inverse is ETH paid per USDC
+    let inverse = quote.inverse_price(&tokens).unwrap();
+    assert!((inverse * rate - Decimal::ONE).abs() < Decimal::new(1, 20));
 }